@@ -47,6 +47,12 @@
         commonArgs = {
           src = ./.;
           strictDeps = true;
+          nativeBuildInputs = [ pkgs.pkg-config ];
+          # rodio's audio output and keyring's secret service
+          buildInputs = [
+            pkgs.alsa-lib
+            pkgs.dbus
+          ];
           cargoArtifacts = craneLib.buildDepsOnly commonArgs;
         };
 
@@ -83,6 +89,9 @@
         devShells.default = pkgs.mkShell {
           packages = [
             pkgs.fenix.stable.toolchain
+            pkgs.pkg-config
+            pkgs.alsa-lib
+            pkgs.dbus
           ];
 
           LD_LIBRARY_PATH = ld_library_path;
@@ -1,30 +1,53 @@
-use std::{collections::VecDeque, ops::RangeInclusive, sync::Arc};
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    ops::RangeInclusive,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local, Utc};
 
 use futures::future::BoxFuture;
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use iced::{
     Alignment, Border, Color, Element, Length, Padding, Task,
     advanced::widget,
-    alignment, mouse,
+    alignment, keyboard, mouse,
     widget::{
-        Container, Row, Text, button, column, container, lazy, mouse_area, row, rule, space,
+        Column, Container, Row, Text, button, column, container, lazy, mouse_area, opaque, row,
+        rule, space,
         text::{Rich, Span},
-        text_input,
+        text_input, tooltip,
     },
+    window,
 };
+use itertools::Itertools;
 use palette::{FromColor, IntoColor};
-use twixel_core::irc_message::{AnySemantic, PrivMsg, tags::OwnedTag};
+use twixel_core::{
+    IrcMessage,
+    irc_message::{
+        AnySemantic, PrivMsg, SemanticIrcMessage, clearchat::TimeoutDuration, tags::OwnedTag,
+    },
+};
 
 use crate::{
-    IMAGE_GENERATION,
-    config::CONFIG,
+    ConnectionState, IMAGE_GENERATION,
+    chat_log::{LogEntry, json_log},
+    cli::ARGS,
+    config::{
+        BlockedTerm, CONFIG, Config, DeletedMessages, EmoteProviders, SETTINGS_GENERATION, UiConfig,
+    },
     platform::{
-        ChannelEmote,
-        twitch::{self, badges::BADGE_CACHE},
+        CdnSize, ChannelEmote, EmoteFlags, EmotePlatform, pronouns,
+        twitch::{self, badges::BADGE_CACHE, cheermotes::Cheer},
     },
     widget::{
         animated::AnimatedImage,
+        context_menu::context_menu,
+        overlaid::Overlaid,
         scrollie::{ScrollViewport, scrollie},
+        splitter::Splitter,
     },
 };
 
@@ -32,15 +55,350 @@ use crate::{
 pub struct Chat {
     pub channel: String,
     scroll_id: widget::Id,
-    pub messages: VecDeque<(Arc<PrivMsg>, u64)>,
+    pub input_id: widget::Id,
+    /// Messages with their key and when they were sent
+    pub messages: VecDeque<(Arc<PrivMsg>, u64, DateTime<Utc>)>,
+    /// System messages with their key and when they happened, shown between
+    /// the chat messages
+    system: VecDeque<(SystemLine, u64, DateTime<Utc>)>,
     pub message: String,
+    /// Messages sent in this channel, oldest first
+    sent_history: Vec<String>,
+    /// Position in [Self::sent_history] while browsing it with the arrow keys
+    history_cursor: Option<usize>,
+    /// What was typed before browsing the history started
+    draft: String,
+    /// Earlier states of the input, for undoing edits
+    edits: EditHistory,
+    /// Shown above the input until it's edited again
+    input_warning: Option<&'static str>,
+    /// Whether Shift is held, which swaps what Enter does in the input
+    shift_held: bool,
     pub usercard: Option<String>,
+    /// The emote whose details are shown
+    emote_info: Option<ChannelEmote>,
+    /// Logins of users whose messages aren't shown in this channel
+    hidden_users: HashSet<String>,
+    /// Keys of messages from [Self::hidden_users], checked as they're added
+    /// and again when a user is hidden or shown
+    hidden: HashSet<u64>,
+    /// Keys of blocked or deleted messages the user chose to reveal
+    revealed: HashSet<u64>,
+    /// Keys of messages removed by moderators
+    deleted: HashSet<u64>,
+    /// Keys of messages that mention one of the accounts or contain a
+    /// highlight term, checked as they're added
+    highlighted: HashSet<u64>,
+    /// Keys of messages containing a blocked term, checked as they're added
+    /// and again when the settings change
+    blocked: HashSet<u64>,
+    /// Key of the message whose text is shown in a selectable field
+    selecting: Option<u64>,
+    selection_id: widget::Id,
+    /// The user moderates this channel, from its USERSTATE
+    pub is_mod: bool,
+    /// The user is a VIP in this channel, from its USERSTATE
+    pub is_vip: bool,
+    /// Seconds users have to wait between messages, 0 when slow mode is off.
+    /// From the channel's ROOMSTATE
+    pub slow_mode: u64,
+    /// When the user can send again after their last message in slow mode
+    cooldown_until: Option<Instant>,
+    /// How many times the font size was zoomed, it's saved once zooming
+    /// stops for [Self::ZOOM_SAVE_DELAY]
+    zooms: u64,
+    /// The last message sent as it went to Twitch, with when it was sent
+    last_sent: Option<(String, Instant)>,
+    /// How many times a message was repeated in a row, by its key
+    repeats: HashMap<u64, usize>,
+    /// Names of who chatted recently, most recent first, as they're
+    /// completed after `@`
+    recent_chatters: VecDeque<String>,
 
     emote_sets_loaded: bool,
     emote_generation: u64,
     pub emotes: HashMap<String, ChannelEmote>,
+    /// Emotes usable in every channel, [Self::emotes] take precedence over
+    /// them
+    global_emotes: Arc<HashMap<String, ChannelEmote>>,
 
+    /// Messages received while another channel was selected
+    pub unread: usize,
+    /// The window the channel was popped out into, it's shown in the main
+    /// window's tabs otherwise
+    pub window: Option<window::Id>,
+    /// Whether the channel is streaming, [None] until it's first polled
+    pub live: Option<bool>,
+    /// How often each emote was used in this channel, by its name
+    emote_stats: HashMap<String, EmoteStat>,
+    /// A request for older messages is in flight
+    pub loading_older: bool,
+    /// Why the channel's history couldn't be loaded
+    pub history_error: Option<String>,
+    /// The history API had nothing older than the first message
+    history_exhausted: bool,
+    /// Whether an odd number of messages were dropped from or loaded into the
+    /// front of the scrollback, keeps alternating rows stable
+    front_parity: bool,
+    /// Platforms whose emote sets couldn't be loaded for this channel
+    pub failed_emotes: Vec<EmotePlatform>,
+    /// A manual reload of the emote sets is in progress
+    pub reloading_emotes: bool,
+    show_emote_stats: bool,
+    show_emote_picker: bool,
+    /// Filters the emotes listed in the picker
+    emote_search: String,
     show_scroll_to_bottom: bool,
+    /// Messages received since the user scrolled away from the bottom
+    unseen_since_scroll: usize,
+}
+
+/// What a system message is about, each kind can be hidden separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemKind {
+    JoinPart,
+    /// Timeouts, bans and cleared chats
+    ClearChat,
+    Connection,
+    /// Notices from Twitch and changes to the chat's settings
+    Notice,
+}
+
+/// A line in chat from Twitch or the client itself rather than a chatter
+#[derive(Debug, Clone)]
+pub struct SystemLine {
+    pub kind: SystemKind,
+    pub text: String,
+}
+
+impl SystemLine {
+    pub fn notice(text: String) -> Self {
+        Self {
+            kind: SystemKind::Notice,
+            text,
+        }
+    }
+
+    pub fn joined(channel: &str) -> Self {
+        Self {
+            kind: SystemKind::JoinPart,
+            text: format!("Joined #{channel}"),
+        }
+    }
+
+    /// `login` was timed out for `duration` or banned without one, without a
+    /// `login` the whole chat was cleared
+    pub fn cleared(login: Option<&str>, duration: Option<Duration>) -> Self {
+        let text = match (login, duration) {
+            (None, _) => "Chat was cleared by a moderator".to_owned(),
+            (Some(login), None) => format!("{login} was permanently banned"),
+            (Some(login), Some(d)) => format!("{login} was timed out for {}s", d.as_secs()),
+        };
+        Self {
+            kind: SystemKind::ClearChat,
+            text,
+        }
+    }
+}
+
+/// Settings that affect how each message line is rendered
+#[derive(Debug, Clone, Copy)]
+struct LineStyle {
+    font_size: f32,
+    emote_scale: f32,
+    providers: EmoteProviders,
+    compact: bool,
+    /// Theme background that usernames need to stand out from
+    background: Color,
+    min_contrast: f32,
+    pronouns: bool,
+}
+
+impl LineStyle {
+    fn new(cfg: &UiConfig, background: Color) -> Self {
+        Self {
+            font_size: cfg.chat_font_size,
+            emote_scale: cfg.emote_scale(),
+            providers: cfg.emote_providers,
+            compact: cfg.compact,
+            background,
+            min_contrast: cfg.min_username_contrast,
+            pronouns: cfg.show_pronouns,
+        }
+    }
+}
+
+/// What a message line shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum LineKind {
+    Message,
+    /// Contains a blocked term, collapsed until revealed
+    Blocked,
+    /// Removed by a moderator, collapsed until revealed
+    Deleted,
+    /// Removed by a moderator, shown struck through
+    Struck,
+    /// Shown as plain text that can be selected and copied
+    Selecting,
+}
+
+/// Where a line sits in a run of consecutive messages from the same user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Group {
+    /// Shows the name and badges
+    first: bool,
+    /// Followed by a separator
+    last: bool,
+}
+
+impl Group {
+    const SOLO: Self = Self {
+        first: true,
+        last: true,
+    };
+}
+
+/// Longest pause between two messages of the same group, in seconds
+const GROUP_GAP_SECS: i64 = 5 * 60;
+
+/// Whether the message line `next` continues the group of `prev`. Groups
+/// break after a pause and around lines that aren't regular messages
+fn same_group(
+    prev: (&PrivMsg, DateTime<Utc>, LineKind),
+    next: (&PrivMsg, DateTime<Utc>, LineKind),
+) -> bool {
+    let is_plain = |(m, _, kind): (&PrivMsg, _, LineKind)| {
+        matches!(kind, LineKind::Message | LineKind::Struck)
+            && !m.is_me()
+            && AnnouncementColor::of(m).is_none()
+    };
+    let login = |m: &PrivMsg| m.get_username().map(|u| u.to_string());
+    is_plain(prev)
+        && is_plain(next)
+        && login(prev.0) == login(next.0)
+        && (next.1 - prev.1).num_seconds() < GROUP_GAP_SECS
+}
+
+/// Background of a message line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Tint {
+    None,
+    /// Every other line when alternating rows are enabled
+    Alternate,
+    /// The message mentions the user or a highlight term
+    Highlight,
+    Announcement(AnnouncementColor),
+}
+
+/// Accent of an announcement, from its `msg-param-color`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnnouncementColor {
+    /// The channel's accent color, shown in the theme's primary color
+    Primary,
+    Blue,
+    Green,
+    Orange,
+    Purple,
+}
+
+impl AnnouncementColor {
+    /// Parses the color from its tag or `/announce` suffix, ignoring case
+    fn parse(s: &str) -> Option<Self> {
+        [
+            ("primary", Self::Primary),
+            ("blue", Self::Blue),
+            ("green", Self::Green),
+            ("orange", Self::Orange),
+            ("purple", Self::Purple),
+        ]
+        .into_iter()
+        .find_map(|(name, c)| name.eq_ignore_ascii_case(s).then_some(c))
+    }
+
+    /// The name Helix takes the color by
+    pub fn api_name(self) -> &'static str {
+        match self {
+            Self::Primary => "primary",
+            Self::Blue => "blue",
+            Self::Green => "green",
+            Self::Orange => "orange",
+            Self::Purple => "purple",
+        }
+    }
+
+    /// The color of `msg` if it's an announcement
+    fn of(msg: &PrivMsg) -> Option<Self> {
+        msg.get_tag(OwnedTag::MsgId)
+            .filter(|id| id == "announcement")
+            .map(|_| {
+                msg.get_tag(OwnedTag::MsgParamColor)
+                    .and_then(|c| Self::parse(&c))
+                    .unwrap_or(Self::Primary)
+            })
+    }
+
+    fn color(self, theme: &iced::Theme) -> Color {
+        match self {
+            Self::Primary => theme.extended_palette().primary.base.color,
+            Self::Blue => Color::from_rgb8(0x00, 0xd6, 0xd6),
+            Self::Green => Color::from_rgb8(0x00, 0xdb, 0x84),
+            Self::Orange => Color::from_rgb8(0xff, 0xb3, 0x1a),
+            Self::Purple => Color::from_rgb8(0x91, 0x46, 0xff),
+        }
+    }
+}
+
+/// Parses an `/announce` command, optionally followed by a color like
+/// `/announceblue`. [None] if `text` isn't one
+pub fn parse_announce(text: &str) -> Option<Result<(AnnouncementColor, &str), &'static str>> {
+    let (command, rest) = text.split_once(' ').unwrap_or((text, ""));
+    let color = command.strip_prefix("/announce")?;
+    let color = match color {
+        "" => AnnouncementColor::Primary,
+        c => match AnnouncementColor::parse(c) {
+            Some(c) => c,
+            None => {
+                return Some(Err(
+                    "Unknown announcement color, use blue, green, orange or purple",
+                ));
+            }
+        },
+    };
+    match rest.trim() {
+        "" => Some(Err("Announcements need a message")),
+        rest => Some(Ok((color, rest))),
+    }
+}
+
+/// Rewrites an announcement USERNOTICE as a PRIVMSG from its sender, keeping
+/// its tags, so it can be shown in the scrollback like any other message
+fn announcement_line(raw: &str) -> Option<String> {
+    let (tags, rest) = raw.trim_end().strip_prefix('@')?.split_once(' ')?;
+    let mut tag_list = tags.split(';');
+    if !tag_list.clone().any(|t| t == "msg-id=announcement") {
+        return None;
+    }
+    let login = tag_list.find_map(|t| t.strip_prefix("login="))?;
+    let params = rest.strip_prefix(":tmi.twitch.tv USERNOTICE ")?;
+    // announcements always have text, it's after the channel
+    params.split_once(" :")?;
+    Some(format!(
+        "@{tags} :{login}!{login}@{login}.tmi.twitch.tv PRIVMSG {params}"
+    ))
+}
+
+/// The announcement in `msg` as a message from its sender, see
+/// [announcement_line]
+pub fn announcement(msg: &IrcMessage) -> Option<PrivMsg> {
+    let line = announcement_line(msg.inner())?;
+    PrivMsg::from_message(IrcMessage::new(line).ok()?).ok()
+}
+
+#[derive(Debug, Clone)]
+struct EmoteStat {
+    count: usize,
+    /// Id of the emote for native Twitch emotes
+    twitch_id: Option<String>,
 }
 
 #[allow(clippy::enum_variant_names)]
@@ -48,10 +406,65 @@ pub struct Chat {
 pub enum Message {
     SendMessage,
     MessageChange(String),
+    /// The input's new value after something was pasted into it
+    Pasted(String),
+    /// Recalls the previously sent message
+    HistoryPrevious,
+    /// Recalls the next sent message, or the draft past the newest one
+    HistoryNext,
+    /// Restores the input from before the last edit
+    Undo,
+    Redo,
+    /// Completes the `@username` being typed with the first suggestion
+    CompleteMention,
+    MentionPicked(String),
     CloseUserCard,
     ShowUserCard(String),
+    /// Shows the details of an emote that was clicked
+    ShowEmoteInfo(ChannelEmote),
+    CloseEmoteInfo,
+    ToggleEmoteStats,
+    ToggleEmotePicker,
+    EmoteSearchChange(String),
+    /// Appends the emote with the given name to the input
+    InsertEmote(String),
+    /// Reload the channel's emote sets, handled by the parent
+    ReloadEmotes,
+    /// Fetch messages older than the scrollback, handled by the parent
+    LoadOlderMessages,
+    /// Load the channel's history again after it failed, handled by the parent
+    RetryHistory,
+    ToggleHideUser(String),
+    RevealMessage(u64),
+    /// Shows the text of the message with the given key in a selectable field
+    SelectMessage(u64),
+    /// The selectable field was typed into, it's read-only so it's ignored
+    SelectionEdited,
+    StopSelecting,
     ScrollToBottom,
+    /// Scrolls to the message with the given key
+    JumpTo(u64),
+    /// Drops the locally buffered messages without leaving the channel
+    ClearLocal,
+    /// Saves the buffered messages to a text or JSON file the user picks
+    ExportLog,
+    CopyToClipboard(String),
+    /// Opens the URL in the default browser
+    OpenUrl(String),
+    /// Moves the channel to its own window or back into the main window's
+    /// tabs, handled by the parent
+    TogglePopOut,
+    /// The divider above the input was dragged to the given ratio
+    InputResized(f32),
+    InputResizeEnded,
     ChatScrolled(ScrollViewport),
+    /// Ctrl was held while scrolling by the given steps, changes the font size
+    Zoom(i32),
+    /// Saves the font size if it's still the given zoom, see [Chat::zooms]
+    ZoomEnded(u64),
+    /// Updates the slow mode countdown
+    CooldownTick,
+    ModifiersChanged(keyboard::Modifiers),
     #[debug("Box<dyn CloneFn + Send>")]
     LoadImage(Box<dyn CloneFn + Send>),
     EmoteSetsLoaded,
@@ -63,10 +476,43 @@ impl Clone for Message {
         match self {
             Self::SendMessage => Self::SendMessage,
             Self::MessageChange(arg0) => Self::MessageChange(arg0.clone()),
+            Self::Pasted(arg0) => Self::Pasted(arg0.clone()),
+            Self::HistoryPrevious => Self::HistoryPrevious,
+            Self::HistoryNext => Self::HistoryNext,
+            Self::Undo => Self::Undo,
+            Self::Redo => Self::Redo,
+            Self::CompleteMention => Self::CompleteMention,
+            Self::MentionPicked(arg0) => Self::MentionPicked(arg0.clone()),
             Self::CloseUserCard => Self::CloseUserCard,
             Self::ShowUserCard(arg0) => Self::ShowUserCard(arg0.clone()),
+            Self::ShowEmoteInfo(arg0) => Self::ShowEmoteInfo(arg0.clone()),
+            Self::CloseEmoteInfo => Self::CloseEmoteInfo,
+            Self::ToggleEmoteStats => Self::ToggleEmoteStats,
+            Self::ToggleEmotePicker => Self::ToggleEmotePicker,
+            Self::EmoteSearchChange(arg0) => Self::EmoteSearchChange(arg0.clone()),
+            Self::InsertEmote(arg0) => Self::InsertEmote(arg0.clone()),
+            Self::ReloadEmotes => Self::ReloadEmotes,
+            Self::LoadOlderMessages => Self::LoadOlderMessages,
+            Self::RetryHistory => Self::RetryHistory,
+            Self::ToggleHideUser(arg0) => Self::ToggleHideUser(arg0.clone()),
+            Self::RevealMessage(arg0) => Self::RevealMessage(*arg0),
+            Self::SelectMessage(arg0) => Self::SelectMessage(*arg0),
+            Self::SelectionEdited => Self::SelectionEdited,
+            Self::StopSelecting => Self::StopSelecting,
             Self::ScrollToBottom => Self::ScrollToBottom,
+            Self::JumpTo(arg0) => Self::JumpTo(*arg0),
+            Self::ClearLocal => Self::ClearLocal,
+            Self::ExportLog => Self::ExportLog,
+            Self::CopyToClipboard(arg0) => Self::CopyToClipboard(arg0.clone()),
+            Self::OpenUrl(arg0) => Self::OpenUrl(arg0.clone()),
+            Self::TogglePopOut => Self::TogglePopOut,
+            Self::InputResized(arg0) => Self::InputResized(*arg0),
+            Self::InputResizeEnded => Self::InputResizeEnded,
             Self::ChatScrolled(arg0) => Self::ChatScrolled(arg0.clone()),
+            Self::Zoom(arg0) => Self::Zoom(*arg0),
+            Self::ZoomEnded(arg0) => Self::ZoomEnded(*arg0),
+            Self::CooldownTick => Self::CooldownTick,
+            Self::ModifiersChanged(arg0) => Self::ModifiersChanged(*arg0),
             Self::LoadImage(arg0) => Self::LoadImage(arg0.clone_boxed()),
             Self::EmoteSetsLoaded => Self::EmoteSetsLoaded,
             Self::EmoteLoaded => Self::EmoteLoaded,
@@ -85,93 +531,893 @@ impl<T: Fn() -> Task<Message> + Clone + Send + 'static> CloneFn for T {
 }
 
 impl Chat {
-    pub fn new(channel: String) -> Self {
+    const MAX_SENT_HISTORY: usize = 100;
+    pub const MAX_SCROLLBACK: usize = 500;
+    const MAX_SYSTEM_LINES: usize = 100;
+    const MAX_RECENT_CHATTERS: usize = 200;
+    const ZOOM_SAVE_DELAY: Duration = Duration::from_millis(500);
+    /// Most usernames suggested at once while typing a mention
+    const MAX_SUGGESTIONS: usize = 5;
+
+    pub fn new(channel: String, global_emotes: Arc<HashMap<String, ChannelEmote>>) -> Self {
+        let hidden_users = CONFIG
+            .read()
+            .hidden_users
+            .get(&channel)
+            .map(|u| u.iter().cloned().collect())
+            .unwrap_or_default();
         Self {
             channel,
             scroll_id: widget::Id::unique(),
+            input_id: widget::Id::unique(),
             messages: Default::default(),
+            system: Default::default(),
             message: Default::default(),
+            sent_history: Default::default(),
+            history_cursor: None,
+            draft: Default::default(),
+            edits: Default::default(),
+            input_warning: None,
+            shift_held: false,
             usercard: Default::default(),
+            emote_info: None,
+            hidden_users,
+            hidden: Default::default(),
+            revealed: Default::default(),
+            deleted: Default::default(),
+            highlighted: Default::default(),
+            blocked: Default::default(),
+            selecting: None,
+            selection_id: widget::Id::unique(),
+            is_mod: false,
+            is_vip: false,
+            slow_mode: 0,
+            cooldown_until: None,
+            zooms: 0,
+            last_sent: None,
+            repeats: Default::default(),
+            recent_chatters: Default::default(),
 
             emote_sets_loaded: false,
             emote_generation: 0,
             emotes: Default::default(),
+            global_emotes,
 
+            unread: 0,
+            window: None,
+            live: None,
+            emote_stats: Default::default(),
+            show_emote_stats: false,
+            show_emote_picker: false,
+            emote_search: String::new(),
+            failed_emotes: Vec::new(),
+            front_parity: false,
+            loading_older: false,
+            history_error: None,
+            history_exhausted: false,
+            reloading_emotes: false,
             show_scroll_to_bottom: false,
+            unseen_since_scroll: 0,
+        }
+    }
+
+    /// Appends a newly received message, dropping the oldest ones past the
+    /// scrollback limit. `highlighted` is [Config::is_highlighted] of its text
+    pub fn push_message(&mut self, msg: Arc<PrivMsg>, key: u64, highlighted: bool) {
+        self.count_emotes(&msg);
+        if let Some(name) = mention_name(&msg) {
+            self.recent_chatters
+                .retain(|n| !n.eq_ignore_ascii_case(&name));
+            self.recent_chatters.push_front(name);
+            self.recent_chatters.truncate(Self::MAX_RECENT_CHATTERS);
+        }
+
+        let (combine, blocked) = {
+            let cfg = CONFIG.read();
+            let blocked = is_blocked(msg.message_text(), &cfg.blocked_terms);
+            (cfg.ui.combine_duplicates, blocked)
+        };
+        let hidden = self.is_hidden(&msg);
+        match push_live(&mut self.messages, msg, key, Self::MAX_SCROLLBACK, combine) {
+            Pushed::Repeat(last_key) => *self.repeats.entry(last_key).or_insert(1) += 1,
+            Pushed::Added(dropped) => {
+                if highlighted {
+                    self.highlighted.insert(key);
+                }
+                if blocked {
+                    self.blocked.insert(key);
+                }
+                if hidden {
+                    self.hidden.insert(key);
+                }
+                self.forget_trimmed(dropped);
+            }
+        }
+        // combined ones are still new messages
+        if self.show_scroll_to_bottom {
+            self.unseen_since_scroll += 1;
+        }
+    }
+
+    /// Adds a system message, unless its kind is hidden. Hidden ones aren't
+    /// kept either
+    pub fn push_system(&mut self, line: SystemLine, key: u64) {
+        if !CONFIG.read().ui.system_messages.is_shown(line.kind) {
+            return;
+        }
+        if self.system.len() >= Self::MAX_SYSTEM_LINES {
+            self.system.pop_front();
+        }
+        self.system.push_back((line, key, Utc::now()));
+    }
+
+    /// Merges the messages sent before the channel was joined into the
+    /// scrollback, see [merge_history]
+    pub fn merge_history(&mut self, history: Vec<PrivMsg>, mut next_key: impl FnMut() -> u64) {
+        // older than the live messages, so they come after who chatted live
+        for name in history.iter().rev().filter_map(mention_name) {
+            if self.recent_chatters.len() >= Self::MAX_RECENT_CHATTERS {
+                break;
+            }
+            if !self
+                .recent_chatters
+                .iter()
+                .any(|n| n.eq_ignore_ascii_case(&name))
+            {
+                self.recent_chatters.push_back(name);
+            }
+        }
+        let mut added = HashSet::new();
+        let next_key = || {
+            let key = next_key();
+            added.insert(key);
+            key
+        };
+        let dropped = merge_history(&mut self.messages, history, next_key, Self::MAX_SCROLLBACK);
+        self.forget_trimmed(dropped);
+        let cfg = CONFIG.read();
+        for (m, key, _) in self
+            .messages
+            .iter()
+            .filter(|(_, key, _)| added.contains(key))
+        {
+            if cfg.is_highlighted(m.message_text()) {
+                self.highlighted.insert(*key);
+            }
+            if is_blocked(m.message_text(), &cfg.blocked_terms) {
+                self.blocked.insert(*key);
+            }
+            if from_hidden_user(m, &self.hidden_users) {
+                self.hidden.insert(*key);
+            }
+        }
+    }
+
+    /// Checks which messages are highlighted again, after what highlights
+    /// them changed
+    pub fn update_highlights(&mut self) {
+        let cfg = CONFIG.read();
+        self.highlighted = self
+            .messages
+            .iter()
+            .filter(|(m, ..)| cfg.is_highlighted(m.message_text()))
+            .map(|(_, key, _)| *key)
+            .collect();
+    }
+
+    /// Checks which messages contain a blocked term again, after the
+    /// settings changed
+    pub fn update_blocked(&mut self) {
+        let cfg = CONFIG.read();
+        self.blocked = self
+            .messages
+            .iter()
+            .filter(|(m, ..)| is_blocked(m.message_text(), &cfg.blocked_terms))
+            .map(|(_, key, _)| *key)
+            .collect();
+    }
+
+    /// Forgets the state of messages that were dropped from the front of the
+    /// scrollback
+    fn forget_trimmed(&mut self, keys: Vec<u64>) {
+        for key in keys {
+            self.front_parity = !self.front_parity;
+            self.repeats.remove(&key);
+            self.revealed.remove(&key);
+            self.deleted.remove(&key);
+            self.highlighted.remove(&key);
+            self.blocked.remove(&key);
+            self.hidden.remove(&key);
+        }
+    }
+
+    /// Replaces the global emotes after another of their sets loaded
+    pub fn set_global_emotes(&mut self, global_emotes: Arc<HashMap<String, ChannelEmote>>) {
+        self.global_emotes = global_emotes;
+        self.emote_generation += 1;
+    }
+
+    /// The channel or global emote called `name`
+    fn emote(&self, name: &str) -> Option<&ChannelEmote> {
+        self.emotes
+            .get(name)
+            .or_else(|| self.global_emotes.get(name))
+    }
+
+    /// Marks the message with the given IRC `id` as deleted, after a
+    /// CLEARMSG
+    pub fn delete_message(&mut self, id: &str) {
+        if let Some((_, key, _)) = self
+            .messages
+            .iter()
+            .find(|(m, ..)| m.get_tag(OwnedTag::Id).is_some_and(|i| i == id))
+        {
+            self.deleted.insert(*key);
+        }
+    }
+
+    /// Marks every message of `login` as deleted after they were timed out
+    /// or banned, or every message at all if the chat was cleared
+    pub fn delete_messages_of(&mut self, login: Option<&str>) {
+        let keys = self
+            .messages
+            .iter()
+            .filter(|(m, ..)| {
+                login.is_none_or(|l| m.get_username().is_some_and(|u| u.to_string() == l))
+            })
+            .map(|(_, key, _)| *key);
+        self.deleted.extend(keys);
+    }
+
+    /// Inserts messages from before the start of the scrollback, as long as
+    /// there's room left for them
+    pub fn prepend_messages(
+        &mut self,
+        msgs: impl Iterator<Item = (Arc<PrivMsg>, u64, DateTime<Utc>)>,
+    ) {
+        self.loading_older = false;
+        let Some(oldest) = self.messages.front().map(|m| m.2) else {
+            return;
+        };
+        let older = msgs.filter(|m| m.2 < oldest).collect_vec();
+        if older.is_empty() {
+            self.history_exhausted = true;
+            return;
+        }
+
+        let room = Self::MAX_SCROLLBACK.saturating_sub(self.messages.len());
+        let added = older.len().min(room);
+        let cfg = CONFIG.read();
+        for msg in older.into_iter().rev().take(added) {
+            if cfg.is_highlighted(msg.0.message_text()) {
+                self.highlighted.insert(msg.1);
+            }
+            if is_blocked(msg.0.message_text(), &cfg.blocked_terms) {
+                self.blocked.insert(msg.1);
+            }
+            if self.is_hidden(&msg.0) {
+                self.hidden.insert(msg.1);
+            }
+            self.messages.push_front(msg);
         }
+        self.front_parity ^= added % 2 == 1;
     }
 
-    pub fn view<'a>(&'a self) -> Element<'a, Message> {
-        let msgs = &self.messages;
+    /// `anonymous` is whether chat is connected without an account, which
+    /// can't send messages
+    pub fn view<'a>(
+        &'a self,
+        connection: ConnectionState,
+        anonymous: bool,
+        background: Color,
+    ) -> Element<'a, Message> {
+        let emote_warning: Element<'a, Message> = if self.failed_emotes.is_empty() {
+            space().into()
+        } else {
+            let platforms = self.failed_emotes.iter().map(|p| p.as_str()).join(", ");
+            row![
+                Text::new(format!("⚠ {platforms} emotes failed to load"))
+                    .size(12)
+                    .color(Color::from_rgb8(0xee, 0xd4, 0x9f)),
+                button(Text::new("Retry").size(12))
+                    .on_press(Message::ReloadEmotes)
+                    .style(button::secondary)
+                    .padding(Padding::ZERO.vertical(2.0).horizontal(6.0)),
+            ]
+            .spacing(6)
+            .align_y(Alignment::Center)
+            .into()
+        };
 
         let header = row([
-            button("hai").into(),
+            button("Clear")
+                .on_press(Message::ClearLocal)
+                .style(button::secondary)
+                .into(),
+            space().width(6).into(),
+            button("Export")
+                .on_press(Message::ExportLog)
+                .style(button::secondary)
+                .into(),
             space().width(Length::Fill).into(),
+            connection_dot(connection),
+            space().width(6).into(),
             self.channel.as_str().into(),
             space().width(Length::Fill).into(),
-            button("hoi").into(),
+            emote_warning,
+            space().width(6).into(),
+            button(if self.reloading_emotes {
+                "Reloading…"
+            } else {
+                "Reload emotes"
+            })
+            .on_press_maybe((!self.reloading_emotes).then_some(Message::ReloadEmotes))
+            .style(button::secondary)
+            .into(),
+            space().width(6).into(),
+            button("Emotes")
+                .on_press(Message::ToggleEmoteStats)
+                .style(button::secondary)
+                .into(),
+            space().width(6).into(),
+            button("Open")
+                .on_press(Message::OpenUrl(format!(
+                    "https://twitch.tv/{}",
+                    self.channel
+                )))
+                .style(button::secondary)
+                .into(),
+            space().width(6).into(),
+            button("Player")
+                .on_press(Message::OpenUrl(format!(
+                    "https://player.twitch.tv/?channel={}&parent=twitch.tv",
+                    self.channel
+                )))
+                .style(button::secondary)
+                .into(),
+            space().width(6).into(),
+            button(if self.window.is_some() {
+                "Pop in"
+            } else {
+                "Pop out"
+            })
+            .on_press(Message::TogglePopOut)
+            .style(button::secondary)
+            .into(),
         ])
         .width(Length::Fill)
         .align_y(alignment::Vertical::Center);
 
-        let message_box = text_input(&format!("Send message in {}", &self.channel), &self.message)
-            .on_paste(Message::MessageChange)
+        let chars = self.message.chars().count();
+        let command_error = parse_announce(&self.message).and_then(Result::err);
+        let sendable = !self.message.trim().is_empty()
+            && chars <= MAX_MESSAGE_CHARS
+            && command_error.is_none();
+        let placeholder = match connection {
+            _ if anonymous => "Log in to chat".to_owned(),
+            ConnectionState::Connecting => "Connecting…".to_owned(),
+            ConnectionState::Disconnected => "Reconnecting…".to_owned(),
+            ConnectionState::Connected => format!("Send message in {}", &self.channel),
+        };
+        let cooldown = self.cooldown_left();
+        let can_send = !anonymous && connection == ConnectionState::Connected && cooldown.is_none();
+        // Enter without submitting just keeps editing the single line
+        let enter_sends = self.shift_held == CONFIG.read().ui.shift_enter_sends;
+        let message_box = text_input(&placeholder, &self.message)
+            .id(self.input_id.clone())
+            .on_paste(Message::Pasted)
             .on_input(Message::MessageChange)
-            .on_submit_maybe(if !self.message.trim().is_empty() {
-                Some(Message::SendMessage)
-            } else {
-                None
+            .on_submit_maybe((sendable && can_send && enter_sends).then_some(Message::SendMessage));
+        let cooldown = cooldown.map(|left| {
+            let secs = left.as_secs() + u64::from(left.subsec_nanos() > 0);
+            Element::from(
+                Text::new(format!("Slow mode: {secs}s"))
+                    .size(12)
+                    .color(Color::from_rgb8(0xee, 0xd4, 0x9f)),
+            )
+        });
+        let counter = Text::new(format!("{chars}/{MAX_MESSAGE_CHARS}"))
+            .size(12)
+            .style(move |t: &iced::Theme| iced::widget::text::Style {
+                color: Some(if chars > MAX_MESSAGE_CHARS {
+                    Color::from_rgb8(0xed, 0x87, 0x96)
+                } else {
+                    t.extended_palette().background.strongest.color
+                }),
             });
+        let suggestions = self.mention_suggestions();
+        let above_input = if !suggestions.is_empty() {
+            Row::from_iter(suggestions.into_iter().map(|name| {
+                button(Text::new(format!("@{name}")).size(12))
+                    .on_press(Message::MentionPicked(name.to_owned()))
+                    .style(button::secondary)
+                    .padding(Padding::ZERO.vertical(2.0).horizontal(6.0))
+                    .into()
+            }))
+            .spacing(4)
+            .into()
+        } else {
+            self.input_warning
+                .or(command_error)
+                .map(|w| {
+                    Element::from(
+                        Text::new(w)
+                            .size(12)
+                            .color(Color::from_rgb8(0xed, 0x87, 0x96)),
+                    )
+                })
+                .unwrap_or_else(|| space().into())
+        };
+        let message_box = column![
+            above_input,
+            Row::from_iter(
+                [
+                    message_box.into(),
+                    button(Text::new("Emotes").size(12))
+                        .on_press(Message::ToggleEmotePicker)
+                        .style(button::secondary)
+                        .padding(Padding::ZERO.vertical(2.0).horizontal(6.0))
+                        .into(),
+                ]
+                .into_iter()
+                .chain(cooldown)
+                .chain([counter.into()])
+            )
+            .spacing(6)
+            .align_y(Alignment::Center)
+        ];
 
         let image_gen = IMAGE_GENERATION.load(std::sync::atomic::Ordering::Relaxed);
+        let settings_gen = SETTINGS_GENERATION.load(std::sync::atomic::Ordering::Relaxed);
 
-        column![
-            header,
-            rule::horizontal(1).style(rule::weak),
-            iced::widget::stack!(
-                scrollie(msgs.iter().map(|(m, key)| {
+        let cfg = CONFIG.read();
+        let style = LineStyle::new(&cfg.ui, background);
+        let alternate_rows = cfg.ui.alternate_rows;
+        let visible = self.visible_messages(&cfg).collect::<Vec<_>>();
+        let grouped = |a: usize, b: usize| {
+            let line = |i: usize| {
+                let (m, _, sent, kind) = visible[i];
+                (&**m, sent, kind)
+            };
+            cfg.ui.group_messages && b < visible.len() && same_group(line(a), line(b))
+        };
+        let children = visible
+            .iter()
+            .copied()
+            .enumerate()
+            .map(|(i, (m, key, sent, kind))| {
+                let group = Group {
+                    first: i == 0 || !grouped(i - 1, i),
+                    last: !grouped(i, i + 1),
+                };
+                let repeats = self.repeats.get(key).copied().unwrap_or(1);
+                // highlights take precedence over the alternating tint
+                let tint = if let Some(color) = AnnouncementColor::of(m) {
+                    Tint::Announcement(color)
+                } else if self.highlighted.contains(key) {
+                    Tint::Highlight
+                } else if alternate_rows && (i % 2 == 1) != self.front_parity {
+                    Tint::Alternate
+                } else {
+                    Tint::None
+                };
+                let line: Element<'_, Message> = lazy(
                     (
-                        lazy(
-                            (
-                                key,
-                                self.emote_generation,
-                                self.emote_sets_loaded,
-                                image_gen,
-                            ),
-                            |_| self.view_message(m),
-                        ),
-                        *key,
-                    )
-                }))
-                .natural_scrolling(CONFIG.read().ui.natural_scrolling)
+                        key,
+                        self.emote_generation,
+                        self.emote_sets_loaded,
+                        image_gen,
+                        kind,
+                        repeats,
+                        settings_gen,
+                        tint,
+                        group,
+                        background.into_rgba8(),
+                    ),
+                    move |_| match kind {
+                        LineKind::Message | LineKind::Struck => context_menu(
+                            mouse_area(self.view_message(
+                                m,
+                                sent,
+                                repeats,
+                                style,
+                                tint,
+                                kind == LineKind::Struck,
+                                group,
+                            ))
+                            .on_double_click(Message::SelectMessage(*key)),
+                            message_menu(m, *key),
+                        )
+                        .into(),
+                        LineKind::Selecting => {
+                            selectable_message(m, self.selection_id.clone(), style.compact)
+                        }
+                        LineKind::Blocked => {
+                            placeholder("Message hidden, click to reveal", *key, style.compact)
+                        }
+                        LineKind::Deleted => {
+                            placeholder("Message deleted, click to reveal", *key, style.compact)
+                        }
+                    },
+                )
+                .into();
+                (sent, *key, line)
+            });
+        let system = self
+            .system
+            .iter()
+            .filter(|(line, ..)| cfg.ui.system_messages.is_shown(line.kind))
+            .map(|(line, key, sent)| (*sent, *key, view_system_line(line, style.compact)));
+        let children = children
+            .merge_by(system, |a, b| (a.0, a.1) <= (b.0, b.1))
+            .map(|(_, key, line)| (line, key));
+        let messages = scrollie(children)
+            .natural_scrolling(cfg.ui.natural_scrolling)
+            .scroll_speed(cfg.ui.scroll_speed)
+            .smooth_scroll(cfg.ui.smooth_scroll);
+        let input_split = cfg.ui.input_split;
+        drop(cfg);
+
+        let messages = iced::widget::stack!(
+            messages
                 .on_scroll(Message::ChatScrolled)
+                .on_zoom(Message::Zoom)
                 .width(Length::Fill)
                 .height(Length::Fill)
                 .id(self.scroll_id.clone()),
-                if self.show_scroll_to_bottom {
-                    scroll_to_bottom()
-                } else {
-                    space().into()
-                }
-            ),
-            message_box
+            if self.show_scroll_to_bottom {
+                scroll_to_bottom(self.unseen_since_scroll)
+            } else {
+                space().into()
+            },
+            if self.show_emote_stats {
+                self.view_emote_stats()
+            } else {
+                space().into()
+            },
+            if self.show_emote_picker {
+                self.view_emote_picker()
+            } else {
+                space().into()
+            },
+            self.usercard
+                .as_deref()
+                .map(|u| self.view_usercard(u))
+                .unwrap_or_else(|| space().into()),
+            self.emote_info
+                .as_ref()
+                .map(view_emote_info)
+                .unwrap_or_else(|| space().into())
+        );
+
+        column![
+            header,
+            rule::horizontal(1).style(rule::weak),
+            self.history_error
+                .as_deref()
+                .map(view_history_error)
+                .unwrap_or_else(|| space().into()),
+            Splitter::new(messages, message_box, input_split)
+                .on_resize(Message::InputResized)
+                .on_resize_end(Message::InputResizeEnded)
         ]
         .into()
     }
 
+    /// The messages that end up in the scrollback along with how they're
+    /// shown, in the same order as they're rendered
+    fn visible_messages<'a>(
+        &'a self,
+        cfg: &'a Config,
+    ) -> impl Iterator<Item = (&'a Arc<PrivMsg>, &'a u64, DateTime<Utc>, LineKind)> + 'a {
+        let show_deleted = cfg.ui.deleted_messages(self.is_mod);
+        self.messages
+            .iter()
+            .filter(|(_, key, _)| !self.hidden.contains(key))
+            .filter_map(move |(m, key, sent)| {
+                let sent = *sent;
+                let revealed = self.revealed.contains(key);
+                if self.deleted.contains(key) {
+                    return match show_deleted {
+                        DeletedMessages::Hide => None,
+                        DeletedMessages::Collapse if !revealed => {
+                            Some((m, key, sent, LineKind::Deleted))
+                        }
+                        _ if self.selecting == Some(*key) => {
+                            Some((m, key, sent, LineKind::Selecting))
+                        }
+                        _ => Some((m, key, sent, LineKind::Struck)),
+                    };
+                }
+                let blocked = self.blocked.contains(key);
+                if blocked && !cfg.ui.collapse_blocked {
+                    return None;
+                }
+                let kind = if blocked && !revealed {
+                    LineKind::Blocked
+                } else if self.selecting == Some(*key) {
+                    LineKind::Selecting
+                } else {
+                    LineKind::Message
+                };
+                Some((m, key, sent, kind))
+            })
+    }
+
+    /// Renders a message of this channel outside of its scrollback
+    pub fn view_mention(
+        &self,
+        msg: &PrivMsg,
+        sent: DateTime<Utc>,
+        background: Color,
+    ) -> Element<'static, Message> {
+        let style = LineStyle::new(&CONFIG.read().ui, background);
+        self.view_message(msg, sent, 1, style, Tint::None, false, Group::SOLO)
+    }
+
     pub fn update(&mut self, msg: Message) -> Task<Message> {
         match msg {
             Message::SendMessage => {
-                self.message.clear();
+                let sent = std::mem::take(&mut self.message);
+                if self.sent_history.last() != Some(&sent) {
+                    if self.sent_history.len() >= Self::MAX_SENT_HISTORY {
+                        self.sent_history.remove(0);
+                    }
+                    self.sent_history.push(sent);
+                }
+                self.history_cursor = None;
+                self.draft.clear();
+                self.edits.clear();
+                self.input_warning = None;
+                if self.slow_mode > 0 {
+                    self.cooldown_until =
+                        Some(Instant::now() + Duration::from_secs(self.slow_mode));
+                    return self.cooldown_tick();
+                }
+            }
+            Message::CooldownTick => return self.cooldown_tick(),
+            Message::ModifiersChanged(modifiers) => self.shift_held = modifiers.shift(),
+            Message::MessageChange(m) => {
+                self.edits.record(&self.message, &m);
+                self.message = m;
+                self.history_cursor = None;
+                self.input_warning = None;
+            }
+            Message::Pasted(m) => {
+                let mut m = single_line(&m);
+                if CONFIG.read().ui.clean_pasted_links {
+                    m = m.split(' ').map(clean_link).join(" ");
+                }
+                self.input_warning = (m.chars().count() > MAX_MESSAGE_CHARS)
+                    .then_some("Pasted text is longer than Twitch's 500 character limit");
+                self.edits.record(&self.message, &m);
+                self.message = m;
+                self.history_cursor = None;
+            }
+            Message::HistoryPrevious => {
+                let cursor = match self.history_cursor {
+                    Some(i) => i.saturating_sub(1),
+                    None if self.sent_history.is_empty() => return Task::none(),
+                    None => {
+                        self.draft = self.message.clone();
+                        self.sent_history.len() - 1
+                    }
+                };
+                self.history_cursor = Some(cursor);
+                let recalled = self.sent_history[cursor].clone();
+                self.edits.record(&self.message, &recalled);
+                self.message = recalled;
+                return self.focus_input();
+            }
+            Message::HistoryNext => {
+                let Some(i) = self.history_cursor else {
+                    return Task::none();
+                };
+                let recalled = if i + 1 < self.sent_history.len() {
+                    self.history_cursor = Some(i + 1);
+                    self.sent_history[i + 1].clone()
+                } else {
+                    self.history_cursor = None;
+                    std::mem::take(&mut self.draft)
+                };
+                self.edits.record(&self.message, &recalled);
+                self.message = recalled;
+                return self.focus_input();
+            }
+            Message::Undo => {
+                if let Some(m) = self.edits.undo(&self.message) {
+                    self.message = m;
+                    self.history_cursor = None;
+                    self.input_warning = None;
+                }
+                return self.focus_input();
+            }
+            Message::CompleteMention => {
+                let first = self.mention_suggestions().first().map(|n| (*n).to_owned());
+                if let Some(name) = first {
+                    return self.update(Message::MentionPicked(name));
+                }
+            }
+            Message::MentionPicked(name) => {
+                let completed = complete_mention(&self.message, &name);
+                self.edits.checkpoint(&self.message);
+                self.message = completed;
+                self.history_cursor = None;
+                return self.focus_input();
+            }
+            Message::Redo => {
+                if let Some(m) = self.edits.redo(&self.message) {
+                    self.message = m;
+                    self.history_cursor = None;
+                    self.input_warning = None;
+                }
+                return self.focus_input();
             }
-            Message::MessageChange(m) => self.message = m,
             Message::ShowUserCard(user) => self.usercard = Some(user),
             Message::CloseUserCard => self.usercard = None,
+            Message::ShowEmoteInfo(emote) => self.emote_info = Some(emote),
+            Message::CloseEmoteInfo => self.emote_info = None,
+            Message::ReloadEmotes => (),
+            Message::LoadOlderMessages => (),
+            Message::RetryHistory => (),
+            Message::TogglePopOut => (),
+            Message::ToggleEmoteStats => self.show_emote_stats = !self.show_emote_stats,
+            Message::ToggleEmotePicker => {
+                self.show_emote_picker = !self.show_emote_picker;
+                self.emote_search.clear();
+            }
+            Message::EmoteSearchChange(search) => self.emote_search = search,
+            Message::InsertEmote(name) => {
+                self.edits.checkpoint(&self.message);
+                if !self.message.is_empty() && !self.message.ends_with(' ') {
+                    self.message.push(' ');
+                }
+                self.message.push_str(&name);
+                self.message.push(' ');
+                self.history_cursor = None;
+                return self.focus_input();
+            }
+            Message::RevealMessage(key) => {
+                self.revealed.insert(key);
+            }
+            Message::SelectMessage(key) => {
+                self.selecting = Some(key);
+                return iced::widget::operation::focus(self.selection_id.clone()).chain(
+                    iced::widget::operation::select_all(self.selection_id.clone()),
+                );
+            }
+            Message::SelectionEdited => (),
+            Message::StopSelecting => self.selecting = None,
+            Message::ToggleHideUser(user) => {
+                if !self.hidden_users.remove(&user) {
+                    self.hidden_users.insert(user.clone());
+                }
+                self.hidden = self
+                    .messages
+                    .iter()
+                    .filter(|(m, ..)| self.is_hidden(m))
+                    .map(|(_, key, _)| *key)
+                    .collect();
+
+                let mut cfg = CONFIG.write();
+                let hidden = cfg.hidden_users.entry(self.channel.clone()).or_default();
+                if self.hidden_users.contains(&user) {
+                    hidden.insert(user);
+                } else {
+                    hidden.remove(&user);
+                }
+                cfg.hidden_users.retain(|_, u| !u.is_empty());
+                if let Err(e) = cfg.save() {
+                    log::error!("Error when saving hidden users: {e}");
+                }
+            }
             Message::ScrollToBottom => {
+                self.unseen_since_scroll = 0;
+                return iced::widget::operation::snap_to_end(self.scroll_id.clone());
+            }
+            Message::JumpTo(key) => {
+                let idx = self
+                    .visible_messages(&CONFIG.read())
+                    .position(|(_, k, ..)| *k == key);
+                if let Some(idx) = idx {
+                    return crate::operation::scroll_to_idx::<u64>(self.scroll_id.clone(), idx)
+                        .discard();
+                }
+            }
+            Message::ClearLocal => {
+                self.messages.clear();
+                self.repeats.clear();
+                self.revealed.clear();
+                self.deleted.clear();
+                self.highlighted.clear();
+                self.blocked.clear();
+                self.hidden.clear();
+                self.selecting = None;
+                self.show_scroll_to_bottom = false;
+                self.unseen_since_scroll = 0;
                 return iced::widget::operation::snap_to_end(self.scroll_id.clone());
             }
+            Message::ExportLog => {
+                let entries = self
+                    .messages
+                    .iter()
+                    .map(|(msg, _, sent)| LogEntry::new(msg, *sent))
+                    .collect_vec();
+                let name = format!("{}-{}.txt", self.channel, Local::now().format("%Y-%m-%d"));
+                return Task::future(async move {
+                    let Some(file) = rfd::AsyncFileDialog::new()
+                        .set_title("Export chat log")
+                        .set_file_name(name)
+                        .add_filter("Text", &["txt", "log"])
+                        .add_filter("JSON", &["json"])
+                        .save_file()
+                        .await
+                    else {
+                        return;
+                    };
+                    let path = file.path();
+                    let json = path
+                        .extension()
+                        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+                    let log = if json {
+                        json_log(&entries)
+                    } else {
+                        entries.iter().map(|e| e.to_line() + "\n").collect()
+                    };
+                    match tokio::fs::write(path, log).await {
+                        Ok(()) => {
+                            log::info!("exported {} messages to {}", entries.len(), path.display())
+                        }
+                        Err(e) => log::error!("couldn't export log to {}: {e}", path.display()),
+                    }
+                })
+                .discard();
+            }
+            Message::CopyToClipboard(s) => return iced::clipboard::write(s),
+            Message::OpenUrl(url) => {
+                if let Err(e) = open::that_detached(&url) {
+                    log::error!("couldn't open {url}: {e}");
+                }
+            }
+            Message::Zoom(steps) => {
+                let mut cfg = CONFIG.write();
+                let range = UiConfig::FONT_SIZE_RANGE;
+                cfg.ui.chat_font_size = (cfg.ui.chat_font_size.round() + steps as f32)
+                    .clamp(*range.start(), *range.end());
+                SETTINGS_GENERATION.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                self.zooms += 1;
+                let zooms = self.zooms;
+                return Task::perform(tokio::time::sleep(Self::ZOOM_SAVE_DELAY), move |_| {
+                    Message::ZoomEnded(zooms)
+                });
+            }
+            Message::ZoomEnded(zooms) => {
+                if zooms == self.zooms
+                    && let Err(e) = CONFIG.write().save()
+                {
+                    log::error!("Error when saving settings: {e}");
+                }
+            }
+            Message::InputResized(ratio) => CONFIG.write().ui.input_split = ratio,
+            Message::InputResizeEnded => {
+                if let Err(e) = CONFIG.write().save() {
+                    log::error!("Error when saving settings: {e}");
+                }
+            }
             Message::ChatScrolled(vp) => {
                 self.show_scroll_to_bottom = !vp.is_at_bottom();
+                if !self.show_scroll_to_bottom {
+                    self.unseen_since_scroll = 0;
+                }
+                if vp.is_at_top()
+                    && !self.loading_older
+                    && !self.history_exhausted
+                    && !self.messages.is_empty()
+                    && self.messages.len() < Self::MAX_SCROLLBACK
+                {
+                    self.loading_older = true;
+                    return Task::done(Message::LoadOlderMessages);
+                }
             }
             Message::LoadImage(t) => return t().chain(Task::done(Message::EmoteLoaded)),
             Message::EmoteSetsLoaded => self.emote_sets_loaded = true,
@@ -180,92 +1426,1133 @@ impl Chat {
         Task::none()
     }
 
-    fn view_message(&self, msg: &PrivMsg) -> Element<'static, Message> {
-        let badges = msg
-            .badges()
+    fn count_emotes(&mut self, msg: &PrivMsg) {
+        let text = msg.message_text();
+        for (id, ranges) in msg.emotes() {
+            for range in ranges {
+                let name: String = text
+                    .chars()
+                    .skip(*range.start())
+                    .take(range.end() + 1 - range.start())
+                    .collect();
+                self.emote_stats
+                    .entry(name)
+                    .or_insert_with(|| EmoteStat {
+                        count: 0,
+                        twitch_id: Some(id.to_owned()),
+                    })
+                    .count += 1;
+            }
+        }
+        for word in text.split(' ').filter(|w| self.emote(w).is_some()) {
+            self.emote_stats
+                .entry(word.to_owned())
+                .or_insert(EmoteStat {
+                    count: 0,
+                    twitch_id: None,
+                })
+                .count += 1;
+        }
+    }
+
+    fn view_emote_stats(&self) -> Element<'_, Message> {
+        const TOP_EMOTES: usize = 10;
+
+        let rows = self
+            .emote_stats
+            .iter()
+            .sorted_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(b.0)))
+            .take(TOP_EMOTES)
+            .map(|(name, stat)| {
+                let image: Element<'_, Message> = match &stat.twitch_id {
+                    Some(id) => twitch::emotes::EMOTE_CACHE
+                        .get(id)
+                        .and_then(|h| h.get()?.as_ref().ok().cloned())
+                        .map(Element::from)
+                        .unwrap_or_else(|| space().into()),
+                    None => self
+                        .emote(name)
+                        .map(|e| e.view(1.0).map(|t| Message::LoadImage(Box::new(t))))
+                        .unwrap_or_else(|| space().into()),
+                };
+                Element::from(
+                    row![
+                        container(image).width(32).center_x(32),
+                        Text::new(name.as_str()).width(Length::Fill),
+                        Text::new(stat.count.to_string()),
+                    ]
+                    .spacing(8)
+                    .align_y(Alignment::Center),
+                )
+            });
+
+        let list: Element<'_, Message> = if self.emote_stats.is_empty() {
+            Text::new("No emotes used yet").into()
+        } else {
+            iced::widget::Column::from_iter(rows).spacing(4).into()
+        };
+
+        container(opaque(
+            container(
+                column![
+                    Text::new("Top emotes").size(20),
+                    list,
+                    button("Close").on_press(Message::ToggleEmoteStats),
+                ]
+                .spacing(12)
+                .width(260),
+            )
+            .style(container::rounded_box)
+            .padding(20),
+        ))
+        .center(Length::Fill)
+        .into()
+    }
+
+    /// Emote picker listing the emotes matching [Self::emote_search], grouped
+    /// by platform
+    fn view_emote_picker(&self) -> Element<'_, Message> {
+        /// Most emotes listed per platform, laying out thousands of them
+        /// would make typing sluggish
+        const MAX_RESULTS: usize = 100;
+
+        let search = self.emote_search.trim();
+        let emote_button = |name: &str, image: Element<'static, Message>| {
+            Element::from(
+                mouse_area(container(image).padding(2))
+                    .on_press(Message::InsertEmote(name.to_owned()))
+                    .interaction(mouse::Interaction::Pointer),
+            )
+        };
+
+        let twitch = self
+            .emote_stats
+            .iter()
+            .filter_map(|(name, stat)| Some((name, stat.twitch_id.as_ref()?)))
+            .filter(|(name, _)| matches_search(name, search))
+            .sorted_unstable_by(|a, b| a.0.cmp(b.0))
+            .filter_map(|(name, id)| {
+                let image = twitch::emotes::EMOTE_CACHE
+                    .get(id)
+                    .and_then(|h| h.get()?.as_ref().ok().cloned())?;
+                Some(emote_button(name, image.into()))
+            })
+            .take(MAX_RESULTS)
+            .collect_vec();
+
+        // channel emotes shadow globals of the same name
+        let others = self.emotes.values().chain(
+            self.global_emotes
+                .values()
+                .filter(|e| !self.emotes.contains_key(e.text_name())),
+        );
+        let mut by_platform: HashMap<EmotePlatform, Vec<&ChannelEmote>> = HashMap::new();
+        for emote in others.filter(|e| matches_search(e.text_name(), search)) {
+            by_platform
+                .entry(emote.metadata.platform)
+                .or_default()
+                .push(emote);
+        }
+
+        let groups = [
+            EmotePlatform::SevenTv,
+            EmotePlatform::BetterTtv,
+            EmotePlatform::FrankerFaceZ,
+        ]
+        .into_iter()
+        .map(|p| {
+            let emotes = by_platform
+                .remove(&p)
+                .unwrap_or_default()
+                .into_iter()
+                .sorted_unstable_by(|a, b| a.text_name().cmp(b.text_name()))
+                .take(MAX_RESULTS)
+                .map(|e| {
+                    emote_button(
+                        e.text_name(),
+                        e.view(1.0).map(|t| Message::LoadImage(Box::new(t))),
+                    )
+                })
+                .collect_vec();
+            (p, emotes)
+        });
+
+        let groups = std::iter::once((EmotePlatform::Twitch, twitch))
+            .chain(groups)
+            .filter(|(_, emotes)| !emotes.is_empty())
+            .map(|(p, emotes)| {
+                Element::from(
+                    column![
+                        Text::new(p.as_str()).size(14),
+                        Row::from_vec(emotes).spacing(2).wrap(),
+                    ]
+                    .spacing(4),
+                )
+            })
+            .collect_vec();
+
+        let list: Element<'_, Message> = if groups.is_empty() {
+            Text::new("No emotes found").into()
+        } else {
+            iced::widget::scrollable(iced::widget::Column::from_vec(groups).spacing(12))
+                .height(320)
+                .into()
+        };
+
+        container(opaque(
+            container(
+                column![
+                    Text::new("Emotes").size(20),
+                    text_input("Search emotes", &self.emote_search)
+                        .on_input(Message::EmoteSearchChange),
+                    list,
+                    button("Close").on_press(Message::ToggleEmotePicker),
+                ]
+                .spacing(12)
+                .width(360),
+            )
+            .style(container::rounded_box)
+            .padding(20),
+        ))
+        .center(Length::Fill)
+        .into()
+    }
+
+    /// The input as it's sent to Twitch, made different from the last message
+    /// if Twitch would reject it as a duplicate
+    pub fn outgoing_message(&mut self) -> String {
+        let last = self
+            .last_sent
+            .as_ref()
+            .filter(|(_, sent)| sent.elapsed() < DUPLICATE_WINDOW)
+            .map(|(text, _)| text.as_str());
+        let text = if CONFIG.read().ui.bypass_duplicate_filter {
+            bypass_duplicate(&self.message, last)
+        } else {
+            self.message.clone()
+        };
+        self.last_sent = Some((text.clone(), Instant::now()));
+        text
+    }
+
+    /// Recent chatters whose name starts with the `@username` being typed
+    fn mention_suggestions(&self) -> Vec<&str> {
+        let Some(partial) = mention_prefix(&self.message) else {
+            return Vec::new();
+        };
+        let partial = partial.to_lowercase();
+        self.recent_chatters
+            .iter()
+            .filter(|n| n.to_lowercase().starts_with(&partial))
+            .take(Self::MAX_SUGGESTIONS)
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// How long the user has to wait before sending again in slow mode,
+    /// [None] if they can send right away or are exempt from it
+    fn cooldown_left(&self) -> Option<Duration> {
+        if self.slow_mode == 0 || self.is_mod || self.is_vip {
+            return None;
+        }
+        let until = self.cooldown_until?;
+        Some(until.saturating_duration_since(Instant::now())).filter(|d| !d.is_zero())
+    }
+
+    /// Wakes up when the seconds shown in the countdown change, until the
+    /// cooldown is over
+    fn cooldown_tick(&mut self) -> Task<Message> {
+        let Some(left) = self.cooldown_left() else {
+            self.cooldown_until = None;
+            return Task::none();
+        };
+        let next = match left.subsec_nanos() {
+            0 => Duration::from_secs(1),
+            n => Duration::from_nanos(n.into()),
+        };
+        Task::perform(tokio::time::sleep(next), |_| Message::CooldownTick)
+    }
+
+    fn focus_input(&self) -> Task<Message> {
+        iced::widget::operation::focus(self.input_id.clone()).chain(
+            iced::widget::operation::move_cursor_to_end(self.input_id.clone()),
+        )
+    }
+
+    fn is_hidden(&self, msg: &PrivMsg) -> bool {
+        from_hidden_user(msg, &self.hidden_users)
+    }
+
+    fn view_usercard<'a>(&'a self, login: &'a str) -> Element<'a, Message> {
+        let pronouns = CONFIG
+            .read()
+            .ui
+            .show_pronouns
+            .then(|| pronouns::pronouns(login))
+            .flatten()
+            .map(|p| pronoun_label(p, 14.0));
+        let hide = if self.hidden_users.contains(login) {
+            "Show messages"
+        } else {
+            "Hide messages"
+        };
+        container(opaque(
+            container(
+                column![
+                    Row::from_iter(
+                        std::iter::once(Text::new(login).size(20).into()).chain(pronouns)
+                    )
+                    .spacing(8)
+                    .align_y(Alignment::Center),
+                    row![
+                        button(hide).on_press(Message::ToggleHideUser(login.to_owned())),
+                        button("Close").on_press(Message::CloseUserCard),
+                    ]
+                    .spacing(6),
+                ]
+                .spacing(12),
+            )
+            .style(container::rounded_box)
+            .padding(20),
+        ))
+        .center(Length::Fill)
+        .into()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn view_message(
+        &self,
+        msg: &PrivMsg,
+        sent: DateTime<Utc>,
+        repeats: usize,
+        style: LineStyle,
+        tint: Tint,
+        struck: bool,
+        group: Group,
+    ) -> Element<'static, Message> {
+        let LineStyle {
+            font_size,
+            emote_scale,
+            providers,
+            compact,
+            pronouns: show_pronouns,
+            ..
+        } = style;
+
+        let badges = CONFIG
+            .read()
+            .ui
+            .sort_badges(msg.badges())
+            .into_iter()
             .filter_map(|(set, id)| {
-                BADGE_CACHE
+                let handle = BADGE_CACHE
                     .get(&(set.to_owned(), id.to_owned()))
-                    .and_then(|h| h.get()?.as_ref().ok().cloned())
+                    .and_then(|h| h.get()?.as_ref().ok().cloned())?;
+                Some((handle, twitch::badges::label(set, id)))
+            })
+            .map(|(h, label)| {
+                // below the line, as the line's own tooltip with the time is above
+                Element::from(tooltip(
+                    iced::widget::image(h.to_owned()),
+                    Container::new(Text::new(label).size(12))
+                        .padding(6)
+                        .style(|_| {
+                            container::Style::default()
+                                .border(Border::default().rounded(6.0))
+                                .background(Color::from_rgba(0.0, 0.0, 0.0, 0.8))
+                        }),
+                    tooltip::Position::Bottom,
+                ))
             })
-            .map(|h| Element::new(iced::widget::image(h.to_owned())))
             .collect::<Row<Message>>()
             .spacing(3);
 
         let emotes = msg
             .emotes()
+            .filter(|_| providers.twitch)
             .filter_map(|(e, ranges)| {
                 Some((
                     twitch::emotes::EMOTE_CACHE
                         .get(e)
                         .and_then(|h| h.get()?.as_ref().ok().cloned())?,
                     ranges,
+                    twitch::emotes::cdn_url(e, CdnSize::FourX),
                 ))
             })
-            .map(|(h, r)| (h.to_owned(), r))
-            .collect::<Vec<(AnimatedImage, Vec<RangeInclusive<usize>>)>>();
+            .map(|(h, r, url)| (h.to_owned().scaled(emote_scale), r, url))
+            .collect::<Vec<(AnimatedImage, Vec<RangeInclusive<usize>>, String)>>();
 
+        let login = msg.get_username().map(|u| u.to_string());
+        let pronouns = login
+            .as_deref()
+            .filter(|_| show_pronouns)
+            .and_then(pronouns::pronouns)
+            .map(|p| pronoun_label(p, font_size * 0.75));
         let username = msg
             .get_tag(OwnedTag::DisplayName)
             .or_else(|| msg.get_username().map(Into::into))
             .unwrap_or("FUCK".into());
 
-        let [r, g, b] = msg.get_color().unwrap_or([96; 3]);
-        let mut hsl: palette::Hsl = palette::Srgb::new(r, g, b).into_format().into_color();
-        hsl.lightness = hsl.lightness.max(0.5);
-        let (r, g, b) = palette::Srgb::from_color(hsl)
-            .into_format()
-            .into_components();
-        let color = Color::from_rgb8(r, g, b);
+        let color = readable_color(
+            msg.get_color().unwrap_or([96; 3]),
+            style.background,
+            style.min_contrast,
+        );
+
+        let bits = msg
+            .get_tag(OwnedTag::Bits)
+            .and_then(|b| b.parse::<u32>().ok());
 
-        let mut char_pos = 0;
         let msg_col = if msg.is_me() { Some(color) } else { None };
 
-        let spans = msg.message_text().split(' ').map(|w| {
-            let word_chars = w.chars().count();
-            let elem = emotes
-                .iter()
-                .find(|e| {
-                    e.1.iter()
-                        .any(|r| *r == (char_pos..=(char_pos + word_chars - 1)))
-                })
-                .map(|e| Element::new(e.0.clone()))
-                .or_else(|| {
-                    self.emotes
-                        .get(w)
-                        .map(|e| e.view().map(|t| Message::LoadImage(Box::new(t))))
-                })
-                .unwrap_or_else(|| Text::new(w.to_owned()).color_maybe(msg_col).into());
-            char_pos += word_chars + 1;
-            elem
-        });
+        enum Word {
+            Text,
+            /// Zero-width emotes after it are stacked on top of it
+            Emote,
+            Cheer,
+        }
+        let mut words: Vec<(Element<'static, Message>, Word)> = Vec::new();
+        let text = without_bypass(msg.message_text());
+        for segment in segments(text, emotes.iter().map(|e| e.1.as_slice())) {
+            let w = match segment {
+                Segment::Word(w) => w,
+                Segment::Emote(i) => {
+                    let (image, _, url) = &emotes[i];
+                    let elem = mouse_area(image.clone())
+                        .on_right_press(Message::CopyToClipboard(url.clone()))
+                        .into();
+                    words.push((elem, Word::Emote));
+                    continue;
+                }
+            };
+            let channel_emote = self
+                .emote(w)
+                .filter(|e| providers.is_enabled(e.metadata.platform));
+
+            if let Some(c) = bits.and_then(|_| Cheer::parse(&self.channel, w)) {
+                words.push((view_cheer(c, font_size, emote_scale), Word::Cheer));
+            } else if let Some(e) = channel_emote {
+                let elem: Element<'static, Message> =
+                    mouse_area(e.view(emote_scale).map(|t| Message::LoadImage(Box::new(t))))
+                        .on_press(Message::ShowEmoteInfo(e.clone()))
+                        .on_right_press(Message::CopyToClipboard(e.cdn_url(CdnSize::FourX)))
+                        .into();
+                match words.last_mut() {
+                    Some((base, Word::Emote))
+                        if e.metadata.flags.contains(EmoteFlags::OVERLAYING) =>
+                    {
+                        let below = std::mem::replace(base, space().into());
+                        *base = Overlaid::new(vec![below, elem]).into();
+                    }
+                    _ => words.push((elem, Word::Emote)),
+                }
+            } else {
+                let elem = if struck {
+                    Rich::<(), _>::with_spans([Span::new(w.to_owned())
+                        .strikethrough(true)
+                        .color_maybe(msg_col)])
+                    .size(font_size)
+                    .into()
+                } else {
+                    Text::new(w.to_owned())
+                        .size(font_size)
+                        .color_maybe(msg_col)
+                        .into()
+                };
+                words.push((elem, Word::Text));
+            }
+        }
 
-        let spans = itertools::intersperse_with(spans, || Text::new(" ").into());
+        // the line is bottom aligned, so emotes are raised from the bottom of
+        // the text to its baseline
+        let baseline = Padding::ZERO.bottom(font_size * TEXT_DESCENT);
+        let spans = words.into_iter().map(|(elem, word)| match word {
+            Word::Text => elem,
+            Word::Emote | Word::Cheer => Container::new(elem).padding(baseline).into(),
+        });
+        let spans = itertools::intersperse_with(spans, || Text::new(" ").size(font_size).into());
 
         let text = Rich::<_, Message>::with_spans([
             Span::new(" "),
             Span::new(username.clone().into_owned())
                 .color(color)
-                .link(username.into_owned()),
+                .link(login.unwrap_or_else(|| username.to_lowercase())),
             Span::new(": "),
         ])
+        .size(font_size)
         .on_link_click(Message::ShowUserCard);
 
-        let line = [badges.into(), text.into()].into_iter().chain(spans);
+        let total_bits = bits.map(|amount| {
+            let color = twitch::cheermotes::bits_color(amount);
+            Element::from(
+                Text::new(format!("{amount} bits"))
+                    .size(font_size * 0.75)
+                    .color(color),
+            )
+        });
 
-        column![
-            Container::new(Row::from_iter(line).align_y(Alignment::End).wrap())
-                .padding(Padding::default().vertical(4.0).horizontal(6.0)),
-            rule::horizontal(1),
+        let repeats = (repeats > 1).then(|| {
+            Element::from(
+                Container::new(Text::new(format!("×{repeats}")).size(12))
+                    .padding(Padding::ZERO.horizontal(4.0))
+                    .style(|t: &iced::Theme| {
+                        container::Style::default()
+                            .background(t.extended_palette().background.strong.color)
+                            .border(Border::default().rounded(4.0))
+                    }),
+            )
+        });
+
+        // later lines of a group are indented under the name instead
+        let header: Vec<Element<'static, Message>> = if group.first {
+            vec![badges.into()]
+                .into_iter()
+                .chain(pronouns)
+                .chain([text.into()])
+                .collect()
+        } else {
+            vec![space().width(font_size).into()]
+        };
+        let line = header.into_iter().chain(spans).chain(
+            total_bits
+                .into_iter()
+                .chain(repeats)
+                .flat_map(|e| [Text::new(" ").into(), e]),
+        );
+
+        let line: Element<'static, Message> =
+            Row::from_iter(line).align_y(Alignment::End).wrap().into();
+        let line = match tint {
+            Tint::Announcement(c) => column![
+                Text::new("Announcement")
+                    .size(font_size * 0.75)
+                    .style(move |t: &iced::Theme| iced::widget::text::Style {
+                        color: Some(c.color(t)),
+                    }),
+                line
+            ]
+            .spacing(2)
+            .into(),
+            _ => line,
+        };
+        let line = Container::new(line)
+            .padding(message_padding(compact))
+            .style(move |t: &iced::Theme| {
+                let palette = t.extended_palette();
+                match tint {
+                    Tint::None => container::Style::default(),
+                    Tint::Alternate => container::Style::default()
+                        .background(palette.background.weak.color.scale_alpha(0.4)),
+                    Tint::Highlight => container::Style::default()
+                        .background(palette.primary.weak.color.scale_alpha(0.3)),
+                    Tint::Announcement(c) => container::Style::default()
+                        .background(c.color(t).scale_alpha(0.15))
+                        .border(Border::default().color(c.color(t)).width(1.0)),
+                }
+            });
+        let line: Element<'static, Message> = tooltip(
+            line,
+            Container::new(Text::new(
+                sent.with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+            ))
+            .padding(8)
+            .style(|_| {
+                container::Style::default()
+                    .border(Border::default().rounded(6.0))
+                    .background(Color::from_rgba(0.0, 0.0, 0.0, 0.8))
+            }),
+            tooltip::Position::Top,
+        )
+        .into();
+
+        if group.last {
+            column![line, separator(compact)].into()
+        } else {
+            line
+        }
+    }
+}
+
+/// A word of a message, or the native Twitch emote it is by its index
+#[derive(Debug, PartialEq, Eq)]
+enum Segment<'a> {
+    Word(&'a str),
+    Emote(usize),
+}
+
+/// Splits `text` into the words separated by spaces, finding the native
+/// Twitch emotes among them by the code point ranges sent for each emote.
+/// Ranges that don't cover a whole word are ignored
+fn segments<'a, 'r>(
+    text: &'a str,
+    emote_ranges: impl IntoIterator<Item = &'r [RangeInclusive<usize>]>,
+) -> Vec<Segment<'a>> {
+    let emote_ranges = emote_ranges.into_iter().collect_vec();
+    let mut pos = 0;
+    text.split(' ')
+        .map(|w| {
+            let len = w.chars().count();
+            let start = pos;
+            pos += len + 1;
+            if len == 0 {
+                return Segment::Word(w);
+            }
+            let range = start..=start + len - 1;
+            match emote_ranges.iter().position(|r| r.contains(&range)) {
+                Some(i) => Segment::Emote(i),
+                None => Segment::Word(w),
+            }
+        })
+        .collect()
+}
+
+/// Merges the time-ordered `new` into the time-ordered `messages` in one
+/// pass. Messages sent at the same time are ordered by their key, so the
+/// order doesn't depend on which batch they came in
+pub fn merge_by_time<T>(
+    messages: &mut VecDeque<(T, u64, DateTime<Utc>)>,
+    new: impl IntoIterator<Item = (T, u64, DateTime<Utc>)>,
+) {
+    *messages = std::mem::take(messages)
+        .into_iter()
+        .merge_by(new, |a, b| (a.2, a.1) <= (b.2, b.1))
+        .collect();
+}
+
+/// Drops the oldest of `messages` until at most `len` are left, returns the
+/// keys of the dropped ones
+pub fn trim_front<T>(messages: &mut VecDeque<(T, u64, DateTime<Utc>)>, len: usize) -> Vec<u64> {
+    let excess = messages.len().saturating_sub(len);
+    messages.drain(..excess).map(|(_, key, _)| key).collect()
+}
+
+/// What [push_live] did with a message
+#[derive(Debug, PartialEq, Eq)]
+pub enum Pushed {
+    /// It had the same text as the last message, which has this key, so it
+    /// wasn't added
+    Repeat(u64),
+    /// It was added after dropping the oldest messages, with these keys
+    Added(Vec<u64>),
+}
+
+/// Appends a message received live to `messages`, keeping at most
+/// `max_scrollback`. With `combine`, a message with the same sender and text
+/// as the last one is only counted as a repeat of it
+pub fn push_live(
+    messages: &mut VecDeque<(Arc<PrivMsg>, u64, DateTime<Utc>)>,
+    msg: Arc<PrivMsg>,
+    key: u64,
+    max_scrollback: usize,
+    combine: bool,
+) -> Pushed {
+    if combine
+        && let Some((last, last_key, _)) = messages.back()
+        && last.get_username() == msg.get_username()
+        && without_bypass(last.message_text()) == without_bypass(msg.message_text())
+    {
+        return Pushed::Repeat(*last_key);
+    }
+    let dropped = trim_front(messages, max_scrollback.saturating_sub(1));
+    let sent = sent_at(&msg);
+    messages.push_back((msg, key, sent));
+    Pushed::Added(dropped)
+}
+
+/// Merges the history of a channel into `messages` with [merge_by_time],
+/// skipping the messages that were already received live. The others get a
+/// key from `next_key` in order. Returns the keys of the oldest messages
+/// dropped to keep at most `max_scrollback`
+pub fn merge_history(
+    messages: &mut VecDeque<(Arc<PrivMsg>, u64, DateTime<Utc>)>,
+    history: impl IntoIterator<Item = PrivMsg>,
+    mut next_key: impl FnMut() -> u64,
+    max_scrollback: usize,
+) -> Vec<u64> {
+    let live: HashSet<String> = messages
+        .iter()
+        .filter_map(|(m, ..)| m.get_tag(OwnedTag::Id))
+        .map(|id| id.to_string())
+        .collect();
+    let new = history
+        .into_iter()
+        .filter(|m| {
+            m.get_tag(OwnedTag::Id)
+                .is_none_or(|id| !live.contains(&*id))
+        })
+        .map(|m| {
+            let sent = sent_at(&m);
+            (Arc::new(m), next_key(), sent)
+        })
+        .collect_vec();
+    merge_by_time(messages, new);
+    trim_front(messages, max_scrollback)
+}
+
+/// When `msg` was sent according to Twitch, or now for the live messages
+/// that come without a `tmi-sent-ts`
+pub fn sent_at(msg: &PrivMsg) -> DateTime<Utc> {
+    msg.get_timestamp().unwrap_or_else(Utc::now)
+}
+
+/// Whether the emote `name` contains `search`, ignoring ASCII case. Doesn't
+/// allocate, as it's run over every loaded emote on each keystroke
+fn matches_search(name: &str, search: &str) -> bool {
+    search.is_empty()
+        || name
+            .as_bytes()
+            .windows(search.len())
+            .any(|w| w.eq_ignore_ascii_case(search.as_bytes()))
+}
+
+/// Distance from the bottom of a line of text to its baseline, relative to
+/// the font size, with iced's default line height
+const TEXT_DESCENT: f32 = 0.3;
+
+/// Longest message Twitch accepts, in characters
+const MAX_MESSAGE_CHARS: usize = 500;
+
+/// How long Twitch rejects messages identical to the previous one
+const DUPLICATE_WINDOW: Duration = Duration::from_secs(30);
+
+/// Appended to get around Twitch's duplicate filter, Twitch trims trailing
+/// spaces but keeps this invisible tag character
+const DUPLICATE_BYPASS: &str = " \u{e0000}";
+
+/// `text`, with [DUPLICATE_BYPASS] appended if it's the same as `last`
+fn bypass_duplicate(text: &str, last: Option<&str>) -> String {
+    if last.is_some_and(|last| last.trim() == text.trim()) {
+        format!("{}{DUPLICATE_BYPASS}", text.trim_end())
+    } else {
+        text.to_owned()
+    }
+}
+
+/// `text` without the [DUPLICATE_BYPASS] this or another client appended to it
+fn without_bypass(text: &str) -> &str {
+    text.strip_suffix(DUPLICATE_BYPASS).unwrap_or(text)
+}
+
+/// The partial username after the `@` being typed at the end of `input`
+fn mention_prefix(input: &str) -> Option<&str> {
+    let word = input.rsplit(' ').next()?;
+    word.strip_prefix('@').filter(|p| !p.is_empty())
+}
+
+/// `input` with its trailing `@partial` replaced by the whole `@name`
+fn complete_mention(input: &str, name: &str) -> String {
+    match input.rsplit_once(' ') {
+        Some((kept, _)) => format!("{kept} @{name} "),
+        None => format!("@{name} "),
+    }
+}
+
+/// How the sender of `msg` is mentioned, by their display name unless it's
+/// more than a different casing of their login, like for CJK names
+fn mention_name(msg: &PrivMsg) -> Option<String> {
+    let login = msg.get_username()?.to_string();
+    Some(
+        msg.get_tag(OwnedTag::DisplayName)
+            .map(|d| d.to_string())
+            .filter(|d| d.eq_ignore_ascii_case(&login))
+            .unwrap_or(login),
+    )
+}
+
+/// Earlier and undone states of the message input. Typing a word is undone as
+/// a whole, other edits one at a time
+#[derive(Default)]
+struct EditHistory {
+    undo: VecDeque<String>,
+    redo: Vec<String>,
+    /// The last edit typed a character that later ones can be merged into
+    typing: bool,
+}
+
+impl EditHistory {
+    const MAX_STEPS: usize = 100;
+
+    /// Records `old` as the state before the input changed to `new`
+    fn record(&mut self, old: &str, new: &str) {
+        if old == new {
+            return;
+        }
+        let typed = new
+            .strip_prefix(old)
+            .is_some_and(|added| added.chars().count() == 1);
+        // the whitespace after a word still belongs to it
+        let merges = typed && self.typing && !old.ends_with(char::is_whitespace);
+        self.typing = typed;
+        if !merges {
+            self.checkpoint(old);
+        }
+    }
+
+    /// Records `current` as a state of its own, before an edit that's never
+    /// merged
+    fn checkpoint(&mut self, current: &str) {
+        self.redo.clear();
+        if self.undo.len() >= Self::MAX_STEPS {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(current.to_owned());
+    }
+
+    fn undo(&mut self, current: &str) -> Option<String> {
+        let previous = self.undo.pop_back()?;
+        self.redo.push(current.to_owned());
+        self.typing = false;
+        Some(previous)
+    }
+
+    fn redo(&mut self, current: &str) -> Option<String> {
+        let next = self.redo.pop()?;
+        self.undo.push_back(current.to_owned());
+        self.typing = false;
+        Some(next)
+    }
+
+    fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.typing = false;
+    }
+}
+
+/// Joins the lines of `text` with spaces, since chat messages are single-line
+fn single_line(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .join(" ")
+}
+
+/// Strips tracking query parameters from `word` if it's a link
+fn clean_link(word: &str) -> Cow<'_, str> {
+    fn is_tracking(key: &str) -> bool {
+        key.starts_with("utm_") || matches!(key, "si" | "feature" | "fbclid" | "gclid")
+    }
+
+    let Ok(mut url) = url::Url::parse(word) else {
+        return word.into();
+    };
+    if !matches!(url.scheme(), "http" | "https") || !url.query_pairs().any(|(k, _)| is_tracking(&k))
+    {
+        return word.into();
+    }
+
+    let kept = url
+        .query_pairs()
+        .filter(|(k, _)| !is_tracking(k))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect_vec();
+    if kept.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(kept);
+    }
+    url.to_string().into()
+}
+
+/// A cheermote followed by its amount of bits in the tier's color
+fn view_cheer(cheer: Cheer, font_size: f32, emote_scale: f32) -> Element<'static, Message> {
+    let image: Element<'static, Message> = match cheer.image() {
+        Some(img) => img.scaled(emote_scale).into(),
+        None => Text::new(cheer.prefix.clone()).size(font_size).into(),
+    };
+    row![
+        image,
+        Text::new(cheer.amount.to_string())
+            .size(font_size)
+            .color(cheer.color())
+    ]
+    .align_y(Alignment::Center)
+    .into()
+}
+
+/// Whether `text` contains any of the blocked `terms`, ignoring case
+fn is_blocked(text: &str, terms: &[BlockedTerm]) -> bool {
+    if terms.is_empty() {
+        return false;
+    }
+    let text = text.to_lowercase();
+    terms.iter().any(|t| t.matches(&text))
+}
+
+/// Whether `msg` was sent by one of the hidden `users`
+fn from_hidden_user(msg: &PrivMsg, users: &HashSet<String>) -> bool {
+    msg.get_username()
+        .is_some_and(|u| users.contains(u.to_string().as_str()))
+}
+
+/// WCAG relative luminance of `color`
+fn luminance(color: Color) -> f32 {
+    fn linear(c: f32) -> f32 {
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    }
+    0.2126 * linear(color.r) + 0.7152 * linear(color.g) + 0.0722 * linear(color.b)
+}
+
+/// WCAG contrast ratio between two colors, from 1 to 21
+fn contrast(a: Color, b: Color) -> f32 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Lightens or darkens `rgb` until it has at least `min_contrast` against
+/// `background`, keeping its hue
+fn readable_color([r, g, b]: [u8; 3], background: Color, min_contrast: f32) -> Color {
+    let mut hsl: palette::Hsl = palette::Srgb::new(r, g, b).into_format().into_color();
+    let step = if luminance(background) < 0.5 {
+        0.05
+    } else {
+        -0.05
+    };
+    loop {
+        let (r, g, b) = palette::Srgb::from_color(hsl)
+            .into_format()
+            .into_components();
+        let color = Color::from_rgb8(r, g, b);
+        if contrast(color, background) >= min_contrast
+            || !(0.0..=1.0).contains(&(hsl.lightness + step))
+        {
+            return color;
+        }
+        hsl.lightness += step;
+    }
+}
+
+fn view_history_error(error: &str) -> Element<'_, Message> {
+    container(
+        row![
+            Text::new(format!("Couldn't load history: {error}"))
+                .size(12)
+                .color(Color::from_rgb8(0xed, 0x87, 0x96))
+                .width(Length::Fill),
+            button(Text::new("Retry").size(12))
+                .on_press(Message::RetryHistory)
+                .style(button::secondary)
+                .padding(Padding::ZERO.vertical(2.0).horizontal(6.0)),
         ]
+        .spacing(6)
+        .align_y(Alignment::Center),
+    )
+    .padding(Padding::ZERO.vertical(4.0).horizontal(6.0))
+    .into()
+}
+
+fn pronoun_label(pronouns: String, size: f32) -> Element<'static, Message> {
+    Container::new(Text::new(pronouns).size(size))
+        .padding(Padding::ZERO.horizontal(4.0))
+        .style(|t: &iced::Theme| {
+            container::Style::default()
+                .background(t.extended_palette().background.strong.color)
+                .border(Border::default().rounded(4.0))
+        })
         .into()
+}
+
+fn message_padding(compact: bool) -> Padding {
+    Padding::default()
+        .vertical(if compact { 1.0 } else { 4.0 })
+        .horizontal(6.0)
+}
+
+/// The rule between messages, hidden in compact mode
+fn separator(compact: bool) -> Element<'static, Message> {
+    if compact {
+        space().into()
+    } else {
+        rule::horizontal(1).into()
     }
 }
 
-fn scroll_to_bottom() -> Element<'static, Message> {
+/// Actions for a message line, shown when it's right-clicked
+fn message_menu(msg: &PrivMsg, key: u64) -> Element<'static, Message> {
+    let entry = |label: &'static str, msg: Message| {
+        button(Text::new(label).size(14))
+            .style(button::subtle)
+            .width(Length::Fill)
+            .on_press(msg)
+            .into()
+    };
+    let login = msg.get_username().map(|u| u.to_string());
+    let raw = ARGS
+        .debug
+        .then(|| msg.inner().inner().trim().to_owned())
+        .map(|raw| entry("Copy raw IRC", Message::CopyToClipboard(raw)));
+    let entries = [
+        entry(
+            "Copy message",
+            Message::CopyToClipboard(without_bypass(msg.message_text()).to_owned()),
+        ),
+        entry("Select text", Message::SelectMessage(key)),
+    ]
+    .into_iter()
+    .chain(login.map(|l| entry("Hide user", Message::ToggleHideUser(l))))
+    .chain(raw);
+
+    Container::new(Column::from_iter(entries))
+        .width(160)
+        .padding(4)
+        .style(|t: &iced::Theme| {
+            container::Style::default()
+                .background(t.extended_palette().background.weak.color)
+                .border(Border::default().rounded(6.0))
+        })
+        .into()
+}
+
+/// Details of an emote, with its largest image
+fn view_emote_info(emote: &ChannelEmote) -> Element<'_, Message> {
+    let field = |label: &'static str, value: String| -> Element<'static, Message> {
+        row![
+            Text::new(label).size(14).width(110),
+            Text::new(value).size(14),
+        ]
+        .into()
+    };
+    let meta = &emote.metadata;
+    let flags = [
+        (EmoteFlags::OVERLAYING, "zero-width"),
+        (EmoteFlags::HIDDEN, "hidden"),
+    ]
+    .into_iter()
+    .filter(|(f, _)| meta.flags.contains(*f))
+    .map(|(_, name)| name)
+    .join(", ");
+    let fields = [
+        Some(field("Provider", meta.platform.to_string())),
+        Some(field("Name", meta.original_name.clone())),
+        emote.alias.clone().map(|a| field("Alias", a)),
+        (!flags.is_empty()).then(|| field("Flags", flags)),
+        Some(field("ID", meta.id.clone())),
+    ]
+    .into_iter()
+    .flatten();
+    let page = emote
+        .page_url()
+        .map(|url| Element::from(button("Open page").on_press(Message::OpenUrl(url))));
+    let buttons = page.into_iter().chain([
+        button("Copy URL")
+            .style(button::secondary)
+            .on_press(Message::CopyToClipboard(emote.cdn_url(CdnSize::FourX)))
+            .into(),
+        button("Close")
+            .style(button::secondary)
+            .on_press(Message::CloseEmoteInfo)
+            .into(),
+    ]);
+
+    container(opaque(
+        container(
+            column![
+                container(
+                    emote
+                        .view_largest()
+                        .map(|t| Message::LoadImage(Box::new(t)))
+                )
+                .center_x(Length::Fill),
+                Text::new(emote.text_name()).size(20),
+                Column::from_iter(fields).spacing(4),
+                Row::from_iter(buttons).spacing(6),
+            ]
+            .spacing(12),
+        )
+        .style(container::rounded_box)
+        .width(360)
+        .padding(20),
+    ))
+    .center(Length::Fill)
+    .into()
+}
+
+/// The text of `msg` in a read-only field, so any part of it can be selected
+/// and copied. Emotes are copied as their names, as that's how they're sent
+fn selectable_message(msg: &PrivMsg, id: widget::Id, compact: bool) -> Element<'static, Message> {
+    let username = msg
+        .get_tag(OwnedTag::DisplayName)
+        .or_else(|| msg.get_username().map(Into::into))
+        .unwrap_or_default();
+    let text = format!("{username}: {}", msg.message_text());
+    column![
+        Container::new(
+            row![
+                text_input("", &text)
+                    .id(id)
+                    .on_input(|_| Message::SelectionEdited)
+                    .on_submit(Message::StopSelecting)
+                    .width(Length::Fill),
+                button(Text::new("Done").size(12))
+                    .style(button::secondary)
+                    .on_press(Message::StopSelecting),
+            ]
+            .spacing(6)
+            .align_y(Alignment::Center),
+        )
+        .padding(message_padding(compact)),
+        separator(compact),
+    ]
+    .into()
+}
+
+/// A collapsed message that's revealed when clicked
+fn placeholder(label: &'static str, key: u64, compact: bool) -> Element<'static, Message> {
+    column![
+        mouse_area(
+            Container::new(Text::new(label).size(12))
+                .padding(message_padding(compact))
+                .width(Length::Fill)
+        )
+        .on_press(Message::RevealMessage(key))
+        .interaction(mouse::Interaction::Pointer),
+        separator(compact),
+    ]
+    .into()
+}
+
+fn view_system_line(line: &SystemLine, compact: bool) -> Element<'static, Message> {
+    column![
+        Container::new(Text::new(line.text.clone()).size(12))
+            .padding(message_padding(compact))
+            .width(Length::Fill),
+        separator(compact),
+    ]
+    .into()
+}
+
+fn connection_dot(state: ConnectionState) -> Element<'static, Message> {
+    let color = match state {
+        ConnectionState::Connecting => Color::from_rgb8(0xee, 0xd4, 0x9f),
+        ConnectionState::Connected => Color::from_rgb8(0xa6, 0xda, 0x95),
+        ConnectionState::Disconnected => Color::from_rgb8(0xed, 0x87, 0x96),
+    };
+    container(space())
+        .width(8)
+        .height(8)
+        .style(move |_| {
+            container::Style::default()
+                .background(color)
+                .border(Border::default().rounded(4.0))
+        })
+        .into()
+}
+
+fn scroll_to_bottom(unseen: usize) -> Element<'static, Message> {
+    let label = match unseen {
+        0 => "Scroll to Bottom".to_owned(),
+        1 => "1 new message".to_owned(),
+        n => format!("{n} new messages"),
+    };
     container::Container::new(
         mouse_area(
-            container::Container::new(Text::new("Scroll to Bottom"))
+            container::Container::new(Text::new(label))
                 .align_x(Alignment::Center)
                 .padding(Padding::ZERO.vertical(4.0).horizontal(8.0))
                 .style(|_| {
@@ -284,71 +2571,444 @@ fn scroll_to_bottom() -> Element<'static, Message> {
     .into()
 }
 
-fn view_irc(msg: &AnySemantic) -> Option<Element<'_, Message>> {
+/// Settings the ROOMSTATE sent on join has all of, updates only carry the one
+/// that changed
+const ROOM_SETTINGS: [&str; 5] = ["emote-only", "followers-only", "r9k", "slow", "subs-only"];
+
+/// NOTICEs repeating a room setting change that also comes as a ROOMSTATE
+const ROOM_MODE_NOTICES: [&str; 11] = [
+    "emote_only_on",
+    "emote_only_off",
+    "followers_on",
+    "followers_on_zero",
+    "followers_off",
+    "r9k_on",
+    "r9k_off",
+    "slow_on",
+    "slow_off",
+    "subs_on",
+    "subs_off",
+];
+
+/// Describes the setting a raw ROOMSTATE changed, [None] for the one sent on
+/// join
+fn room_setting_change(raw: &str) -> Option<String> {
+    let (tags, _) = raw.strip_prefix('@')?.split_once(' ')?;
+    let settings = tags
+        .split(';')
+        .filter_map(|t| t.split_once('='))
+        .filter(|(k, _)| ROOM_SETTINGS.contains(k))
+        .collect_vec();
+    if settings.len() == ROOM_SETTINGS.len() {
+        return None;
+    }
+    let text = match *settings.first()? {
+        ("slow", "0") => "Slow mode is off".to_owned(),
+        ("slow", s) => format!("Slow mode is on, one message every {s}s"),
+        ("followers-only", "-1") => "Followers-only mode is off".to_owned(),
+        ("followers-only", "0") => "Followers-only mode is on".to_owned(),
+        ("followers-only", m) => {
+            format!("Followers-only mode is on, for followers of at least {m} minutes")
+        }
+        (setting, value) => {
+            let name = match setting {
+                "emote-only" => "Emote-only mode",
+                "r9k" => "Unique chat mode",
+                _ => "Subscribers-only mode",
+            };
+            let state = if value == "0" { "off" } else { "on" };
+            format!("{name} is {state}")
+        }
+    };
+    Some(text)
+}
+
+/// The line shown in chat for a system event from IRC, [None] for events that
+/// aren't shown
+pub fn system_line(msg: &AnySemantic) -> Option<SystemLine> {
+    let line = |kind, text: String| Some(SystemLine { kind, text });
     match msg {
         AnySemantic::Pass(_) => None,
         AnySemantic::Nick(_) => None,
         AnySemantic::Join(join) => {
-            let chan = join.get_param(0)?;
-            Some(Rich::<(), _>::with_spans([Span::new("Joined "), Span::new(chan)]).into())
+            let chan = join.get_param(0)?.trim_start_matches('#');
+            Some(SystemLine::joined(chan))
         }
         AnySemantic::Part(part) => {
-            let chan = part.get_param(0)?;
-            Some(Rich::<(), _>::with_spans([Span::new("Parted "), Span::new(chan)]).into())
+            let chan = part.get_param(0)?.trim_start_matches('#');
+            line(SystemKind::JoinPart, format!("Parted #{chan}"))
         }
-        AnySemantic::Notice(notice) => todo!(),
-        AnySemantic::ClearMsg(clear_msg) => None,
+        // shown from the ROOMSTATE that comes with them
+        AnySemantic::Notice(notice)
+            if notice
+                .get_tag(OwnedTag::MsgId)
+                .is_some_and(|id| ROOM_MODE_NOTICES.contains(&&*id)) =>
+        {
+            None
+        }
+        AnySemantic::Notice(notice) => line(SystemKind::Notice, notice.get_param(1)?.to_string()),
+        AnySemantic::ClearMsg(_) => None,
         AnySemantic::ClearChat(clear_chat) => {
-            let target = clear_chat.target_login()?;
-            let duration = clear_chat.duration();
-            match duration {
-                twixel_core::irc_message::clearchat::TimeoutDuration::Permanent => Some(
-                    Rich::<(), _>::with_spans([
-                        Span::new("@"),
-                        Span::new(target),
-                        Span::new("was permanently banned"),
-                    ])
-                    .into(),
-                ),
-                twixel_core::irc_message::clearchat::TimeoutDuration::Temporary(duration) => Some(
-                    Rich::<(), _>::with_spans([
-                        Span::new("@"),
-                        Span::new(target),
-                        Span::new("was timed out for"),
-                        Span::new(duration.as_secs().to_string()),
-                        Span::new("s"),
-                    ])
-                    .into(),
-                ),
-            }
-        }
-        AnySemantic::HostTarget(_) => None,
-        AnySemantic::PrivMsg(priv_msg) => Some(todo!()),
-        AnySemantic::Ping(_) => {
-            if cfg!(debug_assertions) {
-                Some(Rich::<(), _>::with_spans([Span::new("Ping received from twitch.")]).into())
-            } else {
-                None
+            let target = clear_chat.target_login().map(|l| l.to_string());
+            let duration = match clear_chat.duration() {
+                TimeoutDuration::Permanent => None,
+                TimeoutDuration::Temporary(duration) => Some(duration),
+            };
+            Some(SystemLine::cleared(target.as_deref(), duration))
+        }
+        AnySemantic::HostTarget(host) => {
+            // the trailing param is "<target> <viewers>", with "-" as the target
+            // once hosting stops
+            let target = host.get_param(1)?.split(' ').next()?;
+            match target {
+                "-" | "" => line(SystemKind::Notice, "Exited host mode".to_owned()),
+                target => line(SystemKind::Notice, format!("Now hosting {target}")),
             }
         }
+        AnySemantic::PrivMsg(_) => None,
+        AnySemantic::Ping(_) if cfg!(debug_assertions) => line(
+            SystemKind::Connection,
+            "Ping received from Twitch".to_owned(),
+        ),
+        AnySemantic::Ping(_) => None,
         AnySemantic::Pong(_) => None,
         AnySemantic::Cap(_) => None,
-        AnySemantic::GlobalUserState(global_user_state) => todo!(),
-        AnySemantic::UserState(user_state) => todo!(),
-        AnySemantic::RoomState(room_state) => todo!(),
-        AnySemantic::UserNotice(user_notice) => todo!(),
-        AnySemantic::Reconnect(_) => Some(
-            Rich::<(), _>::with_spans([Span::new(
-                "Twitch has requested us to reconnect. Reconnecting...",
-            )])
-            .into(),
+        AnySemantic::GlobalUserState(_) => None,
+        AnySemantic::UserState(_) => None,
+        AnySemantic::RoomState(room_state) => line(
+            SystemKind::Notice,
+            room_setting_change(room_state.inner().inner())?,
+        ),
+        AnySemantic::UserNotice(_) => None,
+        AnySemantic::Reconnect(_) => line(
+            SystemKind::Connection,
+            "Twitch requested a reconnect, reconnecting...".to_owned(),
         ),
-        AnySemantic::Whisper(_) => todo!(),
+        AnySemantic::Whisper(_) => None,
         AnySemantic::UnsupportedError(_) => None,
         AnySemantic::UserList(_) => None,
-        AnySemantic::AuthSuccessful(_) => {
-            Some(Rich::<(), _>::with_spans([Span::new("Connected.")]).into())
-        }
+        AnySemantic::AuthSuccessful(_) => line(SystemKind::Connection, "Connected".to_owned()),
         AnySemantic::Useless(_) => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocked_terms_match_text_or_regex() {
+        let terms = ["spoiler", "/^!\\w+$/"].map(|t| BlockedTerm::new(t.to_owned()).unwrap());
+        assert!(is_blocked("no SPOILERS please", &terms));
+        assert!(is_blocked("!Commands", &terms));
+        assert!(!is_blocked("try !commands", &terms));
+        assert!(BlockedTerm::new("/(unclosed/".to_owned()).is_err());
+        // only between slashes it's a regex
+        assert!(BlockedTerm::new("(unclosed".to_owned()).is_ok());
+    }
+
+    #[test]
+    fn room_setting_changes() {
+        let join = "@emote-only=0;followers-only=-1;r9k=0;room-id=1;slow=0;subs-only=0 :tmi.twitch.tv ROOMSTATE #forsen";
+        assert_eq!(room_setting_change(join), None);
+        assert_eq!(
+            room_setting_change("@room-id=1;slow=30 :tmi.twitch.tv ROOMSTATE #forsen").as_deref(),
+            Some("Slow mode is on, one message every 30s")
+        );
+        assert_eq!(
+            room_setting_change("@emote-only=1;room-id=1 :tmi.twitch.tv ROOMSTATE #forsen")
+                .as_deref(),
+            Some("Emote-only mode is on")
+        );
+        assert_eq!(
+            room_setting_change("@followers-only=10;room-id=1 :tmi.twitch.tv ROOMSTATE #forsen")
+                .as_deref(),
+            Some("Followers-only mode is on, for followers of at least 10 minutes")
+        );
+    }
+
+    #[test]
+    fn mentions_are_completed() {
+        assert_eq!(mention_prefix("hi @par"), Some("par"));
+        assert_eq!(mention_prefix("@Par"), Some("Par"));
+        assert_eq!(mention_prefix("hi @"), None);
+        assert_eq!(mention_prefix("hi @par "), None);
+        assert_eq!(mention_prefix("email@par"), None);
+        assert_eq!(complete_mention("hi @par", "Parrot"), "hi @Parrot ");
+        assert_eq!(complete_mention("@par", "Parrot"), "@Parrot ");
+        assert_eq!(complete_mention(" @par", "Parrot"), " @Parrot ");
+    }
+
+    #[test]
+    fn duplicates_are_made_different() {
+        let bypassed = bypass_duplicate("hi chat", Some("hi chat"));
+        assert_ne!(bypassed, "hi chat");
+        assert_eq!(without_bypass(&bypassed), "hi chat");
+        // the bypassed one differs from the original again
+        assert_eq!(bypass_duplicate("hi chat", Some(&bypassed)), "hi chat");
+        assert_eq!(bypass_duplicate("hi chat", None), "hi chat");
+        assert_eq!(without_bypass("hi chat"), "hi chat");
+    }
+
+    #[test]
+    fn typed_words_are_undone_at_once() {
+        let mut edits = EditHistory::default();
+        let mut input = String::new();
+        for c in "hi chat".chars() {
+            let new = format!("{input}{c}");
+            edits.record(&input, &new);
+            input = new;
+        }
+        edits.record(&input, "hi cha");
+        input = "hi cha".to_owned();
+
+        let mut undone = vec![];
+        while let Some(previous) = edits.undo(&input) {
+            undone.push(previous.clone());
+            input = previous;
+        }
+        assert_eq!(undone, ["hi chat", "hi ", ""]);
+        assert_eq!(edits.redo(&input).as_deref(), Some("hi "));
+        assert_eq!(edits.redo("hi ").as_deref(), Some("hi chat"));
+        edits.record("hi chat", "hi chat!");
+        assert_eq!(edits.redo("hi chat!"), None);
+    }
+
+    #[test]
+    fn announce_commands_are_parsed() {
+        assert_eq!(
+            parse_announce("/announce hello"),
+            Some(Ok((AnnouncementColor::Primary, "hello")))
+        );
+        assert_eq!(
+            parse_announce("/announceBlue hi chat"),
+            Some(Ok((AnnouncementColor::Blue, "hi chat")))
+        );
+        assert!(matches!(parse_announce("/announcered hi"), Some(Err(_))));
+        assert!(matches!(parse_announce("/announce  "), Some(Err(_))));
+        assert_eq!(parse_announce("announce hello"), None);
+    }
+
+    #[test]
+    fn announcements_become_privmsgs() {
+        let raw = "@color=#1E90FF;login=forsen;msg-id=announcement;msg-param-color=BLUE \
+                   :tmi.twitch.tv USERNOTICE #forsen :hello chat\r\n";
+        assert_eq!(
+            announcement_line(raw).as_deref(),
+            Some(
+                "@color=#1E90FF;login=forsen;msg-id=announcement;msg-param-color=BLUE \
+                 :forsen!forsen@forsen.tmi.twitch.tv PRIVMSG #forsen :hello chat"
+            )
+        );
+        let sub = "@login=forsen;msg-id=sub :tmi.twitch.tv USERNOTICE #forsen";
+        assert_eq!(announcement_line(sub), None);
+    }
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(secs, 0).unwrap()
+    }
+
+    #[test]
+    fn history_is_merged_between_live_messages() {
+        // live messages received while the history loaded, one sent at the
+        // same time as a historical one
+        let mut messages = VecDeque::from([("live 1", 10, at(5)), ("live 2", 11, at(8))]);
+        merge_by_time(
+            &mut messages,
+            [
+                ("old 1", 12, at(1)),
+                ("old 2", 13, at(5)),
+                ("old 3", 14, at(6)),
+                ("old 4", 15, at(8)),
+            ],
+        );
+        assert_eq!(
+            messages.iter().map(|m| m.0).collect_vec(),
+            ["old 1", "live 1", "old 2", "old 3", "live 2", "old 4"]
+        );
+    }
+
+    #[test]
+    fn merging_into_empty_keeps_history_order() {
+        let mut messages = VecDeque::new();
+        merge_by_time(
+            &mut messages,
+            [("a", 1, at(1)), ("b", 2, at(1)), ("c", 3, at(2))],
+        );
+        assert_eq!(messages.iter().map(|m| m.0).collect_vec(), ["a", "b", "c"]);
+    }
+
+    fn privmsg(id: &str, text: &str, secs: i64) -> PrivMsg {
+        let line = format!(
+            "@id={id};tmi-sent-ts={} :forsen!forsen@forsen.tmi.twitch.tv PRIVMSG #forsen :{text}",
+            secs * 1000
+        );
+        IrcMessage::new(line)
+            .ok()
+            .and_then(|m| PrivMsg::from_message(m).ok())
+            .unwrap()
+    }
+
+    fn live(messages: &[(&str, &str, i64)]) -> VecDeque<(Arc<PrivMsg>, u64, DateTime<Utc>)> {
+        messages
+            .iter()
+            .zip(1..)
+            .map(|((id, text, secs), key)| (Arc::new(privmsg(id, text, *secs)), key, at(*secs)))
+            .collect()
+    }
+
+    fn texts(messages: &VecDeque<(Arc<PrivMsg>, u64, DateTime<Utc>)>) -> Vec<&str> {
+        messages.iter().map(|(m, ..)| m.message_text()).collect()
+    }
+
+    #[test]
+    fn live_messages_are_trimmed_at_max_scrollback() {
+        let mut messages = live(&[("a", "one", 1), ("b", "two", 2), ("c", "three", 3)]);
+        let pushed = push_live(
+            &mut messages,
+            Arc::new(privmsg("d", "four", 4)),
+            4,
+            3,
+            false,
+        );
+        assert_eq!(pushed, Pushed::Added(vec![1]));
+        assert_eq!(texts(&messages), ["two", "three", "four"]);
+        assert_eq!(trim_front(&mut messages, 1), [2, 3]);
+        assert!(trim_front(&mut messages, 5).is_empty());
+    }
+
+    #[test]
+    fn repeated_messages_are_combined() {
+        let mut messages = live(&[("a", "hi", 1)]);
+        let repeat = Arc::new(privmsg("b", "hi", 2));
+        assert_eq!(
+            push_live(&mut messages, repeat.clone(), 2, 10, true),
+            Pushed::Repeat(1)
+        );
+        assert_eq!(texts(&messages), ["hi"]);
+        assert_eq!(
+            push_live(&mut messages, repeat, 2, 10, false),
+            Pushed::Added(vec![])
+        );
+        assert_eq!(texts(&messages), ["hi", "hi"]);
+
+        let other = IrcMessage::new(
+            "@id=c;tmi-sent-ts=3000 :xqc!xqc@xqc.tmi.twitch.tv PRIVMSG #forsen :hi".to_owned(),
+        )
+        .ok()
+        .and_then(|m| PrivMsg::from_message(m).ok())
+        .unwrap();
+        assert_eq!(
+            push_live(&mut messages, Arc::new(other), 3, 10, true),
+            Pushed::Added(vec![])
+        );
+    }
+
+    #[test]
+    fn history_received_live_is_skipped() {
+        // joined in time to get the last two messages live
+        let mut messages = live(&[("b", "two", 2), ("c", "three", 3)]);
+        let history = [
+            privmsg("z", "zero", 0),
+            privmsg("a", "one", 1),
+            privmsg("b", "two", 2),
+            privmsg("c", "three", 3),
+        ];
+        let mut keys = 10..;
+        let dropped = merge_history(&mut messages, history, || keys.next().unwrap(), 3);
+        assert_eq!(dropped, [10]);
+        assert_eq!(texts(&messages), ["one", "two", "three"]);
+        assert_eq!(messages.iter().map(|m| m.1).collect_vec(), [11, 1, 2]);
+    }
+
+    #[test]
+    fn emotes_are_found_by_code_point() {
+        use Segment::*;
+        assert_eq!(
+            segments("ねこ Kappa", [&[3..=7][..]]),
+            [Word("ねこ"), Emote(0)]
+        );
+        assert_eq!(
+            segments("Kappa Kappa LUL", [&[0..=4, 6..=10][..], &[12..=14]]),
+            [Emote(0), Emote(0), Emote(1)]
+        );
+        assert_eq!(segments("Kappa", [&[0..=4][..]]), [Emote(0)]);
+        assert_eq!(segments("hi Kappa", [&[3..=7][..]]), [Word("hi"), Emote(0)]);
+    }
+
+    #[test]
+    fn emotes_after_emoji_are_found() {
+        use Segment::*;
+        assert_eq!(segments("😂 Kappa", [&[2..=6][..]]), [Word("😂"), Emote(0)]);
+        // a family is five code points joined into one emoji, the accent is a
+        // combining character
+        assert_eq!(
+            segments("👨\u{200d}👩\u{200d}👧 cafe\u{301} Kappa", [&[12..=16][..]]),
+            [
+                Word("👨\u{200d}👩\u{200d}👧"),
+                Word("cafe\u{301}"),
+                Emote(0)
+            ]
+        );
+    }
+
+    #[test]
+    fn emote_ranges_must_cover_words() {
+        use Segment::*;
+        // leading and doubled spaces make empty words
+        assert_eq!(
+            segments(" Kappa  LUL", [&[1..=5][..], &[8..=10]]),
+            [Word(""), Emote(0), Word(""), Emote(1)]
+        );
+        assert_eq!(
+            segments("Kappa123 x", [&[0..=4][..], &[0..=10]]),
+            [Word("Kappa123"), Word("x")]
+        );
+        assert_eq!(segments("", [&[0..=0][..]]), [Word("")]);
+    }
+
+    #[test]
+    fn emote_search_ignores_case() {
+        assert!(matches_search("KEKW", "kek"));
+        assert!(matches_search("peepoHappy", "HAPPY"));
+        assert!(matches_search("Kappa", ""));
+        assert!(!matches_search("Kappa", "kappa123"));
+    }
+
+    #[test]
+    fn pasted_lines_are_joined() {
+        assert_eq!(single_line("hello\n  world \r\n\nxd"), "hello world xd");
+        assert_eq!(single_line("one line"), "one line");
+    }
+
+    #[test]
+    fn tracking_params_are_removed() {
+        assert_eq!(
+            clean_link("https://youtu.be/dQw4w9WgXcQ?si=abcdef"),
+            "https://youtu.be/dQw4w9WgXcQ"
+        );
+        assert_eq!(
+            clean_link("https://example.com/?a=1&utm_source=twitch"),
+            "https://example.com/?a=1"
+        );
+        assert_eq!(
+            clean_link("https://example.com/?a=1"),
+            "https://example.com/?a=1"
+        );
+        assert_eq!(clean_link("not_a_link"), "not_a_link");
+    }
+
+    #[test]
+    fn username_colors_are_readable() {
+        let dark = Color::from_rgb8(0x24, 0x27, 0x3a);
+        let light = Color::WHITE;
+        for rgb in [[0, 0, 255], [255, 255, 0], [96, 96, 96]] {
+            assert!(contrast(readable_color(rgb, dark, 3.0), dark) >= 3.0);
+            assert!(contrast(readable_color(rgb, light, 3.0), light) >= 3.0);
+        }
+        assert_eq!(
+            readable_color([255, 255, 255], dark, 3.0),
+            Color::from_rgb8(255, 255, 255)
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    sync::mpsc,
+};
+
+use chrono::{DateTime, Local, NaiveDate, SecondsFormat, Utc};
+use hashbrown::HashMap;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use twixel_core::irc_message::{PrivMsg, SemanticIrcMessage, tags::OwnedTag};
+
+use crate::config::logs_dir;
+
+/// A message as written to chat logs, with emotes as their names
+pub struct LogEntry {
+    sent: DateTime<Utc>,
+    username: String,
+    text: String,
+}
+
+impl LogEntry {
+    pub fn new(msg: &PrivMsg, sent: DateTime<Utc>) -> Self {
+        let username = msg
+            .get_tag(OwnedTag::DisplayName)
+            .or_else(|| msg.get_username().map(Into::into))
+            .unwrap_or_default()
+            .to_string();
+        Self {
+            sent,
+            username,
+            text: msg.message_text().to_owned(),
+        }
+    }
+
+    /// `[2024-01-31 18:00:00] user: text`, in local time
+    pub fn to_line(&self) -> String {
+        format!(
+            "[{}] {}: {}",
+            self.sent.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            self.username,
+            self.text
+        )
+    }
+
+    /// An object with the timestamp in UTC
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "timestamp": self.sent.to_rfc3339_opts(SecondsFormat::Millis, true),
+            "username": self.username,
+            "text": self.text,
+        })
+    }
+}
+
+/// `entries` as a JSON array of objects with UTC timestamps
+pub fn json_log(entries: &[LogEntry]) -> String {
+    let entries = entries.iter().map(LogEntry::to_json).collect_vec();
+    serde_json::to_string_pretty(&entries).unwrap_or_default()
+}
+
+/// How messages are written to the continuous logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line
+    JsonLines,
+}
+
+impl LogFormat {
+    pub const ALL: [Self; 2] = [Self::Text, Self::JsonLines];
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "log",
+            Self::JsonLines => "jsonl",
+        }
+    }
+
+    fn line(self, entry: &LogEntry) -> String {
+        match self {
+            Self::Text => entry.to_line(),
+            Self::JsonLines => entry.to_json().to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Text => "Text",
+            Self::JsonLines => "JSON lines",
+        })
+    }
+}
+
+enum Command {
+    Append {
+        channel: String,
+        date: NaiveDate,
+        format: LogFormat,
+        line: String,
+    },
+    CloseAll,
+}
+
+/// Appends received messages to a file per channel and day under
+/// [logs_dir], on a thread of its own so a slow disk doesn't hold up the UI
+pub struct ChatLogger {
+    commands: mpsc::Sender<Command>,
+    /// What the writer thread couldn't write, it stops until [Self::close_all]
+    errors: mpsc::Receiver<std::io::Error>,
+}
+
+impl Default for ChatLogger {
+    fn default() -> Self {
+        let (commands, rx) = mpsc::channel();
+        let (errors_tx, errors) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("chat log".to_owned())
+            .spawn(move || write_logs(rx, errors_tx))
+            .expect("couldn't start the chat log thread");
+        Self { commands, errors }
+    }
+}
+
+impl ChatLogger {
+    /// Queues `entry` for today's log of `channel`, failing with the error
+    /// the writer ran into since the last call
+    pub fn append(
+        &mut self,
+        channel: &str,
+        entry: &LogEntry,
+        format: LogFormat,
+    ) -> std::io::Result<()> {
+        if let Ok(e) = self.errors.try_recv() {
+            return Err(e);
+        }
+        let _ = self.commands.send(Command::Append {
+            channel: channel.to_owned(),
+            date: Local::now().date_naive(),
+            format,
+            line: format.line(entry),
+        });
+        Ok(())
+    }
+
+    /// Closes every open log, forgetting earlier errors
+    pub fn close_all(&mut self) {
+        while self.errors.try_recv().is_ok() {}
+        let _ = self.commands.send(Command::CloseAll);
+    }
+}
+
+/// Runs the writer thread until its [ChatLogger] is dropped, flushing once
+/// nothing else is queued
+fn write_logs(commands: mpsc::Receiver<Command>, errors: mpsc::Sender<std::io::Error>) {
+    // the open log of each channel, with the day and format it was opened for
+    let mut files: HashMap<String, (NaiveDate, LogFormat, BufWriter<File>)> = HashMap::new();
+    let mut failed = false;
+    while let Ok(first) = commands.recv() {
+        let mut res = Ok(());
+        for command in std::iter::once(first).chain(commands.try_iter()) {
+            match command {
+                Command::CloseAll => {
+                    for (_, _, file) in files.values_mut() {
+                        let _ = file.flush();
+                    }
+                    files.clear();
+                    failed = false;
+                }
+                Command::Append { .. } if failed => (),
+                Command::Append {
+                    channel,
+                    date,
+                    format,
+                    line,
+                } => {
+                    res = write_line(&mut files, channel, date, format, &line);
+                    if res.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        let res = res.and_then(|()| files.values_mut().try_for_each(|(_, _, file)| file.flush()));
+        if let Err(e) = res {
+            failed = true;
+            files.clear();
+            let _ = errors.send(e);
+        }
+    }
+}
+
+/// Writes `line` to the log of `channel` for `date`, starting a new file when
+/// the day or the format changed
+fn write_line(
+    files: &mut HashMap<String, (NaiveDate, LogFormat, BufWriter<File>)>,
+    channel: String,
+    date: NaiveDate,
+    format: LogFormat,
+    line: &str,
+) -> std::io::Result<()> {
+    if files
+        .get(&channel)
+        .is_none_or(|(d, f, _)| *d != date || *f != format)
+    {
+        let file = BufWriter::new(open_log(&channel, date, format)?);
+        files.insert(channel.clone(), (date, format, file));
+    }
+    let (_, _, file) = files.get_mut(&channel).expect("opened above");
+    writeln!(file, "{line}")
+}
+
+fn open_log(channel: &str, date: NaiveDate, format: LogFormat) -> std::io::Result<File> {
+    let dir = logs_dir().join(channel);
+    std::fs::create_dir_all(&dir)?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(format!("{date}.{}", format.extension())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logs_export_as_json() {
+        use chrono::TimeZone;
+
+        let entries = [LogEntry {
+            sent: Utc.with_ymd_and_hms(2024, 1, 31, 18, 0, 0).unwrap(),
+            username: "forsen".into(),
+            text: "hello \"chat\" KEKW".into(),
+        }];
+        let json: serde_json::Value = serde_json::from_str(&json_log(&entries)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([{
+                "timestamp": "2024-01-31T18:00:00.000Z",
+                "username": "forsen",
+                "text": "hello \"chat\" KEKW",
+            }])
+        );
+
+        let line = LogFormat::JsonLines.line(&entries[0]);
+        assert!(!line.contains('\n'));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&line).unwrap(),
+            json[0]
+        );
+    }
+}
@@ -6,6 +6,12 @@ use clap::Parser;
 pub struct Args {
     #[arg(long)]
     pub config: Option<PathBuf>,
+    /// Copy the raw IRC line of a message when right-clicking it
+    #[arg(long)]
+    pub debug: bool,
+    /// Show a scripted chat instead of connecting to Twitch
+    #[arg(long)]
+    pub demo: bool,
 }
 
 pub static ARGS: LazyLock<Args> = LazyLock::new(Args::parse);
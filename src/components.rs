@@ -1 +1,2 @@
 pub mod join_popup;
+pub mod setup;
@@ -1,7 +1,10 @@
 use iced::{
-    Element, Task,
-    widget::{self, button, column, container, row, sensor, text_input},
+    Color, Element, Length, Padding, Task,
+    widget::{self, Column, Text, button, column, container, row, sensor, text_input},
 };
+use itertools::Itertools;
+
+use crate::config::CONFIG;
 
 pub struct JoinPopup {
     pub value: String,
@@ -12,11 +15,38 @@ pub struct JoinPopup {
 pub enum Message {
     Shown,
     ChannelChange(String),
+    SuggestionSelected(String),
     Submit,
     Close,
 }
 
+/// Whether `login` is a valid Twitch login: 4 to 25 characters of lowercase
+/// ASCII letters, digits and underscores
+pub fn is_valid_login(login: &str) -> bool {
+    (4..=25).contains(&login.len())
+        && login
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Fuzzy-matches `query` against `candidate`, returning a score where lower is
+/// better, or [None] if the characters of `query` don't appear in order.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<usize> {
+    if let Some(pos) = candidate.find(query) {
+        return Some(pos);
+    }
+    let mut chars = candidate.char_indices();
+    let mut last = 0;
+    for q in query.chars() {
+        let (i, _) = chars.find(|(_, c)| *c == q)?;
+        last = i;
+    }
+    Some(candidate.len() + last)
+}
+
 impl JoinPopup {
+    const MAX_SUGGESTIONS: usize = 5;
+
     pub fn new() -> Self {
         Self {
             value: String::new(),
@@ -24,16 +54,68 @@ impl JoinPopup {
         }
     }
 
+    /// The trimmed and lowercased login typed into the popup
+    pub fn login(&self) -> String {
+        self.value.trim().to_lowercase()
+    }
+
+    /// Recently joined channels matching the current input, best matches first
+    fn suggestions(&self) -> Vec<String> {
+        let login = self.login();
+        if login.is_empty() {
+            return Vec::new();
+        }
+        CONFIG
+            .read()
+            .recent_channels
+            .iter()
+            .filter(|c| **c != login)
+            .filter_map(|c| Some((fuzzy_score(&login, c)?, c)))
+            .sorted_by_key(|(score, _)| *score)
+            .take(Self::MAX_SUGGESTIONS)
+            .map(|(_, c)| c.clone())
+            .collect()
+    }
+
     pub fn view(&self) -> Element<'_, Message> {
+        let suggestions = self
+            .suggestions()
+            .into_iter()
+            .map(|c| {
+                Element::from(
+                    button(Text::new(c.clone()))
+                        .on_press(Message::SuggestionSelected(c))
+                        .style(button::subtle)
+                        .padding(Padding::ZERO.vertical(2.0).horizontal(6.0))
+                        .width(Length::Fill),
+                )
+            })
+            .collect::<Column<Message>>();
+
+        let login = self.login();
+        let valid = is_valid_login(&login);
+        let submit = valid.then_some(Message::Submit);
+
+        let hint: Element<'_, Message> = if valid || login.is_empty() {
+            Text::new("").size(12).into()
+        } else {
+            Text::new("Logins are 4-25 letters, numbers or underscores")
+                .size(12)
+                .color(Color::from_rgb8(0xed, 0x87, 0x96))
+                .into()
+        };
+
         sensor(
             container(column![
                 text_input("Twitch Login", &self.value)
                     .id(self.input_id.clone())
                     .on_input(Message::ChannelChange)
-                    .on_submit(Message::Submit)
+                    .on_submit_maybe(submit.clone())
                     .width(300.0),
+                suggestions,
+                hint,
                 row![
-                    button("Confirm").on_press(Message::Submit),
+                    button("Confirm").on_press_maybe(submit),
                     button("Cancel").on_press(Message::Close)
                 ]
             ])
@@ -48,9 +130,37 @@ impl JoinPopup {
         match msg {
             Message::Shown => return iced::widget::operation::focus(self.input_id.clone()),
             Message::ChannelChange(c) => self.value = c,
+            Message::SuggestionSelected(c) => {
+                self.value = c;
+                return iced::widget::operation::focus(self.input_id.clone());
+            }
             Message::Submit => (),
             Message::Close => (),
         };
         Task::none()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_matching() {
+        assert_eq!(fuzzy_score("for", "forsen"), Some(0));
+        assert!(fuzzy_score("fsn", "forsen").is_some());
+        assert!(fuzzy_score("fsn", "forsen") > fuzzy_score("sen", "forsen"));
+        assert_eq!(fuzzy_score("xqc", "forsen"), None);
+    }
+
+    #[test]
+    fn login_validation() {
+        assert!(is_valid_login("forsen"));
+        assert!(is_valid_login("xqc_1"));
+        assert!(!is_valid_login(""));
+        assert!(!is_valid_login("abc"));
+        assert!(!is_valid_login("Forsen"));
+        assert!(!is_valid_login("for sen"));
+        assert!(!is_valid_login(&"a".repeat(26)));
+    }
+}
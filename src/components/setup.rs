@@ -0,0 +1,190 @@
+use iced::{
+    Alignment, Color, Element, Length, Task,
+    widget::{self, Text, button, column, container, row, sensor, text_input},
+};
+
+use crate::{
+    components::join_popup::is_valid_login,
+    config::{Account, CONFIG},
+};
+
+/// Shown instead of the chats on the first launch, while there are neither
+/// accounts nor channels in the config
+pub struct Setup {
+    step: Step,
+    username: String,
+    token: String,
+    channel: String,
+    input_id: widget::Id,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Account,
+    Channel,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Shown,
+    UsernameChange(String),
+    TokenChange(String),
+    AddAccount,
+    ContinueAnonymously,
+    ChannelChange(String),
+    /// Intercepted by the parent to join [Setup::channel]
+    Join,
+    /// Intercepted by the parent to close the setup without joining anything
+    Skip,
+}
+
+/// `token` without the `oauth:` prefix it's often copied with
+fn clean_token(token: &str) -> &str {
+    let token = token.trim();
+    token.strip_prefix("oauth:").unwrap_or(token)
+}
+
+impl Setup {
+    pub fn new() -> Self {
+        Self {
+            step: Step::Account,
+            username: String::new(),
+            token: String::new(),
+            channel: String::new(),
+            input_id: widget::Id::unique(),
+        }
+    }
+
+    /// Whether this is the first launch, with nothing configured yet
+    pub fn is_needed() -> bool {
+        let cfg = CONFIG.read();
+        cfg.accounts.is_empty() && cfg.chats.is_empty()
+    }
+
+    /// The trimmed and lowercased channel to join first
+    pub fn channel(&self) -> String {
+        self.channel.trim().to_lowercase()
+    }
+
+    fn username(&self) -> String {
+        self.username.trim().to_lowercase()
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let content = match self.step {
+            Step::Account => self.view_account(),
+            Step::Channel => self.view_channel(),
+        };
+        container(
+            sensor(
+                container(content)
+                    .style(container::rounded_box)
+                    .padding(20)
+                    .width(360),
+            )
+            .on_show(|_| Message::Shown),
+        )
+        .center(Length::Fill)
+        .into()
+    }
+
+    fn view_account(&self) -> Element<'_, Message> {
+        let can_add = is_valid_login(&self.username()) && !clean_token(&self.token).is_empty();
+        let add = can_add.then_some(Message::AddAccount);
+        column![
+            Text::new("Welcome to Juliarino").size(20),
+            Text::new("Add your Twitch account to chat, or continue without one to only read.")
+                .size(14),
+            text_input("Username", &self.username)
+                .id(self.input_id.clone())
+                .on_input(Message::UsernameChange),
+            text_input("OAuth token", &self.token)
+                .secure(true)
+                .on_input(Message::TokenChange)
+                .on_submit_maybe(add.clone()),
+            row![
+                button("Add account").on_press_maybe(add),
+                button("Continue anonymously")
+                    .style(button::secondary)
+                    .on_press(Message::ContinueAnonymously),
+            ]
+            .spacing(6),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    fn view_channel(&self) -> Element<'_, Message> {
+        let login = self.channel();
+        let valid = is_valid_login(&login);
+        let join = valid.then_some(Message::Join);
+        let hint: Element<'_, Message> = if valid || login.is_empty() {
+            Text::new("").size(12).into()
+        } else {
+            Text::new("Logins are 4-25 letters, numbers or underscores")
+                .size(12)
+                .color(Color::from_rgb8(0xed, 0x87, 0x96))
+                .into()
+        };
+        column![
+            Text::new("Join a channel").size(20),
+            text_input("Twitch Login", &self.channel)
+                .id(self.input_id.clone())
+                .on_input(Message::ChannelChange)
+                .on_submit_maybe(join.clone()),
+            hint,
+            row![
+                button("Join").on_press_maybe(join),
+                button("Skip")
+                    .style(button::secondary)
+                    .on_press(Message::Skip),
+            ]
+            .spacing(6)
+            .align_y(Alignment::Center),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    pub fn update(&mut self, msg: Message) -> Task<Message> {
+        match msg {
+            Message::Shown => return iced::widget::operation::focus(self.input_id.clone()),
+            Message::UsernameChange(u) => self.username = u,
+            Message::TokenChange(t) => self.token = t,
+            Message::AddAccount => {
+                let account = Account::new(self.username(), clean_token(&self.token).to_owned());
+                let mut cfg = CONFIG.write();
+                cfg.accounts.push(account);
+                if let Err(e) = cfg.save() {
+                    log::error!("Error when saving settings: {e}");
+                }
+                drop(cfg);
+                self.token.clear();
+                return self.next_step();
+            }
+            Message::ContinueAnonymously => return self.next_step(),
+            Message::ChannelChange(c) => self.channel = c,
+            Message::Join => (),
+            Message::Skip => (),
+        }
+        Task::none()
+    }
+
+    fn next_step(&mut self) -> Task<Message> {
+        self.step = Step::Channel;
+        // the channel input takes the id of the username one
+        iced::widget::operation::focus(self.input_id.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_cleaned() {
+        assert_eq!(clean_token(" oauth:abc123 "), "abc123");
+        assert_eq!(clean_token("abc123"), "abc123");
+        assert_eq!(clean_token("   "), "");
+    }
+}
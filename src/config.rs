@@ -1,13 +1,29 @@
 use std::{
+    collections::{BTreeMap, BTreeSet},
     io::{self, Write},
+    ops::RangeInclusive,
     path::{Path, PathBuf},
-    sync::LazyLock,
+    sync::{
+        LazyLock,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
 };
 
+use chrono::{DateTime, Utc};
+use iced::{Theme, theme};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 
-use crate::cli::ARGS;
+use crate::{
+    chat::SystemKind,
+    chat_log::LogFormat,
+    cli::ARGS,
+    keybinds::Keybindings,
+    platform::{
+        EmotePlatform,
+        twitch::auth::{Token, Validation},
+    },
+};
 
 pub static CONFIG: LazyLock<RwLock<Config>> = LazyLock::new(|| {
     log::info!(
@@ -25,6 +41,10 @@ pub static CONFIG: LazyLock<RwLock<Config>> = LazyLock::new(|| {
     RwLock::new(config)
 });
 
+/// Bumped whenever a setting that affects how messages are rendered changes,
+/// so already rendered messages get rebuilt
+pub static SETTINGS_GENERATION: AtomicU64 = AtomicU64::new(0);
+
 static CONFIG_FILE_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
     ARGS.config
         .clone()
@@ -35,30 +55,832 @@ static CONFIG_FILE_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
 pub struct Config {
     pub accounts: Vec<Account>,
     pub chats: Vec<String>,
+    /// Most recently joined channels, newest first
+    #[serde(default)]
+    pub recent_channels: Vec<String>,
+    /// Channel whose tab was selected last
+    #[serde(default)]
+    pub last_active: Option<String>,
+    /// Per-channel logins of users whose messages are hidden
+    #[serde(default)]
+    pub hidden_users: BTreeMap<String, BTreeSet<String>>,
+    /// Messages containing or matching any of these terms are hidden, ignoring
+    /// case
+    #[serde(default)]
+    pub blocked_terms: Vec<BlockedTerm>,
+    /// Messages containing any of these terms are highlighted, ignoring case
+    #[serde(default)]
+    pub highlight_terms: Vec<String>,
+    #[serde(default)]
+    pub sounds: Sounds,
+    #[serde(default)]
+    pub logging: Logging,
+    #[serde(default)]
+    pub keybindings: Keybindings,
     #[serde(default)]
     pub ui: UiConfig,
+    #[serde(default)]
+    pub endpoints: Endpoints,
+    /// `http://`, `https://` or `socks5://` proxy for all HTTP requests (emotes,
+    /// badges, history and the APIs). The IRC connection doesn't go through
+    /// it, twixel_core's `Connection` opens its socket itself and takes no
+    /// proxy
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Keep account tokens in the config file instead of the system keyring
+    #[serde(default)]
+    pub tokens_in_file: bool,
+}
+
+/// A term messages are hidden for, a regex if it's between slashes like
+/// `/^!\w+/`
+#[derive(Debug)]
+pub struct BlockedTerm {
+    term: String,
+    /// The term matched against the lowercase text
+    lowercase: String,
+    regex: Option<regex::Regex>,
+}
+
+impl BlockedTerm {
+    /// Fails if it's a regex that doesn't compile
+    pub fn new(term: String) -> Result<Self, regex::Error> {
+        let pattern = term
+            .strip_prefix('/')
+            .and_then(|t| t.strip_suffix('/'))
+            .filter(|p| !p.is_empty());
+        let regex = match pattern {
+            Some(p) => Some(regex::RegexBuilder::new(p).case_insensitive(true).build()?),
+            None => None,
+        };
+        Ok(Self {
+            regex,
+            ..Self::plain(term)
+        })
+    }
+
+    /// Matched as plain text even if it's between slashes
+    fn plain(term: String) -> Self {
+        Self {
+            lowercase: term.to_lowercase(),
+            term,
+            regex: None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.term
+    }
+
+    /// Whether the lowercase `text` contains the term or matches its regex
+    pub fn matches(&self, text: &str) -> bool {
+        match &self.regex {
+            Some(regex) => regex.is_match(text),
+            None => !self.lowercase.is_empty() && text.contains(&self.lowercase),
+        }
+    }
+}
+
+impl Serialize for BlockedTerm {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.term)
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockedTerm {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let term = String::deserialize(deserializer)?;
+        // matched as plain text, rather than failing to load the whole config
+        Ok(Self::new(term.clone()).unwrap_or_else(|e| {
+            log::warn!("Blocked term {term} isn't a valid regex: {e}");
+            Self::plain(term)
+        }))
+    }
+}
+
+/// Sounds played when a message is highlighted
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sounds {
+    /// Played when one of the accounts is mentioned
+    pub mention: Option<PathBuf>,
+    /// Played for messages containing the highlight term, by term
+    pub terms: BTreeMap<String, PathBuf>,
+    /// From 0 to 1
+    pub volume: f32,
+}
+
+impl Default for Sounds {
+    fn default() -> Self {
+        Self {
+            mention: None,
+            terms: BTreeMap::new(),
+            volume: 0.8,
+        }
+    }
+}
+
+/// Continuous logging of received messages to [logs_dir]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Logging {
+    pub enabled: bool,
+    pub format: LogFormat,
+}
+
+/// Base URLs of the APIs and CDNs used, for proxies and mirrors. Written
+/// without a trailing slash.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Endpoints {
+    pub seventv_api: String,
+    pub seventv_cdn: String,
+    pub bttv_api: String,
+    pub bttv_cdn: String,
+    pub ffz_api: String,
+    pub ffz_cdn: String,
+    pub twitch_cdn: String,
+    pub recent_messages: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            seventv_api: "https://7tv.io/v3".into(),
+            seventv_cdn: "https://cdn.7tv.app".into(),
+            bttv_api: "https://api.betterttv.net/3".into(),
+            bttv_cdn: "https://cdn.betterttv.net".into(),
+            ffz_api: "https://api.frankerfacez.com/v1".into(),
+            ffz_cdn: "https://cdn.frankerfacez.com".into(),
+            twitch_cdn: "https://static-cdn.jtvnw.net".into(),
+            recent_messages: "https://recent-messages.robotty.de/api/v2".into(),
+        }
+    }
+}
+
+impl Endpoints {
+    /// Replaces invalid URLs with their defaults and strips trailing slashes
+    fn validate(&mut self) {
+        let defaults = Endpoints::default();
+        for (value, default) in [
+            (&mut self.seventv_api, defaults.seventv_api),
+            (&mut self.seventv_cdn, defaults.seventv_cdn),
+            (&mut self.bttv_api, defaults.bttv_api),
+            (&mut self.bttv_cdn, defaults.bttv_cdn),
+            (&mut self.ffz_api, defaults.ffz_api),
+            (&mut self.ffz_cdn, defaults.ffz_cdn),
+            (&mut self.twitch_cdn, defaults.twitch_cdn),
+            (&mut self.recent_messages, defaults.recent_messages),
+        ] {
+            if let Err(e) = url::Url::parse(value) {
+                log::warn!("Invalid endpoint \"{value}\" ({e}), using {default} instead");
+                *value = default;
+            }
+            let len = value.trim_end_matches('/').len();
+            value.truncate(len);
+        }
+    }
 }
 
+/// Service the tokens are stored under in the system keyring
+const KEYRING_SERVICE: &str = "juliarino";
+
+/// Set once storing a token in the keyring failed, so the tokens stay in the
+/// file without warning on every save
+static KEYRING_UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+/// A token, left out of the config file once it's stored in the keyring
+#[derive(Default)]
+struct Secret {
+    value: String,
+    in_keyring: bool,
+}
+
+impl Secret {
+    fn new(value: String) -> Self {
+        Self {
+            value,
+            in_keyring: false,
+        }
+    }
+
+    fn in_keyring(&self) -> bool {
+        self.in_keyring
+    }
+
+    /// Reads the secret stored as `user`, [None] if there's none
+    ///
+    /// If the keyring can't be read the secret is left empty but still marked
+    /// as stored, so saving doesn't replace the one in the keyring
+    fn from_keyring(user: &str) -> Option<Self> {
+        let res = keyring::Entry::new(KEYRING_SERVICE, user).and_then(|e| e.get_password());
+        let value = match res {
+            Ok(value) => value,
+            Err(keyring::Error::NoEntry) => return None,
+            Err(e) => {
+                log::warn!("Couldn't read {user} from the keyring: {e}");
+                String::new()
+            }
+        };
+        Some(Self {
+            value,
+            in_keyring: true,
+        })
+    }
+
+    /// Moves the secret into the keyring as `user`, keeping it in the file if
+    /// there's no keyring
+    fn store(&mut self, user: &str) {
+        if self.in_keyring || self.value.is_empty() || KEYRING_UNAVAILABLE.load(Ordering::Relaxed) {
+            return;
+        }
+        let res =
+            keyring::Entry::new(KEYRING_SERVICE, user).and_then(|e| e.set_password(&self.value));
+        match res {
+            Ok(()) => self.in_keyring = true,
+            Err(e) => {
+                log::warn!("Couldn't use the keyring, keeping tokens in the config file: {e}");
+                KEYRING_UNAVAILABLE.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Moves the secret back into the file, unless it couldn't be read from
+    /// the keyring
+    fn unstore(&mut self, user: &str) {
+        if self.in_keyring && !self.value.is_empty() {
+            self.in_keyring = false;
+            delete_from_keyring(user);
+        }
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.value)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+fn delete_from_keyring(user: &str) {
+    let res = keyring::Entry::new(KEYRING_SERVICE, user).and_then(|e| e.delete_credential());
+    if let Err(e) = res
+        && !matches!(e, keyring::Error::NoEntry)
+    {
+        log::warn!("Couldn't delete {user} from the keyring: {e}");
+    }
+}
+
+fn refresh_token_in_keyring(token: &Option<Secret>) -> bool {
+    token.as_ref().is_none_or(Secret::in_keyring)
+}
+
+/// The tokens are only written to the TOML if they aren't in the keyring, which
+/// stores them under the username
 #[derive(Serialize, Deserialize)]
 pub struct Account {
     username: String,
-    token: String,
+    #[serde(default, skip_serializing_if = "Secret::in_keyring")]
+    token: Secret,
+    /// Exchanged for a new token before it expires, only for accounts that
+    /// logged in through Twitch
+    #[serde(default, skip_serializing_if = "refresh_token_in_keyring")]
+    refresh_token: Option<Secret>,
+    #[serde(default)]
+    expires_at: Option<DateTime<Utc>>,
+    /// What the token allows, as of the last time it was validated
+    #[serde(default)]
+    scopes: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Default)]
+impl Account {
+    pub fn new(username: String, token: String) -> Self {
+        Self {
+            username,
+            token: Secret::new(token),
+            refresh_token: None,
+            expires_at: None,
+            scopes: Vec::new(),
+        }
+    }
+
+    fn refresh_user(&self) -> String {
+        format!("{}/refresh", self.username)
+    }
+
+    /// Reads the tokens missing from the file from the keyring
+    fn load_tokens(&mut self) {
+        if self.token.value.is_empty()
+            && let Some(token) = Secret::from_keyring(&self.username)
+        {
+            self.token = token;
+        }
+        // stored next to the token, if the account logged in through Twitch
+        if self.token.in_keyring && self.refresh_token.is_none() {
+            self.refresh_token = Secret::from_keyring(&self.refresh_user());
+        }
+    }
+
+    fn store_tokens(&mut self, in_file: bool) {
+        let refresh_user = self.refresh_user();
+        if in_file {
+            self.token.unstore(&self.username);
+            if let Some(t) = &mut self.refresh_token {
+                t.unstore(&refresh_user);
+            }
+        } else {
+            self.token.store(&self.username);
+            if let Some(t) = &mut self.refresh_token {
+                t.store(&refresh_user);
+            }
+        }
+    }
+
+    fn delete_tokens(&self) {
+        if self.token.in_keyring {
+            delete_from_keyring(&self.username);
+        }
+        if self.refresh_token.as_ref().is_some_and(Secret::in_keyring) {
+            delete_from_keyring(&self.refresh_user());
+        }
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token.value
+    }
+
+    pub fn refresh_token(&self) -> Option<&str> {
+        self.refresh_token
+            .as_ref()
+            .map(|t| t.value.as_str())
+            .filter(|t| !t.is_empty())
+    }
+
+    pub fn scopes(&self) -> &[String] {
+        &self.scopes
+    }
+
+    /// Takes the scopes and expiry Twitch reported for the token
+    pub fn validated(&mut self, validation: &Validation) {
+        self.scopes = validation.scopes.clone();
+        self.expires_at = validation.expires_at();
+    }
+
+    /// The refresh token, if the token expires within `margin`
+    pub fn needs_refresh(&self, margin: chrono::Duration) -> Option<&str> {
+        let expires_at = self.expires_at?;
+        if expires_at - Utc::now() < margin {
+            self.refresh_token()
+        } else {
+            None
+        }
+    }
+}
+
+impl From<Token> for Account {
+    fn from(token: Token) -> Self {
+        Self {
+            username: token.login,
+            token: Secret::new(token.access_token),
+            refresh_token: Some(Secret::new(token.refresh_token)),
+            expires_at: Some(token.expires_at),
+            scopes: token.scopes,
+        }
+    }
+}
+
+/// How the joined channels are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ChannelLayout {
+    #[default]
+    Tabs,
+    Sidebar,
+}
+
+/// How messages removed by moderators are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeletedMessages {
+    Hide,
+    /// Replaced with a placeholder that can be clicked to reveal them
+    Collapse,
+    /// Shown struck through
+    Strike,
+}
+
+impl DeletedMessages {
+    pub const ALL: [Self; 3] = [Self::Hide, Self::Collapse, Self::Strike];
+}
+
+impl std::fmt::Display for DeletedMessages {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Hide => "Hide",
+            Self::Collapse => "Collapse",
+            Self::Strike => "Strike through",
+        })
+    }
+}
+
+/// Which emote providers are used to render messages
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmoteProviders {
+    pub seventv: bool,
+    pub bttv: bool,
+    pub ffz: bool,
+    /// Emotes sent natively through Twitch
+    pub twitch: bool,
+}
+
+impl EmoteProviders {
+    pub fn is_enabled(&self, platform: EmotePlatform) -> bool {
+        match platform {
+            EmotePlatform::SevenTv => self.seventv,
+            EmotePlatform::BetterTtv => self.bttv,
+            EmotePlatform::FrankerFaceZ => self.ffz,
+            EmotePlatform::Twitch => self.twitch,
+        }
+    }
+}
+
+impl Default for EmoteProviders {
+    fn default() -> Self {
+        Self {
+            seventv: true,
+            bttv: true,
+            ffz: true,
+            twitch: true,
+        }
+    }
+}
+
+/// Which system messages are shown in chat
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SystemMessages {
+    pub joins_parts: bool,
+    /// Timeouts, bans and cleared chats
+    pub clear_chat: bool,
+    pub connection: bool,
+    pub notices: bool,
+}
+
+impl SystemMessages {
+    pub fn is_shown(&self, kind: SystemKind) -> bool {
+        match kind {
+            SystemKind::JoinPart => self.joins_parts,
+            SystemKind::ClearChat => self.clear_chat,
+            SystemKind::Connection => self.connection,
+            SystemKind::Notice => self.notices,
+        }
+    }
+}
+
+impl Default for SystemMessages {
+    fn default() -> Self {
+        Self {
+            joins_parts: true,
+            clear_chat: true,
+            connection: true,
+            notices: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct UiConfig {
-    #[serde(default)]
     pub natural_scrolling: bool,
+    /// Keep tabs on a single scrollable row instead of wrapping them
+    pub single_row_tabs: bool,
+    /// Collapse messages with blocked terms behind a placeholder instead of
+    /// hiding them entirely
+    pub collapse_blocked: bool,
+    /// Combine consecutive identical messages into one line with a counter
+    pub combine_duplicates: bool,
+    /// Pixels scrolled per mouse wheel line
+    pub scroll_speed: f32,
+    /// Animate scrolling instead of jumping straight to the target
+    pub smooth_scroll: bool,
+    /// Text size of chat messages, emotes are scaled to match
+    pub chat_font_size: f32,
+    /// Size of emotes relative to the chat text, 1 being their native size at
+    /// [UiConfig::BASE_FONT_SIZE]
+    pub emote_size: f32,
+    /// Name of one of iced's built-in themes, or [UiConfig::AUTO_THEME]
+    pub theme: String,
+    /// Fraction of a chat's height taken by the messages above the input
+    pub input_split: f32,
+    pub layout: ChannelLayout,
+    /// Strip tracking parameters from links pasted into the input
+    pub clean_pasted_links: bool,
+    /// Send with Shift+Enter instead of Enter, so a stray Enter keeps editing
+    pub shift_enter_sends: bool,
+    /// Append an invisible character to a message identical to the last one
+    /// sent, which Twitch would otherwise reject
+    pub bypass_duplicate_filter: bool,
+    pub emote_providers: EmoteProviders,
+    pub system_messages: SystemMessages,
+    /// Tighter message spacing without separators between messages
+    pub compact: bool,
+    /// Tint every other message to tell lines apart
+    pub alternate_rows: bool,
+    /// Only show the name and badges on the first of consecutive messages
+    /// from the same user
+    pub group_messages: bool,
+    /// Minimum contrast ratio between username colors and the background,
+    /// colors are lightened or darkened until they reach it
+    pub min_username_contrast: f32,
+    /// Show pronouns from pronouns.alejo.io next to usernames
+    pub show_pronouns: bool,
+    /// Show a desktop notification for highlighted messages while none of the
+    /// windows are focused
+    pub notify_on_mention: bool,
+    /// Number of messages loaded from the recent messages API, at most
+    /// [UiConfig::MAX_HISTORY_LIMIT]
+    pub history_limit: usize,
+    /// How deleted messages are shown, [None] strikes them through in
+    /// channels the user moderates and collapses them elsewhere
+    pub show_deleted: Option<DeletedMessages>,
+    /// Badge sets shown before the others, in this order. The rest keep the
+    /// order Twitch sends them in
+    pub badge_order: Vec<String>,
+    /// Badge sets that aren't shown next to usernames
+    pub hidden_badges: BTreeSet<String>,
+}
+
+impl UiConfig {
+    /// The text size emotes are sized for at their native resolution
+    pub const BASE_FONT_SIZE: f32 = 16.0;
+
+    /// Smallest and largest chat text size
+    pub const FONT_SIZE_RANGE: RangeInclusive<f32> = 10.0..=32.0;
+
+    /// Most messages the recent messages API returns at once
+    pub const MAX_HISTORY_LIMIT: usize = 1000;
+
+    /// Theme name that follows the light or dark mode of the system
+    pub const AUTO_THEME: &str = "Auto";
+
+    /// Badge sets listed in the settings, any other set can still be ordered
+    /// or hidden by editing the config file
+    pub const KNOWN_BADGES: &[&str] = &[
+        "broadcaster",
+        "moderator",
+        "lead_moderator",
+        "vip",
+        "staff",
+        "partner",
+        "founder",
+        "subscriber",
+        "sub-gifter",
+        "sub-gift-leader",
+        "bits",
+        "bits-leader",
+        "predictions",
+        "premium",
+        "turbo",
+        "artist-badge",
+        "bot-badge",
+        "no_audio",
+        "no_video",
+    ];
+
+    /// Names of every selectable theme
+    pub fn theme_names() -> Vec<String> {
+        std::iter::once(Self::AUTO_THEME.to_owned())
+            .chain(Theme::ALL.iter().map(|t| t.to_string()))
+            .collect()
+    }
+
+    /// The selected theme given the system's `mode`, falling back to the
+    /// default one for unknown names
+    pub fn theme(&self, mode: theme::Mode) -> Theme {
+        if self.theme == Self::AUTO_THEME {
+            return match mode {
+                theme::Mode::Light => Theme::CatppuccinLatte,
+                _ => Theme::CatppuccinMacchiato,
+            };
+        }
+        Theme::ALL
+            .iter()
+            .find(|t| t.to_string() == self.theme)
+            .cloned()
+            .unwrap_or(Theme::CatppuccinMacchiato)
+    }
+
+    /// How deleted messages are shown in a channel, `is_mod` being whether
+    /// the user moderates it
+    pub fn deleted_messages(&self, is_mod: bool) -> DeletedMessages {
+        match self.show_deleted {
+            Some(d) => d,
+            None if is_mod => DeletedMessages::Strike,
+            None => DeletedMessages::Collapse,
+        }
+    }
+
+    /// The shown badges out of `badges`, reordered by [UiConfig::badge_order]
+    pub fn sort_badges<'a>(
+        &self,
+        badges: impl Iterator<Item = (&'a str, &'a str)>,
+    ) -> Vec<(&'a str, &'a str)> {
+        let mut badges = badges
+            .filter(|(set, _)| !self.hidden_badges.contains(*set))
+            .collect::<Vec<_>>();
+        // stable, so unordered sets stay in tag order
+        badges.sort_by_key(|(set, _)| {
+            self.badge_order
+                .iter()
+                .position(|s| s == set)
+                .unwrap_or(usize::MAX)
+        });
+        badges
+    }
+
+    /// [UiConfig::KNOWN_BADGES] in the order they're shown in
+    pub fn known_badges(&self) -> Vec<&'static str> {
+        let mut known = Self::KNOWN_BADGES.to_vec();
+        known.sort_by_key(|set| {
+            self.badge_order
+                .iter()
+                .position(|s| s == set)
+                .unwrap_or(usize::MAX)
+        });
+        known
+    }
+
+    /// Swaps the known badge set `set` with the one shown before it, or after
+    /// it if `later`
+    pub fn move_badge(&mut self, set: &str, later: bool) {
+        let mut known = self.known_badges();
+        let Some(i) = known.iter().position(|s| *s == set) else {
+            return;
+        };
+        let j = if later { i + 1 } else { i.wrapping_sub(1) };
+        if j >= known.len() {
+            return;
+        }
+        known.swap(i, j);
+        let unknown = self
+            .badge_order
+            .iter()
+            .filter(|s| !Self::KNOWN_BADGES.contains(&s.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        self.badge_order = known
+            .into_iter()
+            .map(str::to_owned)
+            .chain(unknown)
+            .collect();
+    }
+
+    /// How much emotes need to be scaled to stay proportional to the chat
+    /// text, times [UiConfig::emote_size]
+    pub fn emote_scale(&self) -> f32 {
+        self.chat_font_size / Self::BASE_FONT_SIZE * self.emote_size
+    }
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            natural_scrolling: false,
+            single_row_tabs: false,
+            collapse_blocked: false,
+            combine_duplicates: false,
+            scroll_speed: 80.0,
+            smooth_scroll: true,
+            chat_font_size: Self::BASE_FONT_SIZE,
+            emote_size: 1.0,
+            theme: Theme::CatppuccinMacchiato.to_string(),
+            input_split: 0.9,
+            layout: ChannelLayout::Tabs,
+            clean_pasted_links: true,
+            shift_enter_sends: false,
+            bypass_duplicate_filter: true,
+            emote_providers: EmoteProviders::default(),
+            system_messages: SystemMessages::default(),
+            compact: false,
+            alternate_rows: false,
+            group_messages: false,
+            min_username_contrast: 3.0,
+            show_pronouns: false,
+            notify_on_mention: false,
+            history_limit: 250,
+            show_deleted: None,
+            badge_order: Vec::new(),
+            hidden_badges: BTreeSet::new(),
+        }
+    }
 }
 
 impl Config {
+    const MAX_RECENT_CHANNELS: usize = 20;
+
+    /// Adds the account that logged in with `token`, replacing its old tokens
+    /// if it was already added
+    pub fn add_account(&mut self, token: Token) {
+        let account = Account::from(token);
+        match self
+            .accounts
+            .iter_mut()
+            .find(|a| a.username == account.username)
+        {
+            Some(existing) => *existing = account,
+            None => self.accounts.push(account),
+        }
+    }
+
+    pub fn account_mut(&mut self, username: &str) -> Option<&mut Account> {
+        self.accounts.iter_mut().find(|a| a.username == username)
+    }
+
+    pub fn remove_account(&mut self, username: &str) {
+        self.accounts.retain(|a| {
+            let keep = a.username != username;
+            if !keep {
+                a.delete_tokens();
+            }
+            keep
+        });
+    }
+
+    /// Moves `channel` to the front of the recently joined channels
+    pub fn push_recent_channel(&mut self, channel: &str) {
+        self.recent_channels.retain(|c| c != channel);
+        self.recent_channels.insert(0, channel.to_owned());
+        self.recent_channels.truncate(Self::MAX_RECENT_CHANNELS);
+    }
+
+    /// Whether `text` mentions one of the accounts or contains a highlight
+    /// term, ignoring case
+    pub fn is_highlighted(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        self.mentions_account(&text) || self.matching_terms(&text).next().is_some()
+    }
+
+    /// The sound of the first highlight `text` matches that has one, mentions
+    /// of the accounts coming first
+    pub fn highlight_sound(&self, text: &str) -> Option<&Path> {
+        let text = text.to_lowercase();
+        self.sounds
+            .mention
+            .as_deref()
+            .filter(|_| self.mentions_account(&text))
+            .or_else(|| {
+                self.matching_terms(&text)
+                    .find_map(|t| self.sounds.terms.get(t))
+                    .map(PathBuf::as_path)
+            })
+    }
+
+    /// Whether the lowercase `text` mentions one of the accounts
+    fn mentions_account(&self, text: &str) -> bool {
+        text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .any(|w| {
+                self.accounts
+                    .iter()
+                    .any(|a| a.username.eq_ignore_ascii_case(w))
+            })
+    }
+
+    /// Highlight terms contained in the lowercase `text`
+    fn matching_terms<'a>(&'a self, text: &'a str) -> impl Iterator<Item = &'a String> {
+        self.highlight_terms
+            .iter()
+            .filter(move |t| text.contains(&t.to_lowercase()))
+    }
+
     pub fn save(&mut self) -> Result<(), std::io::Error> {
         self.save_to_file(&CONFIG_FILE_PATH)
     }
 
     fn read_from_file(path: &Path) -> Result<Self, std::io::Error> {
         let res = std::fs::read_to_string(path)
-            .and_then(|s| toml::from_str(&s).map_err(std::io::Error::other));
+            .and_then(|s| toml::from_str::<Self>(&s).map_err(std::io::Error::other))
+            .map(|mut c| {
+                c.endpoints.validate();
+                for account in &mut c.accounts {
+                    account.load_tokens();
+                }
+                c
+            })
+            .and_then(|c| match c.proxy.as_deref().map(reqwest::Proxy::all) {
+                Some(Err(e)) => Err(io::Error::other(format!(
+                    "Invalid proxy URL \"{}\": {e}",
+                    c.proxy.as_deref().unwrap_or_default()
+                ))),
+                _ => Ok(c),
+            });
         if let Err(e) = &res
             && e.kind() == io::ErrorKind::NotFound
         {
@@ -79,6 +901,9 @@ impl Config {
             .write(true)
             .create(true)
             .open(path)?;
+        for account in &mut self.accounts {
+            account.store_tokens(self.tokens_in_file);
+        }
         let toml = toml::ser::to_string_pretty(self).map_err(std::io::Error::other)?;
         #[cfg(unix)]
         {
@@ -102,6 +927,11 @@ impl Config {
     }
 }
 
+/// Where chat logs are written, in a directory per channel
+pub fn logs_dir() -> PathBuf {
+    config_dir().join("logs")
+}
+
 fn config_dir() -> PathBuf {
     dirs::config_dir()
         .map(|d| d.join("juliarino"))
@@ -1,12 +1,45 @@
+use std::path::{Path, PathBuf};
+
 use iced::{
-    Element, Length, Padding,
-    widget::{Button, Container, Text, button, checkbox, column, row},
+    Alignment, Element, Length, Padding, Task,
+    widget::{
+        Button, Column, Container, Text, button, checkbox, column, pick_list, row, scrollable,
+        slider, text_input,
+    },
 };
 
-use crate::config::{CONFIG, Config};
+use crate::{
+    chat::Chat,
+    chat_log::LogFormat,
+    config::{
+        BlockedTerm, CONFIG, ChannelLayout, Config, DeletedMessages, SETTINGS_GENERATION, UiConfig,
+        logs_dir,
+    },
+    keybinds::{Action, KeyCombo},
+    platform::twitch::auth::{self, DeviceCode, Token},
+    sound,
+};
 
 pub struct ConfigUi {
     active_tab: Tab,
+    new_blocked_term: String,
+    /// Why the blocked term being added isn't a valid regex
+    blocked_term_error: Option<String>,
+    new_highlight_term: String,
+    /// The action whose keybinding is waiting for a key press
+    recording: Option<Action>,
+    /// Why the last recorded keybinding wasn't applied
+    key_conflict: Option<String>,
+    login: Option<Login>,
+}
+
+/// A login with Twitch in progress on the accounts tab
+enum Login {
+    Requesting,
+    /// Waiting for the user to enter the code on Twitch, aborted with the
+    /// handle
+    Waiting(DeviceCode, iced::task::Handle),
+    Failed(String),
 }
 
 #[derive(Debug, Clone, Default)]
@@ -14,6 +47,10 @@ pub enum Tab {
     #[default]
     General,
     Highlights,
+    Filters,
+    Badges,
+    Accounts,
+    Keys,
     Sounds,
     About,
 }
@@ -21,6 +58,22 @@ pub enum Tab {
 #[derive(derive_more::Debug)]
 pub enum Message {
     SwitchTo(Tab),
+    BlockedTermChange(String),
+    AddBlockedTerm,
+    HighlightTermChange(String),
+    AddHighlightTerm,
+    /// Opens a file dialog to choose the sound of a highlight
+    PickSound(SoundSlot),
+    TestSound(PathBuf),
+    /// Waits for the next key press to bind it to the action
+    RecordKey(Action),
+    KeyRecorded(KeyCombo),
+    StartLogin,
+    LoginCode(Result<DeviceCode, String>),
+    LoginFinished(Result<Token, String>),
+    CancelLogin,
+    /// Opens the page the login code is entered on
+    OpenLoginPage,
     #[debug("Box<dyn ConfigChanger>")]
     Execute(Box<dyn ConfigChanger>),
 }
@@ -39,11 +92,68 @@ impl Clone for Message {
     fn clone(&self) -> Self {
         match self {
             Self::SwitchTo(arg0) => Self::SwitchTo(arg0.clone()),
+            Self::BlockedTermChange(arg0) => Self::BlockedTermChange(arg0.clone()),
+            Self::AddBlockedTerm => Self::AddBlockedTerm,
+            Self::HighlightTermChange(arg0) => Self::HighlightTermChange(arg0.clone()),
+            Self::AddHighlightTerm => Self::AddHighlightTerm,
+            Self::PickSound(arg0) => Self::PickSound(arg0.clone()),
+            Self::TestSound(arg0) => Self::TestSound(arg0.clone()),
+            Self::RecordKey(arg0) => Self::RecordKey(*arg0),
+            Self::KeyRecorded(arg0) => Self::KeyRecorded(arg0.clone()),
+            Self::StartLogin => Self::StartLogin,
+            Self::LoginCode(arg0) => Self::LoginCode(arg0.clone()),
+            Self::LoginFinished(arg0) => Self::LoginFinished(arg0.clone()),
+            Self::CancelLogin => Self::CancelLogin,
+            Self::OpenLoginPage => Self::OpenLoginPage,
             Self::Execute(arg0) => Self::Execute(arg0.clone_boxed()),
         }
     }
 }
 
+/// Which highlight a sound is played for
+#[derive(Debug, Clone)]
+pub enum SoundSlot {
+    Mention,
+    Term(String),
+}
+
+impl SoundSlot {
+    fn set(&self, cfg: &mut Config, path: Option<PathBuf>) {
+        match (self, path) {
+            (SoundSlot::Mention, path) => cfg.sounds.mention = path,
+            (SoundSlot::Term(t), Some(path)) => {
+                cfg.sounds.terms.insert(t.clone(), path);
+            }
+            (SoundSlot::Term(t), None) => {
+                cfg.sounds.terms.remove(t);
+            }
+        }
+    }
+}
+
+/// A highlight's sound with buttons to change, test and remove it
+fn sound_row(label: String, path: Option<&Path>, slot: SoundSlot) -> Element<'static, Message> {
+    let file = path
+        .and_then(Path::file_name)
+        .map(|f| f.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "No sound".to_owned());
+    let clear = slot.clone();
+    row![
+        Text::new(label).width(Length::Fill),
+        Text::new(file).size(12),
+        button("Choose…").on_press(Message::PickSound(slot)),
+        button("Test")
+            .style(button::secondary)
+            .on_press_maybe(path.map(|p| Message::TestSound(p.to_owned()))),
+        button("Remove").style(button::danger).on_press_maybe(
+            path.map(|_| { Message::Execute(Box::new(move |c| clear.set(c, None))) })
+        ),
+    ]
+    .spacing(6)
+    .align_y(Alignment::Center)
+    .into()
+}
+
 fn tab(text: &'static str, tab: Tab) -> Element<'static, Message> {
     Button::new(text)
         .on_press(Message::SwitchTo(tab))
@@ -57,15 +167,42 @@ impl ConfigUi {
     pub fn new() -> Self {
         Self {
             active_tab: Default::default(),
+            new_blocked_term: String::new(),
+            blocked_term_error: None,
+            new_highlight_term: String::new(),
+            recording: None,
+            key_conflict: None,
+            login: None,
+        }
+    }
+
+    /// Shows the accounts tab and starts logging in, for when a token expired
+    pub fn open_login(&mut self) -> Task<Message> {
+        self.active_tab = Tab::Accounts;
+        match self.login {
+            None | Some(Login::Failed(_)) if auth::CLIENT_ID.is_some() => {
+                self.update(Message::StartLogin)
+            }
+            _ => Task::none(),
         }
     }
 
+    /// Key presses are being recorded for a keybinding, so they shouldn't
+    /// trigger other ones
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
     pub fn view(&self) -> Element<'_, Message> {
         let cfg = CONFIG.read();
 
         let sections = row![
             tab("General", Tab::General),
             tab("Highlights", Tab::Highlights),
+            tab("Filters", Tab::Filters),
+            tab("Badges", Tab::Badges),
+            tab("Accounts", Tab::Accounts),
+            tab("Keys", Tab::Keys),
             tab("Sounds", Tab::Sounds),
             tab("About", Tab::About),
         ]
@@ -74,15 +211,452 @@ impl ConfigUi {
         .wrap();
         let view: Element<'_, Message> = match self.active_tab {
             Tab::General => column![
+                row![
+                    Text::new("Theme"),
+                    pick_list(
+                        UiConfig::theme_names(),
+                        Some(cfg.ui.theme.clone()),
+                        |t: String| Message::Execute(Box::new(move |c| c.ui.theme = t.clone()))
+                    )
+                ]
+                .spacing(8)
+                .align_y(iced::Alignment::Center),
                 checkbox(cfg.ui.natural_scrolling)
                     .label("Natural scrolling")
                     .on_toggle(|l| Message::Execute(Box::new(move |c| {
                         c.ui.natural_scrolling = l
-                    })))
+                    }))),
+                checkbox(cfg.ui.layout == ChannelLayout::Sidebar)
+                    .label("Show channels in a sidebar instead of tabs")
+                    .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                        c.ui.layout = if l {
+                            ChannelLayout::Sidebar
+                        } else {
+                            ChannelLayout::Tabs
+                        }
+                    }))),
+                checkbox(cfg.ui.single_row_tabs)
+                    .label("Keep tabs on a single row")
+                    .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                        c.ui.single_row_tabs = l
+                    }))),
+                checkbox(cfg.ui.compact)
+                    .label("Compact message spacing")
+                    .on_toggle(|l| Message::Execute(Box::new(move |c| { c.ui.compact = l }))),
+                checkbox(cfg.ui.alternate_rows)
+                    .label("Alternate message backgrounds")
+                    .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                        c.ui.alternate_rows = l
+                    }))),
+                checkbox(cfg.ui.group_messages)
+                    .label("Group consecutive messages from the same user")
+                    .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                        c.ui.group_messages = l
+                    }))),
+                checkbox(cfg.ui.show_pronouns)
+                    .label("Show pronouns next to usernames")
+                    .on_toggle(|l| Message::Execute(Box::new(move |c| { c.ui.show_pronouns = l }))),
+                row![
+                    Text::new("Deleted messages"),
+                    pick_list(DeletedMessages::ALL, cfg.ui.show_deleted, |d| {
+                        Message::Execute(Box::new(move |c| c.ui.show_deleted = Some(d)))
+                    })
+                    .placeholder("Automatic"),
+                    button("Automatic").style(button::subtle).on_press_maybe(
+                        cfg.ui
+                            .show_deleted
+                            .is_some()
+                            .then(|| { Message::Execute(Box::new(|c| c.ui.show_deleted = None)) })
+                    ),
+                ]
+                .spacing(8)
+                .align_y(iced::Alignment::Center),
+                checkbox(cfg.ui.combine_duplicates)
+                    .label("Combine repeated messages")
+                    .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                        c.ui.combine_duplicates = l
+                    }))),
+                checkbox(cfg.ui.clean_pasted_links)
+                    .label("Remove tracking parameters from pasted links")
+                    .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                        c.ui.clean_pasted_links = l
+                    }))),
+                checkbox(cfg.ui.shift_enter_sends)
+                    .label("Send messages with Shift+Enter instead of Enter")
+                    .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                        c.ui.shift_enter_sends = l
+                    }))),
+                checkbox(cfg.ui.bypass_duplicate_filter)
+                    .label("Allow sending the same message twice in a row")
+                    .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                        c.ui.bypass_duplicate_filter = l
+                    }))),
+                row![
+                    Text::new("Emotes"),
+                    checkbox(cfg.ui.emote_providers.twitch)
+                        .label("Twitch")
+                        .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                            c.ui.emote_providers.twitch = l
+                        }))),
+                    checkbox(cfg.ui.emote_providers.seventv)
+                        .label("7TV")
+                        .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                            c.ui.emote_providers.seventv = l
+                        }))),
+                    checkbox(cfg.ui.emote_providers.bttv)
+                        .label("BTTV")
+                        .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                            c.ui.emote_providers.bttv = l
+                        }))),
+                    checkbox(cfg.ui.emote_providers.ffz)
+                        .label("FFZ")
+                        .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                            c.ui.emote_providers.ffz = l
+                        }))),
+                ]
+                .spacing(8)
+                .align_y(iced::Alignment::Center),
+                row![
+                    Text::new("System messages"),
+                    checkbox(cfg.ui.system_messages.joins_parts)
+                        .label("Joins and parts")
+                        .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                            c.ui.system_messages.joins_parts = l
+                        }))),
+                    checkbox(cfg.ui.system_messages.clear_chat)
+                        .label("Timeouts and bans")
+                        .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                            c.ui.system_messages.clear_chat = l
+                        }))),
+                    checkbox(cfg.ui.system_messages.connection)
+                        .label("Connection")
+                        .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                            c.ui.system_messages.connection = l
+                        }))),
+                    checkbox(cfg.ui.system_messages.notices)
+                        .label("Notices")
+                        .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                            c.ui.system_messages.notices = l
+                        }))),
+                ]
+                .spacing(8)
+                .align_y(iced::Alignment::Center),
+                checkbox(cfg.ui.smooth_scroll)
+                    .label("Smooth scrolling")
+                    .on_toggle(|l| Message::Execute(Box::new(move |c| { c.ui.smooth_scroll = l }))),
+                row![
+                    Text::new("Scroll speed"),
+                    slider(20.0..=300.0, cfg.ui.scroll_speed, |v| Message::Execute(
+                        Box::new(move |c| c.ui.scroll_speed = v)
+                    ))
+                    .step(10.0)
+                ]
+                .spacing(8),
+                row![
+                    Text::new("Messages loaded on join"),
+                    slider(
+                        // the scrollback doesn't keep more than that
+                        50.0..=Chat::MAX_SCROLLBACK as f32,
+                        cfg.ui.history_limit as f32,
+                        |v| Message::Execute(Box::new(move |c| c.ui.history_limit = v as usize))
+                    )
+                    .step(50.0),
+                    Text::new(cfg.ui.history_limit.to_string()),
+                ]
+                .spacing(8),
+                row![
+                    Text::new("Username contrast"),
+                    slider(1.0..=7.0, cfg.ui.min_username_contrast, |v| {
+                        Message::Execute(Box::new(move |c| c.ui.min_username_contrast = v))
+                    })
+                    .step(0.5)
+                ]
+                .spacing(8),
+                row![
+                    Text::new("Chat font size"),
+                    slider(UiConfig::FONT_SIZE_RANGE, cfg.ui.chat_font_size, |v| {
+                        Message::Execute(Box::new(move |c| c.ui.chat_font_size = v))
+                    })
+                    .step(1.0)
+                ]
+                .spacing(8),
+                row![
+                    Text::new("Emote size"),
+                    slider(0.5..=3.0, cfg.ui.emote_size, |v| Message::Execute(
+                        Box::new(move |c| c.ui.emote_size = v)
+                    ))
+                    .step(0.25),
+                    Text::new(format!("{}×", cfg.ui.emote_size)),
+                ]
+                .spacing(8),
+                row![
+                    checkbox(cfg.logging.enabled)
+                        .label("Save chat logs")
+                        .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                            c.logging.enabled = l
+                        }))),
+                    pick_list(LogFormat::ALL, Some(cfg.logging.format), |f| {
+                        Message::Execute(Box::new(move |c| c.logging.format = f))
+                    }),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+                Text::new(format!("Logs are saved in {}", logs_dir().display())).size(12),
             ]
             .into(),
-            Tab::Highlights => column![].into(),
-            Tab::Sounds => column![].into(),
+            Tab::Highlights => {
+                let terms = cfg
+                    .highlight_terms
+                    .iter()
+                    .enumerate()
+                    .map(|(i, t)| {
+                        row![
+                            Text::new(t.clone()).width(Length::Fill),
+                            button("Remove")
+                                .style(button::danger)
+                                .on_press(Message::Execute(Box::new(move |c| {
+                                    if i < c.highlight_terms.len() {
+                                        let term = c.highlight_terms.remove(i);
+                                        c.sounds.terms.remove(&term);
+                                    }
+                                })))
+                        ]
+                        .into()
+                    })
+                    .collect::<Column<Message>>()
+                    .spacing(4);
+                column![
+                    Text::new("Mentions of your accounts are always highlighted"),
+                    checkbox(cfg.ui.notify_on_mention)
+                        .label("Notify about highlights while the app isn't focused")
+                        .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                            c.ui.notify_on_mention = l
+                        }))),
+                    row![
+                        text_input("Highlight term", &self.new_highlight_term)
+                            .on_input(Message::HighlightTermChange)
+                            .on_submit(Message::AddHighlightTerm),
+                        button("Add").on_press(Message::AddHighlightTerm)
+                    ]
+                    .spacing(4),
+                    terms
+                ]
+                .spacing(8)
+                .into()
+            }
+            Tab::Filters => {
+                let terms = cfg
+                    .blocked_terms
+                    .iter()
+                    .enumerate()
+                    .map(|(i, t)| {
+                        row![
+                            Text::new(t.as_str().to_owned()).width(Length::Fill),
+                            button("Remove")
+                                .style(button::danger)
+                                .on_press(Message::Execute(Box::new(move |c| {
+                                    if i < c.blocked_terms.len() {
+                                        c.blocked_terms.remove(i);
+                                    }
+                                })))
+                        ]
+                        .into()
+                    })
+                    .collect::<Column<Message>>()
+                    .spacing(4);
+                column![
+                    checkbox(cfg.ui.collapse_blocked)
+                        .label("Collapse blocked messages instead of hiding them")
+                        .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                            c.ui.collapse_blocked = l
+                        }))),
+                    row![
+                        text_input("Blocked term, or /regex/", &self.new_blocked_term)
+                            .on_input(Message::BlockedTermChange)
+                            .on_submit(Message::AddBlockedTerm),
+                        button("Add").on_press(Message::AddBlockedTerm)
+                    ]
+                    .spacing(4),
+                    Column::from_iter(self.blocked_term_error.as_deref().map(|e| {
+                        Element::from(Text::new(e).size(12).style(iced::widget::text::danger))
+                    })),
+                    terms
+                ]
+                .spacing(8)
+                .into()
+            }
+            Tab::Badges => {
+                let known = cfg.ui.known_badges();
+                let last = known.len() - 1;
+                let sets = known
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, set)| {
+                        row![
+                            checkbox(!cfg.ui.hidden_badges.contains(set))
+                                .label(set)
+                                .on_toggle(move |l| Message::Execute(Box::new(move |c| {
+                                    if l {
+                                        c.ui.hidden_badges.remove(set);
+                                    } else {
+                                        c.ui.hidden_badges.insert(set.to_owned());
+                                    }
+                                })))
+                                .width(Length::Fill),
+                            button("Up")
+                                .style(button::subtle)
+                                .on_press_maybe((i > 0).then(|| Message::Execute(Box::new(
+                                    move |c| { c.ui.move_badge(set, false) }
+                                )))),
+                            button("Down")
+                                .style(button::subtle)
+                                .on_press_maybe((i < last).then(|| Message::Execute(Box::new(
+                                    move |c| { c.ui.move_badge(set, true) }
+                                )))),
+                        ]
+                        .spacing(4)
+                        .align_y(iced::Alignment::Center)
+                        .into()
+                    })
+                    .collect::<Column<Message>>()
+                    .spacing(4);
+                column![
+                    Text::new("Badges are shown in this order, others follow as Twitch sends them"),
+                    scrollable(sets),
+                ]
+                .spacing(8)
+                .into()
+            }
+            Tab::Accounts => {
+                let accounts = cfg.accounts.iter().map(|a| -> Element<'static, Message> {
+                    let username = a.username().to_owned();
+                    row![
+                        column![
+                            Text::new(username.clone()),
+                            Text::new(a.scopes().join(", ")).size(12),
+                        ]
+                        .width(Length::Fill),
+                        button("Remove")
+                            .style(button::danger)
+                            .on_press(Message::Execute(Box::new(move |c| {
+                                c.remove_account(&username)
+                            }))),
+                    ]
+                    .spacing(6)
+                    .align_y(Alignment::Center)
+                    .into()
+                });
+                let login: Element<'_, Message> = match &self.login {
+                    _ if auth::CLIENT_ID.is_none() => {
+                        Text::new("This build can't log in with Twitch").into()
+                    }
+                    None => button("Log in with Twitch")
+                        .on_press(Message::StartLogin)
+                        .into(),
+                    Some(Login::Requesting) => Text::new("Starting the login…").into(),
+                    Some(Login::Waiting(code, _)) => column![
+                        Text::new(format!(
+                            "Enter this code at {} to log in",
+                            code.verification_uri
+                        )),
+                        Text::new(code.user_code.clone()).size(24),
+                        row![
+                            button("Open Twitch").on_press(Message::OpenLoginPage),
+                            button("Cancel")
+                                .style(button::secondary)
+                                .on_press(Message::CancelLogin),
+                        ]
+                        .spacing(6),
+                    ]
+                    .spacing(8)
+                    .into(),
+                    Some(Login::Failed(e)) => column![
+                        Text::new(format!("Couldn't log in: {e}"))
+                            .style(iced::widget::text::danger),
+                        button("Try again").on_press(Message::StartLogin),
+                    ]
+                    .spacing(8)
+                    .into(),
+                };
+                column![
+                    Column::from_iter(accounts).spacing(4),
+                    login,
+                    checkbox(cfg.tokens_in_file)
+                        .label("Store tokens in the config file instead of the system keyring")
+                        .on_toggle(|l| Message::Execute(Box::new(move |c| {
+                            c.tokens_in_file = l
+                        }))),
+                ]
+                .spacing(12)
+                .into()
+            }
+            Tab::Keys => {
+                let bindings = Action::ALL.map(|action| -> Element<'static, Message> {
+                    let label = if self.recording == Some(action) {
+                        "Press a key, Escape to cancel".to_owned()
+                    } else {
+                        cfg.keybindings.get(action).to_string()
+                    };
+                    row![
+                        Text::new(action.to_string()).width(Length::Fill),
+                        button(Text::new(label))
+                            .style(button::secondary)
+                            .on_press(Message::RecordKey(action)),
+                        button("Reset").style(button::subtle).on_press_maybe(
+                            (!cfg.keybindings.is_default(action)).then(|| {
+                                Message::Execute(Box::new(move |c| c.keybindings.reset(action)))
+                            })
+                        ),
+                    ]
+                    .spacing(6)
+                    .align_y(Alignment::Center)
+                    .into()
+                });
+                let conflict = self
+                    .key_conflict
+                    .as_deref()
+                    .map(|e| Element::from(Text::new(e).style(iced::widget::text::danger)));
+                column![
+                    Column::from_iter(conflict),
+                    scrollable(Column::from_iter(bindings).spacing(4)),
+                ]
+                .spacing(8)
+                .into()
+            }
+            Tab::Sounds => {
+                let volume = cfg.sounds.volume;
+                let terms = cfg.highlight_terms.iter().map(|t| {
+                    sound_row(
+                        t.clone(),
+                        cfg.sounds.terms.get(t).map(PathBuf::as_path),
+                        SoundSlot::Term(t.clone()),
+                    )
+                });
+                let hint = cfg
+                    .highlight_terms
+                    .is_empty()
+                    .then(|| Text::new("Highlight terms can have their own sound too").into());
+                column![
+                    row![
+                        Text::new("Volume"),
+                        slider(0.0..=1.0, volume, |v| Message::Execute(Box::new(
+                            move |c| c.sounds.volume = v
+                        )))
+                        .step(0.05),
+                        Text::new(format!("{:.0}%", volume * 100.0)),
+                    ]
+                    .spacing(8)
+                    .align_y(Alignment::Center),
+                    sound_row(
+                        "Mentions of your accounts".to_owned(),
+                        cfg.sounds.mention.as_deref(),
+                        SoundSlot::Mention,
+                    ),
+                    Column::from_iter(terms.chain(hint)).spacing(6),
+                ]
+                .spacing(8)
+                .into()
+            }
             Tab::About => Element::new(Text::new("FART").size(200)),
         };
         let view = Container::new(view).width(Length::FillPortion(3));
@@ -92,16 +666,159 @@ impl ConfigUi {
             .into()
     }
 
-    pub fn update(&mut self, msg: Message) {
+    pub fn update(&mut self, msg: Message) -> Task<Message> {
         match msg {
             Message::SwitchTo(tab) => self.active_tab = tab,
+            Message::BlockedTermChange(t) => {
+                self.new_blocked_term = t;
+                self.blocked_term_error = None;
+            }
+            Message::AddBlockedTerm => {
+                let term = self.new_blocked_term.trim();
+                if term.is_empty() {
+                    return Task::none();
+                }
+                // kept in the input to be fixed
+                let term = match BlockedTerm::new(term.to_owned()) {
+                    Ok(t) => t,
+                    Err(e) => {
+                        self.blocked_term_error = Some(format!("Invalid regex: {e}"));
+                        return Task::none();
+                    }
+                };
+                self.new_blocked_term.clear();
+                let mut cfg = CONFIG.write();
+                if !cfg
+                    .blocked_terms
+                    .iter()
+                    .any(|t| t.as_str() == term.as_str())
+                {
+                    cfg.blocked_terms.push(term);
+                }
+                SETTINGS_GENERATION.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                if let Err(e) = cfg.save() {
+                    log::error!("Error when saving settings: {e}");
+                }
+            }
+            Message::HighlightTermChange(t) => self.new_highlight_term = t,
+            Message::AddHighlightTerm => {
+                let term = std::mem::take(&mut self.new_highlight_term);
+                let term = term.trim();
+                if term.is_empty() {
+                    return Task::none();
+                }
+                let mut cfg = CONFIG.write();
+                if !cfg.highlight_terms.iter().any(|t| t == term) {
+                    cfg.highlight_terms.push(term.to_owned());
+                }
+                SETTINGS_GENERATION.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                if let Err(e) = cfg.save() {
+                    log::error!("Error when saving settings: {e}");
+                }
+            }
+            Message::PickSound(slot) => {
+                return Task::future(async {
+                    rfd::AsyncFileDialog::new()
+                        .set_title("Choose a sound")
+                        .add_filter("Audio", &["wav", "mp3", "ogg", "flac"])
+                        .pick_file()
+                        .await
+                        .map(|f| f.path().to_owned())
+                })
+                .and_then(move |path| {
+                    let slot = slot.clone();
+                    Task::done(Message::Execute(Box::new(move |c| {
+                        slot.set(c, Some(path.clone()))
+                    })))
+                });
+            }
+            Message::TestSound(path) => sound::play(&path, CONFIG.read().sounds.volume),
+            Message::RecordKey(action) => {
+                self.recording = Some(action);
+                self.key_conflict = None;
+            }
+            Message::KeyRecorded(combo) => {
+                let Some(action) = self.recording.take() else {
+                    return Task::none();
+                };
+                if combo.is_escape() {
+                    return Task::none();
+                }
+                let mut cfg = CONFIG.write();
+                match cfg.keybindings.action(&combo) {
+                    Some(other) if other != action => {
+                        self.key_conflict = Some(format!("{combo} is already bound to {other}"));
+                    }
+                    _ => {
+                        cfg.keybindings.set(action, combo);
+                        if let Err(e) = cfg.save() {
+                            log::error!("Error when saving settings: {e}");
+                        }
+                    }
+                }
+            }
+            Message::StartLogin => {
+                self.login = Some(Login::Requesting);
+                return Task::perform(auth::request_device_code(), |r| {
+                    Message::LoginCode(r.map_err(|e| e.to_string()))
+                });
+            }
+            Message::LoginCode(code) => {
+                // the login was cancelled while the code was requested
+                if !matches!(self.login, Some(Login::Requesting)) {
+                    return Task::none();
+                }
+                match code {
+                    Ok(code) => {
+                        let (task, handle) =
+                            Task::perform(auth::wait_for_token(code.clone()), |r| {
+                                Message::LoginFinished(r.map_err(|e| e.to_string()))
+                            })
+                            .abortable();
+                        self.login = Some(Login::Waiting(code, handle));
+                        return task;
+                    }
+                    Err(e) => {
+                        log::warn!("Couldn't start logging in: {e}");
+                        self.login = Some(Login::Failed(e));
+                    }
+                }
+            }
+            Message::LoginFinished(Ok(token)) => {
+                self.login = None;
+                log::info!("Logged in as {}", token.login);
+                let mut cfg = CONFIG.write();
+                cfg.add_account(token);
+                SETTINGS_GENERATION.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                if let Err(e) = cfg.save() {
+                    log::error!("Error when saving settings: {e}");
+                }
+            }
+            Message::LoginFinished(Err(e)) => {
+                log::warn!("Couldn't log in: {e}");
+                self.login = Some(Login::Failed(e));
+            }
+            Message::CancelLogin => {
+                if let Some(Login::Waiting(_, handle)) = self.login.take() {
+                    handle.abort();
+                }
+            }
+            Message::OpenLoginPage => {
+                if let Some(Login::Waiting(code, _)) = &self.login
+                    && let Err(e) = open::that_detached(&code.verification_uri)
+                {
+                    log::error!("couldn't open {}: {e}", code.verification_uri);
+                }
+            }
             Message::Execute(f) => {
                 let mut cfg = CONFIG.write();
                 f(&mut cfg);
+                SETTINGS_GENERATION.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                 if let Err(e) = cfg.save() {
                     log::error!("Error when saving settings: {e}");
                 }
             }
         };
+        Task::none()
     }
 }
@@ -0,0 +1,168 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use futures::{
+    FutureExt, SinkExt, StreamExt,
+    channel::mpsc::{Sender, UnboundedReceiver},
+};
+use itertools::Itertools;
+use twixel_core::{
+    IrcMessage,
+    irc_message::{PrivMsg, SemanticIrcMessage},
+};
+
+use crate::{ConnectionState, IrcCommand, Message, config::CONFIG};
+
+/// Time between scripted messages
+const INTERVAL: Duration = Duration::from_millis(1200);
+
+/// Who chats in the demo as login, display name, color and badges
+const CHATTERS: [(&str, &str, &str, &str); 7] = [
+    (
+        "juliapixel",
+        "Juliapixel",
+        "#FF7FBF",
+        "broadcaster/1,subscriber/12",
+    ),
+    ("modbot", "ModBot", "#00AD03", "moderator/1"),
+    ("vipviewer", "VipViewer", "#8A2BE2", "vip/1,subscriber/3"),
+    ("lurker42", "lurker42", "", ""),
+    ("subfan", "SubFan", "#1E90FF", "subscriber/6"),
+    ("nekochan", "ねこちゃん", "#DAA520", "subscriber/0"),
+    ("cheerleader", "Cheerleader", "#FF4500", "bits/1000"),
+];
+
+/// Native Twitch emotes used in [SCRIPT] by name and id
+const EMOTES: [(&str, &str); 4] = [
+    ("Kappa", "25"),
+    ("LUL", "425618"),
+    ("PogChamp", "305954156"),
+    ("<3", "555555584"),
+];
+
+/// What's said in chat in order, with the bits cheered in it
+const SCRIPT: [(&str, Option<u32>); 12] = [
+    ("hello chat <3", None),
+    ("is this the demo mode? Kappa", None),
+    ("welcome everyone, please be nice in chat", None),
+    ("LUL LUL LUL", None),
+    ("this one has a link https://github.com/juliapixel", None),
+    ("Cheer100 great stream!", Some(100)),
+    ("PogChamp PogChamp", None),
+    ("こんにちは <3", None),
+    (
+        "this is a much longer message to check how wrapping looks when someone \
+         writes a whole paragraph into chat instead of a couple of words",
+        None,
+    ),
+    ("Kappa 123 Kappa", None),
+    ("first time chatter, long time lurker", None),
+    ("gn chat", None),
+];
+
+/// The `emotes` tag Twitch would send with `text`
+fn emotes_tag(text: &str) -> String {
+    let mut ranges: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut pos = 0;
+    for word in text.split(' ') {
+        let len = word.chars().count();
+        if let Some((_, id)) = EMOTES.iter().find(|(name, _)| *name == word) {
+            ranges
+                .entry(id)
+                .or_default()
+                .push(format!("{pos}-{}", pos + len - 1));
+        }
+        pos += len + 1;
+    }
+    ranges
+        .into_iter()
+        .map(|(id, ranges)| format!("{id}:{}", ranges.join(",")))
+        .join("/")
+}
+
+/// The `n`th message of the script, sent in `channel`
+fn scripted_line(n: usize, channel: &str) -> String {
+    let user = n % CHATTERS.len();
+    let (login, display_name, color, badges) = CHATTERS[user];
+    let (text, bits) = SCRIPT[n % SCRIPT.len()];
+    let bits = bits.map(|b| format!("bits={b};")).unwrap_or_default();
+    format!(
+        "@badges={badges};{bits}color={color};display-name={display_name};emotes={emotes};\
+         id=demo-{n};room-id=1;tmi-sent-ts={sent};user-id={user_id} \
+         :{login}!{login}@{login}.tmi.twitch.tv PRIVMSG #{channel} :{text}",
+        emotes = emotes_tag(text),
+        sent = chrono::Utc::now().timestamp_millis(),
+        user_id = 1000 + user,
+    )
+}
+
+fn scripted_message(n: usize, channel: &str) -> Option<PrivMsg> {
+    PrivMsg::from_message(IrcMessage::new(scripted_line(n, channel)).ok()?).ok()
+}
+
+/// Stands in for the IRC connection with `--demo`, joining every channel
+/// right away and sending [SCRIPT] into them round-robin. Nothing is sent to
+/// Twitch
+pub async fn run(mut output: Sender<Message>, mut commands: UnboundedReceiver<IrcCommand>) {
+    output
+        .send(Message::ConnectionStateChanged(ConnectionState::Connected))
+        .await
+        .unwrap();
+    let mut channels = CONFIG.read().chats.clone();
+    for chan in &channels {
+        output
+            .send(Message::ChannelJoined(chan.clone()))
+            .await
+            .unwrap();
+    }
+
+    let mut n = 0;
+    loop {
+        futures::select! {
+            _ = tokio::time::sleep(INTERVAL).fuse() => {
+                if channels.is_empty() {
+                    continue;
+                }
+                if let Some(msg) = scripted_message(n, &channels[n % channels.len()]) {
+                    output.send(Message::NewMessage(msg)).await.unwrap();
+                }
+                n += 1;
+            },
+            cmd = commands.next() => match cmd {
+                Some(IrcCommand::Join(chan)) => {
+                    channels.push(chan.clone());
+                    output.send(Message::ChannelJoined(chan)).await.unwrap();
+                },
+                Some(IrcCommand::Part(chan)) => channels.retain(|c| *c != chan),
+                Some(IrcCommand::Message(chan, msg)) => {
+                    log::info!("Not sending \"{}\" to #{} in demo mode", &msg, &chan);
+                },
+                Some(IrcCommand::Login(_)) => (),
+                None => panic!("IRC control channel closed"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emote_ranges_are_in_chars() {
+        assert_eq!(emotes_tag("Kappa 123 Kappa"), "25:0-4,10-14");
+        assert_eq!(emotes_tag("こんにちは <3"), "555555584:6-7");
+        assert_eq!(emotes_tag("LUL PogChamp"), "305954156:4-11/425618:0-2");
+        assert_eq!(emotes_tag("gn chat"), "");
+    }
+
+    #[test]
+    fn whole_script_parses() {
+        for n in 0..SCRIPT.len() * CHATTERS.len() {
+            assert!(
+                scripted_message(n, "demo").is_some(),
+                "{}",
+                scripted_line(n, "demo")
+            );
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+use iced::keyboard::{self, key::Named};
+use serde::{Deserialize, Serialize};
+
+/// Something a keybinding does, keys only handled while a widget is focused
+/// or hovered (scrolling, closing menus) aren't actions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    FocusInput,
+    /// Recalls the previously sent message
+    HistoryPrevious,
+    HistoryNext,
+    Undo,
+    Redo,
+    /// Completes the `@username` being typed with the first suggestion
+    CompleteMention,
+    CloseTab,
+    ReopenClosedTab,
+    NextTab,
+    PreviousTab,
+    JoinChannel,
+    MarkAllRead,
+    ToggleEmotePicker,
+    ToggleSettings,
+}
+
+impl Action {
+    pub const ALL: [Self; 14] = [
+        Self::FocusInput,
+        Self::HistoryPrevious,
+        Self::HistoryNext,
+        Self::Undo,
+        Self::Redo,
+        Self::CompleteMention,
+        Self::CloseTab,
+        Self::ReopenClosedTab,
+        Self::NextTab,
+        Self::PreviousTab,
+        Self::JoinChannel,
+        Self::MarkAllRead,
+        Self::ToggleEmotePicker,
+        Self::ToggleSettings,
+    ];
+
+    pub fn default_binding(self) -> KeyCombo {
+        match self {
+            Self::FocusInput => "Enter",
+            Self::HistoryPrevious => "ArrowUp",
+            Self::HistoryNext => "ArrowDown",
+            Self::Undo => "Ctrl+Z",
+            Self::Redo => "Ctrl+Y",
+            Self::CompleteMention => "Tab",
+            Self::CloseTab => "Ctrl+W",
+            Self::ReopenClosedTab => "Ctrl+Shift+T",
+            Self::NextTab => "Ctrl+Tab",
+            Self::PreviousTab => "Ctrl+Shift+Tab",
+            Self::JoinChannel => "Ctrl+T",
+            Self::MarkAllRead => "Ctrl+Shift+A",
+            Self::ToggleEmotePicker => "Ctrl+E",
+            Self::ToggleSettings => "Ctrl+,",
+        }
+        .parse()
+        .expect("default keybindings are valid")
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::FocusInput => "Focus the message input",
+            Self::HistoryPrevious => "Previous sent message",
+            Self::HistoryNext => "Next sent message",
+            Self::Undo => "Undo typing",
+            Self::Redo => "Redo typing",
+            Self::CompleteMention => "Complete a mention",
+            Self::CloseTab => "Close tab",
+            Self::ReopenClosedTab => "Reopen closed tab",
+            Self::NextTab => "Next tab",
+            Self::PreviousTab => "Previous tab",
+            Self::JoinChannel => "Join a channel",
+            Self::MarkAllRead => "Mark all read",
+            Self::ToggleEmotePicker => "Emote picker",
+            Self::ToggleSettings => "Settings",
+        })
+    }
+}
+
+/// Keys that can be bound besides characters, by the name they're saved as
+const NAMED_KEYS: [(&str, Named); 24] = [
+    ("Enter", Named::Enter),
+    ("Escape", Named::Escape),
+    ("Tab", Named::Tab),
+    ("Space", Named::Space),
+    ("Backspace", Named::Backspace),
+    ("Delete", Named::Delete),
+    ("ArrowUp", Named::ArrowUp),
+    ("ArrowDown", Named::ArrowDown),
+    ("ArrowLeft", Named::ArrowLeft),
+    ("ArrowRight", Named::ArrowRight),
+    ("PageUp", Named::PageUp),
+    ("PageDown", Named::PageDown),
+    ("Home", Named::Home),
+    ("End", Named::End),
+    ("F1", Named::F1),
+    ("F2", Named::F2),
+    ("F3", Named::F3),
+    ("F4", Named::F4),
+    ("F5", Named::F5),
+    ("F6", Named::F6),
+    ("F7", Named::F7),
+    ("F8", Named::F8),
+    ("F9", Named::F9),
+    ("F10", Named::F10),
+];
+
+/// A key with modifiers, written like `Ctrl+Shift+T`. Ctrl stands for Cmd on
+/// macOS
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyCombo {
+    /// Lowercase character or one of [NAMED_KEYS]
+    key: String,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl KeyCombo {
+    /// The combination of a key press, [None] for keys that can't be bound
+    /// like the modifiers themselves
+    pub fn from_key(key: &keyboard::Key, modifiers: keyboard::Modifiers) -> Option<Self> {
+        let key = match key {
+            keyboard::Key::Character(c) => c.to_lowercase(),
+            keyboard::Key::Named(named) => NAMED_KEYS
+                .iter()
+                .find(|(_, n)| n == named)
+                .map(|(name, _)| (*name).to_owned())?,
+            keyboard::Key::Unidentified => return None,
+        };
+        Some(Self {
+            key,
+            ctrl: modifiers.command(),
+            shift: modifiers.shift(),
+            alt: modifiers.alt(),
+        })
+    }
+
+    /// Escape without modifiers, which cancels rebinding
+    pub fn is_escape(&self) -> bool {
+        self.key == "Escape" && !(self.ctrl || self.shift || self.alt)
+    }
+}
+
+impl FromStr for KeyCombo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // `+` itself can be bound, as in `Ctrl++`
+        let (modifiers, key) = match s.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None => s.rsplit_once('+').unwrap_or(("", s)),
+        };
+        let key = if let Some((name, _)) = NAMED_KEYS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        {
+            (*name).to_owned()
+        } else if key.chars().count() == 1 {
+            key.to_lowercase()
+        } else {
+            return Err(format!("unknown key {key:?}"));
+        };
+
+        let mut combo = Self {
+            key,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        };
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "cmd" => combo.ctrl = true,
+                "shift" => combo.shift = true,
+                "alt" => combo.alt = true,
+                _ => return Err(format!("unknown modifier {modifier:?}")),
+            }
+        }
+        Ok(combo)
+    }
+}
+
+impl Display for KeyCombo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "Ctrl+"),
+            (self.shift, "Shift+"),
+            (self.alt, "Alt+"),
+        ] {
+            if held {
+                f.write_str(name)?;
+            }
+        }
+        if self.key.chars().count() == 1 {
+            f.write_str(&self.key.to_uppercase())
+        } else {
+            f.write_str(&self.key)
+        }
+    }
+}
+
+impl TryFrom<String> for KeyCombo {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<KeyCombo> for String {
+    fn from(value: KeyCombo) -> Self {
+        value.to_string()
+    }
+}
+
+/// Keybindings changed from their [Action::default_binding]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Keybindings(BTreeMap<Action, KeyCombo>);
+
+impl Keybindings {
+    pub fn get(&self, action: Action) -> KeyCombo {
+        self.0
+            .get(&action)
+            .cloned()
+            .unwrap_or_else(|| action.default_binding())
+    }
+
+    pub fn is_default(&self, action: Action) -> bool {
+        !self.0.contains_key(&action)
+    }
+
+    pub fn set(&mut self, action: Action, combo: KeyCombo) {
+        if combo == action.default_binding() {
+            self.0.remove(&action);
+        } else {
+            self.0.insert(action, combo);
+        }
+    }
+
+    pub fn reset(&mut self, action: Action) {
+        self.0.remove(&action);
+    }
+
+    /// The action `combo` is bound to
+    pub fn action(&self, combo: &KeyCombo) -> Option<Action> {
+        Action::ALL.into_iter().find(|a| self.get(*a) == *combo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combos_round_trip() {
+        for s in [
+            "Ctrl+Shift+T",
+            "Enter",
+            "Alt+F4",
+            "Ctrl++",
+            "Ctrl+,",
+            "Shift+ArrowUp",
+        ] {
+            assert_eq!(s.parse::<KeyCombo>().unwrap().to_string(), s);
+        }
+        assert_eq!(
+            "ctrl+shift+t".parse::<KeyCombo>(),
+            "Ctrl+Shift+T".parse::<KeyCombo>()
+        );
+        assert!("Ctrl+Nope".parse::<KeyCombo>().is_err());
+        assert!("Hyper+T".parse::<KeyCombo>().is_err());
+    }
+
+    #[test]
+    fn default_bindings_dont_conflict() {
+        let bindings = Keybindings::default();
+        for action in Action::ALL {
+            assert_eq!(bindings.action(&action.default_binding()), Some(action));
+        }
+    }
+
+    #[test]
+    fn key_presses_match_bindings() {
+        let shift_t = KeyCombo::from_key(
+            &keyboard::Key::Character("T".into()),
+            keyboard::Modifiers::COMMAND | keyboard::Modifiers::SHIFT,
+        );
+        assert_eq!(shift_t, Some(Action::ReopenClosedTab.default_binding()));
+        let shift = KeyCombo::from_key(
+            &keyboard::Key::Named(Named::Shift),
+            keyboard::Modifiers::SHIFT,
+        );
+        assert_eq!(shift, None);
+    }
+}
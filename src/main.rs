@@ -1,52 +1,82 @@
-use std::sync::{
-    Arc,
-    atomic::{AtomicU64, Ordering},
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{
+        Arc, LazyLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::Poll,
+    time::{Duration, Instant},
 };
 
+use chrono::{DateTime, Utc};
 use futures::{SinkExt, Stream, StreamExt, TryFutureExt, channel::mpsc::UnboundedSender};
 use iced::{
-    Alignment, Color, Element, Length, Subscription, Task, Theme, stream,
-    widget::{container, opaque, space},
+    Alignment, Color, Element, Event, Length, Padding, Size, Subscription, Task, event, keyboard,
+    mouse, stream, system, theme,
+    widget::{
+        Column, button, column, container, mouse_area, opaque, row, rule, scrollable, space, text,
+    },
     window,
 };
 use indexmap::IndexMap;
 use itertools::Itertools;
 use twixel_core::{
     IrcMessage, MessageBuilder,
-    auth::Anonymous,
-    irc_message::{AnySemantic, PrivMsg, SemanticIrcMessage},
+    auth::{Anonymous, OAuthToken},
+    irc_message::{
+        AnySemantic, PrivMsg, SemanticIrcMessage, clearchat::TimeoutDuration, tags::OwnedTag,
+    },
 };
 
 use crate::{
-    chat::Chat,
-    components::join_popup::{self, JoinPopup},
-    config::CONFIG,
+    chat::{AnnouncementColor, Chat, SystemLine},
+    chat_log::{ChatLogger, LogEntry},
+    cli::ARGS,
+    components::{
+        join_popup::{self, JoinPopup},
+        setup::{self, Setup},
+    },
+    config::{CONFIG, ChannelLayout, Config, SETTINGS_GENERATION},
     config_ui::ConfigUi,
-    operation::switch_to_tab,
+    keybinds::{Action, KeyCombo},
+    operation::{focus_any, switch_to_tab},
     platform::{
+        ChannelEmote, EmotePlatform,
         betterttv::BetterTtvClient,
         frankerfacez::FfzClient,
+        pronouns,
         recent_messages::get_recent_messages,
         seventv::SevenTvClient,
-        twitch::{self, badges::load_badge},
+        twitch::{
+            self,
+            auth::{self, Token, Validation},
+            badges::load_badge,
+        },
     },
     title_bar::TitleBar,
-    widget::tabs::Tabs,
+    util::default_client,
+    widget::{scrollie::scrollie, tabs::Tabs},
 };
 
 mod chat;
+mod chat_log;
 mod cli;
 mod components;
 mod config;
 mod config_ui;
+mod demo;
+mod keybinds;
 mod operation;
 mod platform;
+mod sound;
 mod title_bar;
 mod util;
 mod widget;
 
 static MESSAGE_KEY: AtomicU64 = AtomicU64::new(1);
 
+static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(default_client);
+
 #[global_allocator]
 static ALLOC: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
@@ -54,27 +84,91 @@ enum IrcCommand {
     Join(String),
     Part(String),
     Message(String, String),
+    /// Reconnect logged in as the account, or anonymously
+    Login(Option<ChatLogin>),
+}
+
+/// The account chat is logged in as
+#[derive(Clone, PartialEq, Eq)]
+struct ChatLogin {
+    nick: String,
+    token: String,
+}
+
+impl ChatLogin {
+    /// The first account with a token that isn't in `expired`
+    fn from_config(expired: &[String]) -> Option<Self> {
+        CONFIG
+            .read()
+            .accounts
+            .iter()
+            .find(|a| !a.token().is_empty() && !expired.iter().any(|e| e == a.username()))
+            .map(|a| Self {
+                nick: a.username().to_owned(),
+                token: a.token().to_owned(),
+            })
+    }
+}
+
+/// State of the IRC connection as reported by [twitch_worker]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConnectionState {
+    #[default]
+    Connecting,
+    Connected,
+    Disconnected,
 }
 
 struct Juliarino {
     tabs_id: iced::widget::Id,
     irc_command: Option<UnboundedSender<IrcCommand>>,
+    connection: ConnectionState,
+    /// The account chat is connected as, nothing can be sent without one
+    login: Option<ChatLogin>,
+    /// Light or dark mode of the system, followed by the auto theme
+    system_theme: theme::Mode,
 
     seventv_client: Arc<SevenTvClient>,
     bttv_client: Arc<BetterTtvClient>,
     ffz_client: Arc<FfzClient>,
 
     join_window: Option<JoinPopup>,
+    /// Guides through adding an account and joining a channel on the first
+    /// launch
+    setup: Option<Setup>,
+    /// Tab waiting for confirmation to be closed since it has unsent input
+    confirm_close: Option<String>,
     channels: IndexMap<String, Chat>,
+    /// Channels that were closed with the position they had, the last one
+    /// closed last
+    closed_tabs: Vec<(String, usize)>,
+    /// Emotes usable in every channel, shared with each [Chat]
+    global_emotes: Arc<hashbrown::HashMap<String, ChannelEmote>>,
+    /// Highlighted messages across all channels, oldest first
+    mentions: VecDeque<(Arc<PrivMsg>, u64, DateTime<Utc>)>,
+    active_tab: Option<String>,
     show_config: bool,
     config: ConfigUi,
+    main_window: window::Id,
+    /// The app's window that has focus, if any
+    focused_window: Option<window::Id>,
+    /// When the last highlight notification was shown
+    last_notification: Option<Instant>,
+    logger: ChatLogger,
+    /// Accounts whose token is being refreshed, by username
+    refreshing: HashSet<String>,
+    /// Accounts whose token Twitch doesn't accept anymore, until they log in
+    /// again or it's dismissed
+    expired_accounts: Vec<String>,
+    /// Why logging was turned off, until it's dismissed
+    log_error: Option<String>,
     title_bar: TitleBar,
 }
 
 #[allow(clippy::enum_variant_names)]
 #[derive(Clone, Debug)]
 enum Message {
-    /// A new image was loaded into cache (emote or badge)
+    /// A new image (emote or badge) or a user's pronouns were loaded into cache
     ImageLoaded,
     ChannelSevenTvDataLoaded {
         login: String,
@@ -87,53 +181,171 @@ enum Message {
     ChannelFfzDataLoaded {
         login: String,
     },
+    /// Check which channels are streaming
+    PollLive,
+    /// Whether each of the polled channels is streaming, by login
+    LiveStatusLoaded(Vec<(String, bool)>),
+    /// The channel's own cheermotes were loaded, replacing the global ones
+    CheermotesLoaded(String),
+    /// A global emote set was loaded
+    GlobalEmotesLoaded,
+    /// Every emote set of the channel was loaded, except for the `failed` ones
+    ChannelEmotesDone {
+        login: String,
+        failed: Vec<EmotePlatform>,
+    },
 
     IrcConnected(UnboundedSender<IrcCommand>),
+    ConnectionStateChanged(ConnectionState),
 
     /// Close button on a tab was closed
     TabClosed(String),
+    /// Close the selected tab, as if its close button was clicked, or the
+    /// window a channel was popped out into
+    CloseActiveTab(window::Id),
+    /// Reset the unread counters of every channel and clear the mentions
+    MarkAllRead,
+    /// Close the tab awaiting confirmation, discarding its input
+    ConfirmCloseTab,
+    CancelCloseTab,
+    /// A different tab was selected
+    TabSelected(String),
     /// A tab open request was made for the given channel
     OpenJoin,
     CloseJoin,
     /// Should add this tab
     OpenTab(String),
+    /// Open the most recently closed tab again where it was
+    ReopenClosedTab,
     /// A channel has been successfully joined via IRC
     ChannelJoined(String),
     /// New message received over IRC
     NewMessage(PrivMsg),
-    RecentMessagesLoaded(String, Vec<IrcMessage>),
+    /// A moderator deleted the message with the given id
+    MessageDeleted {
+        channel: String,
+        id: String,
+    },
+    /// A user was timed out or banned, or the whole chat was cleared if
+    /// there's no login
+    MessagesCleared {
+        channel: String,
+        login: Option<String>,
+        /// How long `login` was timed out for, [None] if they were banned
+        duration: Option<Duration>,
+    },
+    /// Our USERSTATE in a channel, received after joining it or sending a
+    /// message
+    UserStateReceived {
+        channel: String,
+        is_mod: bool,
+        is_vip: bool,
+    },
+    /// The channel's slow mode changed, in seconds between messages
+    SlowModeChanged {
+        channel: String,
+        slow: u64,
+    },
+    /// An IRC event shown as a line in `channel`, or in every channel without
+    /// one. See [Message::system_event]
+    SystemEvent {
+        channel: Option<String>,
+        event: AnySemantic,
+    },
+    RecentMessagesLoaded(String, Result<Vec<IrcMessage>, String>),
+    /// Messages sent before the oldest one in the channel's scrollback
+    OlderMessagesLoaded(String, Result<Vec<IrcMessage>, String>),
     /// Message for [components::join_popup::JoinPopup]
     ToggleSettings,
     JoinPopupMessage(join_popup::Message),
+    SetupMessage(setup::Message),
+    /// The first launch setup is done, joining the channel if one was picked
+    SetupDone(Option<String>),
     /// Message for [chat::Chat]
     ChatMessage(String, chat::Message),
     /// Message for [config_ui::ConfigUi]
     ConfigMessage(config_ui::Message),
     /// Message for [title_bar::TitleBar]
     TitleBarMessage(title_bar::Message),
+    /// Focus the message input of the chat visible in the window
+    FocusInput(window::Id),
+    /// Message for the [chat::Chat] visible in the window
+    ActiveChatMessage(window::Id, chat::Message),
+    /// The window was closed, closing the main one quits
+    WindowClosed(window::Id),
+    /// The window gained or lost focus
+    WindowFocused(window::Id, bool),
+    DismissLogError,
+    /// The keybinding of the action was pressed in the window
+    Shortcut(window::Id, Action),
+    SystemThemeChanged(theme::Mode),
+    /// Passed to every chat, Shift changes what Enter does in the input
+    ModifiersChanged(keyboard::Modifiers),
+    /// Switch to the channel's tab and scroll to the message with the given key
+    JumpToMessage(String, u64),
+    /// Check whether Twitch still accepts the token of every account
+    ValidateTokens,
+    /// The token of the account with the username was checked, [None] if it's
+    /// invalid
+    TokenValidated(String, Result<Option<Validation>, String>),
+    /// Log in again as the account whose token expired
+    LogInAgain,
+    DismissExpiredAccount,
+    /// Refresh the tokens of accounts that expire soon
+    RefreshTokens,
+    /// The new token of the account with the username
+    TokenRefreshed(String, Result<Token, String>),
+    /// An announcement was sent in the channel through Helix
+    AnnouncementSent(String, Result<(), String>),
 }
 
 static IMAGE_GENERATION: AtomicU64 = AtomicU64::new(0);
 
+/// Id of the tab aggregating highlighted messages, `@` can't be part of a login
+const MENTIONS_TAB: &str = "@mentions";
+const MAX_MENTIONS: usize = 500;
+const MAX_CLOSED_TABS: usize = 20;
+const LIVE_POLL_INTERVAL: Duration = Duration::from_secs(120);
+const TOKEN_REFRESH_INTERVAL: Duration = Duration::from_secs(600);
+/// Least time between two highlight notifications, the ones in between are
+/// dropped
+const NOTIFICATION_INTERVAL: Duration = Duration::from_secs(10);
+
 impl Juliarino {
     fn new(channels: impl IntoIterator<Item = impl Into<String>>, main_window: window::Id) -> Self {
         let chats: IndexMap<String, Chat> = channels
             .into_iter()
             .map(|c| {
                 let c = c.into();
-                (c.clone(), Chat::new(c))
+                (c.clone(), Chat::new(c, Default::default()))
             })
             .collect();
         Self {
             tabs_id: iced::widget::Id::unique(),
             join_window: None,
+            setup: Setup::is_needed().then(Setup::new),
+            confirm_close: None,
             seventv_client: Arc::new(SevenTvClient::new()),
             bttv_client: Arc::new(BetterTtvClient::new()),
             ffz_client: Arc::new(FfzClient::new()),
             channels: chats,
+            closed_tabs: Vec::new(),
+            global_emotes: Default::default(),
+            mentions: VecDeque::new(),
+            active_tab: CONFIG.read().last_active.clone(),
             show_config: false,
             config: ConfigUi::new(),
             irc_command: None,
+            connection: ConnectionState::default(),
+            login: ChatLogin::from_config(&[]),
+            system_theme: theme::Mode::Dark,
+            main_window,
+            focused_window: Some(main_window),
+            last_notification: None,
+            logger: ChatLogger::default(),
+            refreshing: HashSet::new(),
+            expired_accounts: Vec::new(),
+            log_error: None,
             title_bar: TitleBar::new("Juliarino", main_window),
         }
     }
@@ -141,46 +353,60 @@ impl Juliarino {
     fn update(&mut self, msg: Message) -> Task<Message> {
         match msg {
             Message::IrcConnected(tx) => self.irc_command = Some(tx),
+            Message::ConnectionStateChanged(state) => self.connection = state,
             Message::RecentMessagesLoaded(chan, new) => {
                 let Some(chan) = self.channels.get_mut(&chan) else {
                     return Task::none();
                 };
-
-                let cur = &mut chan.messages;
-
-                for msg in new
-                    .into_iter()
-                    .filter_map(|m| PrivMsg::from_message(m).ok())
-                {
-                    let Some(ts) = msg.get_timestamp() else {
-                        continue;
-                    };
-
-                    if cur
-                        .front()
-                        .is_some_and(|l| l.0.get_timestamp().is_some_and(|t| t < ts))
-                    {
-                        cur.push_back((Arc::new(msg), MESSAGE_KEY.fetch_add(1, Ordering::Relaxed)));
-                        continue;
+                let new = match new {
+                    Ok(new) => new,
+                    Err(e) => {
+                        log::error!("couldn't load history of #{}: {e}", chan.channel);
+                        chan.history_error = Some(e);
+                        return Task::none();
                     }
+                };
+                chan.history_error = None;
 
-                    let idx = cur
-                        .iter()
-                        .enumerate()
-                        .filter_map(|m| Some((m.0, m.1.0.get_timestamp()?)))
-                        .tuple_windows::<(_, _)>()
-                        .find(|(a, b)| ts > a.1 && ts < b.1)
-                        .map(|r| r.1.0);
-
-                    if let Some(idx) = idx {
-                        cur.insert(
-                            idx,
-                            (Arc::new(msg), MESSAGE_KEY.fetch_add(1, Ordering::Relaxed)),
-                        );
-                    } else {
-                        cur.push_back((Arc::new(msg), MESSAGE_KEY.fetch_add(1, Ordering::Relaxed)));
+                let new = new
+                    .into_iter()
+                    .filter_map(|m| {
+                        chat::announcement(&m).or_else(|| PrivMsg::from_message(m).ok())
+                    })
+                    .collect_vec();
+                let task = load_images(&new);
+                chan.merge_history(new, || MESSAGE_KEY.fetch_add(1, Ordering::Relaxed));
+                return task;
+            }
+            Message::OlderMessagesLoaded(chan, older) => {
+                let Some(chan) = self.channels.get_mut(&chan) else {
+                    return Task::none();
+                };
+                let older = match older {
+                    Ok(older) => older,
+                    Err(e) => {
+                        log::error!("couldn't load older history of #{}: {e}", chan.channel);
+                        // allows trying again by scrolling back up
+                        chan.loading_older = false;
+                        return Task::none();
                     }
-                }
+                };
+                let older = older
+                    .into_iter()
+                    .filter_map(|m| {
+                        chat::announcement(&m).or_else(|| PrivMsg::from_message(m).ok())
+                    })
+                    .collect_vec();
+                let task = load_images(&older);
+                chan.prepend_messages(older.into_iter().map(|m| {
+                    let sent = chat::sent_at(&m);
+                    (
+                        Arc::new(m),
+                        MESSAGE_KEY.fetch_add(1, Ordering::Relaxed),
+                        sent,
+                    )
+                }));
+                return task;
             }
             Message::NewMessage(priv_msg) => {
                 let chan = priv_msg.channel_login();
@@ -188,39 +414,145 @@ impl Juliarino {
                     return Task::none();
                 };
 
-                let badge_tasks = priv_msg
-                    .badges()
-                    .map(|(set, id)| (set.to_owned(), id.to_owned()))
-                    .map(|(set, id)| Task::future(async { load_badge(set, id).await }));
-
-                let emote_tasks = priv_msg
-                    .emotes()
-                    .map(|e| Task::future(twitch::emotes::load_emote(e.0.to_owned())));
+                if self.active_tab.as_deref() != Some(chan) && chat.window.is_none() {
+                    chat.unread += 1;
+                }
 
-                while chat.messages.len() >= 500 {
-                    chat.messages.pop_front();
+                let task = load_images([&priv_msg]);
+                let key = MESSAGE_KEY.fetch_add(1, Ordering::Relaxed);
+                let highlighted = CONFIG.read().is_highlighted(priv_msg.message_text());
+                let msg = Arc::new(priv_msg);
+                chat.push_message(msg.clone(), key, highlighted);
+                if highlighted
+                    && self.focused_window.is_none()
+                    && CONFIG.read().ui.notify_on_mention
+                    && self
+                        .last_notification
+                        .is_none_or(|l| l.elapsed() >= NOTIFICATION_INTERVAL)
+                {
+                    self.last_notification = Some(Instant::now());
+                    notify_mention(&msg);
                 }
-                let task = Task::batch(badge_tasks.chain(emote_tasks)).then(|r| {
-                    if r {
-                        Task::done(Message::ImageLoaded)
-                    } else {
-                        Task::none()
+                if highlighted {
+                    let cfg = CONFIG.read();
+                    if let Some(path) = cfg.highlight_sound(msg.message_text()) {
+                        sound::play_highlight(path, cfg.sounds.volume);
                     }
-                });
-                let key = MESSAGE_KEY.fetch_add(1, Ordering::Relaxed);
-                chat.messages.push_back((Arc::new(priv_msg), key));
+                    drop(cfg);
+                    while self.mentions.len() >= MAX_MENTIONS {
+                        self.mentions.pop_front();
+                    }
+                    let sent = chat::sent_at(&msg);
+                    self.mentions.push_back((msg.clone(), key, sent));
+                }
+
+                let logging = CONFIG.read().logging;
+                if !logging.enabled {
+                    self.logger.close_all();
+                } else if let Err(e) = self.logger.append(
+                    msg.channel_login(),
+                    &LogEntry::new(&msg, chat::sent_at(&msg)),
+                    logging.format,
+                ) {
+                    self.disable_logging(e);
+                }
                 return task;
             }
+            Message::MessageDeleted { channel, id } => {
+                if let Some(chat) = self.channels.get_mut(&channel) {
+                    chat.delete_message(&id);
+                }
+            }
+            Message::MessagesCleared {
+                channel,
+                login,
+                duration,
+            } => {
+                if let Some(chat) = self.channels.get_mut(&channel) {
+                    chat.delete_messages_of(login.as_deref());
+                    chat.push_system(
+                        SystemLine::cleared(login.as_deref(), duration),
+                        MESSAGE_KEY.fetch_add(1, Ordering::Relaxed),
+                    );
+                }
+            }
+            Message::UserStateReceived {
+                channel,
+                is_mod,
+                is_vip,
+            } => {
+                if let Some(chat) = self.channels.get_mut(&channel) {
+                    chat.is_mod = is_mod;
+                    chat.is_vip = is_vip;
+                }
+            }
+            Message::SlowModeChanged { channel, slow } => {
+                if let Some(chat) = self.channels.get_mut(&channel) {
+                    chat.slow_mode = slow;
+                }
+            }
+            Message::SystemEvent { channel, event } => {
+                let Some(line) = chat::system_line(&event) else {
+                    return Task::none();
+                };
+                let chats = self
+                    .channels
+                    .iter_mut()
+                    .filter(|(chan, _)| channel.as_ref().is_none_or(|c| c == *chan));
+                for (_, chat) in chats {
+                    chat.push_system(line.clone(), MESSAGE_KEY.fetch_add(1, Ordering::Relaxed));
+                }
+            }
+            Message::TabClosed(tab) if tab == MENTIONS_TAB => self.mentions.clear(),
             Message::TabClosed(tab) => {
+                if self
+                    .channels
+                    .get(&tab)
+                    .is_some_and(|c| !c.message.trim().is_empty())
+                {
+                    self.confirm_close = Some(tab);
+                } else {
+                    return self.close_tab(tab);
+                }
+            }
+            Message::ConfirmCloseTab => {
+                if let Some(tab) = self.confirm_close.take() {
+                    return self.close_tab(tab);
+                }
+            }
+            Message::CancelCloseTab => self.confirm_close = None,
+            Message::MarkAllRead => {
+                for chat in self.channels.values_mut() {
+                    chat.unread = 0;
+                }
+                self.mentions.clear();
+            }
+            Message::CloseActiveTab(window) if window != self.main_window => {
+                return window::close(window);
+            }
+            Message::CloseActiveTab(_) => {
+                // the settings and join popup aren't tabs
+                if self.show_config || self.join_window.is_some() {
+                    return Task::none();
+                }
+                if let Some(tab) = self.active_tab.clone()
+                    && (tab == MENTIONS_TAB || self.channels.contains_key(&tab))
+                {
+                    return self.update(Message::TabClosed(tab));
+                }
+            }
+            Message::TabSelected(tab) => {
                 let mut config = CONFIG.write();
-                config.chats.retain(|c| c != &tab);
-                config.save().unwrap();
+                config.last_active = Some(tab.clone());
+                if let Err(e) = config.save() {
+                    log::error!("Error when saving active tab: {e}");
+                }
                 drop(config);
 
-                self.channels.shift_remove(&tab);
-                if let Some(tx) = &self.irc_command {
-                    tx.unbounded_send(IrcCommand::Part(tab)).unwrap();
+                if let Some(chat) = self.channels.get_mut(&tab) {
+                    chat.unread = 0;
                 }
+                self.active_tab = Some(tab);
             }
             Message::OpenJoin => {
                 self.join_window = Some(JoinPopup::new());
@@ -228,110 +560,288 @@ impl Juliarino {
             Message::CloseJoin => {
                 self.join_window = None;
             }
-            Message::OpenTab(tab) => {
-                let tab = tab.to_lowercase().trim().to_owned();
-                let mut config = CONFIG.write();
-                config.chats.push(tab.clone());
-                config.save().unwrap();
-                drop(config);
-
-                self.channels.insert(tab.clone(), Chat::new(tab.clone()));
-                if let Some(tx) = &self.irc_command {
-                    tx.unbounded_send(IrcCommand::Join(tab.clone())).unwrap();
+            Message::OpenTab(tab) => return self.open_tab(tab, None),
+            Message::ReopenClosedTab => {
+                if let Some((tab, at)) = self.closed_tabs.pop() {
+                    return self.open_tab(tab, Some(at));
                 }
-                self.join_window = None;
-                return switch_to_tab(self.tabs_id.clone(), tab).discard();
             }
             Message::ChannelJoined(chan) => {
-                let stv = self.seventv_client.clone();
-                let bttv = self.bttv_client.clone();
-                let ffz = self.ffz_client.clone();
-                let chan2 = chan.clone();
-                let emotes_task = Task::future(async move {
-                    let data =
-                        reqwest::get(format!("https://api.ivr.fi/v2/twitch/user?login={}", &chan))
-                            .and_then(|r| r.json::<serde_json::Value>())
-                            .inspect_err(|e| log::error!("{e}\n{e:?}"))
-                            .await;
-
-                    if let Some(id) = data.ok().as_ref().and_then(|d| d[0]["id"].as_str()) {
-                        let (stve, bttve, ffze) = futures::future::join3(
-                            stv.load_channel_emote_set(id.to_owned()),
-                            bttv.load_channel_emote_set(id.to_owned()),
-                            ffz.load_channel_emote_set_login(chan.clone()),
-                        )
-                        .await;
-                        (chan, id.to_owned(), stve, bttve, ffze)
-                    } else {
-                        (chan, "".into(), false, false, false)
-                    }
-                })
-                .then(|(c, id, s, b, f)| {
-                    let task = if s {
-                        Task::done(Message::ChannelSevenTvDataLoaded {
-                            login: c.clone(),
-                            id: id.clone(),
-                        })
-                    } else {
-                        Task::none()
-                    };
-                    let task = if b {
-                        task.chain(Task::done(Message::ChannelBttvDataLoaded {
-                            login: c.clone(),
-                            id: id.clone(),
-                        }))
-                    } else {
-                        task
-                    };
-                    if f {
-                        task.chain(Task::done(Message::ChannelFfzDataLoaded {
-                            login: c.clone(),
-                        }))
-                    } else {
-                        task
+                if let Some(chat) = self.channels.get_mut(&chan) {
+                    chat.push_system(
+                        SystemLine::joined(&chan),
+                        MESSAGE_KEY.fetch_add(1, Ordering::Relaxed),
+                    );
+                }
+                let emotes_task = self.load_channel_emotes(chan.clone());
+                // the demo only shows its own messages, not the channel's
+                // history or whether it's live
+                if ARGS.demo {
+                    return emotes_task;
+                }
+                let cheermotes = match &self.login {
+                    Some(login) => load_channel_cheermotes(chan.clone(), login.token.clone()),
+                    None => Task::none(),
+                };
+                return Task::batch([
+                    emotes_task,
+                    load_recent_messages(chan.clone()),
+                    cheermotes,
+                    poll_live(vec![chan]),
+                ]);
+            }
+            Message::CheermotesLoaded(chan) => {
+                if let Some(chat) = self.channels.get(&chan) {
+                    return load_images(chat.messages.iter().map(|m| &*m.0));
+                }
+            }
+            Message::PollLive => {
+                return poll_live(self.channels.keys().cloned().collect());
+            }
+            Message::LiveStatusLoaded(status) => {
+                for (login, live) in status {
+                    if let Some(chat) = self.channels.get_mut(&login) {
+                        chat.live = Some(live);
                     }
-                });
-
-                let recent_task = Task::future(async move {
-                    let msgs = get_recent_messages(&chan2).await;
-                    Message::RecentMessagesLoaded(chan2, msgs)
-                });
-
-                return Task::batch([emotes_task, recent_task]);
+                }
             }
             Message::ToggleSettings => {
                 self.show_config = !self.show_config;
             }
+            Message::RefreshTokens => {
+                // refreshed while the token is still valid for at least one
+                // more check
+                let margin = chrono::Duration::from_std(TOKEN_REFRESH_INTERVAL * 2).unwrap();
+                let cfg = CONFIG.read();
+                let due = cfg
+                    .accounts
+                    .iter()
+                    .filter(|a| !self.refreshing.contains(a.username()))
+                    .filter_map(|a| {
+                        Some((a.username().to_owned(), a.needs_refresh(margin)?.to_owned()))
+                    })
+                    .collect_vec();
+                drop(cfg);
+                return Task::batch(
+                    due.into_iter()
+                        .map(|(username, refresh_token)| self.refresh(username, refresh_token)),
+                );
+            }
+            Message::ValidateTokens => {
+                let cfg = CONFIG.read();
+                return Task::batch(
+                    cfg.accounts
+                        .iter()
+                        .filter(|a| !a.token().is_empty())
+                        .map(|a| {
+                            let username = a.username().to_owned();
+                            let token = a.token().to_owned();
+                            Task::future(async move {
+                                let validation = auth::validate(&token).await;
+                                Message::TokenValidated(
+                                    username,
+                                    validation.map_err(|e| e.to_string()),
+                                )
+                            })
+                        })
+                        .collect_vec(),
+                );
+            }
+            Message::TokenValidated(username, Ok(Some(validation))) => {
+                let mut cfg = CONFIG.write();
+                if let Some(account) = cfg.account_mut(&username) {
+                    account.validated(&validation);
+                    if let Err(e) = cfg.save() {
+                        log::error!("Error when saving settings: {e}");
+                    }
+                }
+                // it might expire before the first periodic refresh
+                return Task::done(Message::RefreshTokens);
+            }
+            Message::TokenValidated(username, Ok(None)) => {
+                let refresh_token = CONFIG
+                    .read()
+                    .accounts
+                    .iter()
+                    .find(|a| a.username() == username)
+                    .and_then(|a| a.refresh_token().map(str::to_owned));
+                match refresh_token {
+                    Some(t) if !self.refreshing.contains(&username) => {
+                        return self.refresh(username, t);
+                    }
+                    Some(_) => (),
+                    None => {
+                        log::warn!("The token of {username} isn't valid anymore");
+                        self.token_expired(username);
+                    }
+                }
+            }
+            Message::TokenValidated(username, Err(e)) => {
+                log::warn!("Couldn't validate the token of {username}: {e}");
+            }
+            Message::LogInAgain => {
+                self.show_config = true;
+                return self.config.open_login().map(Message::ConfigMessage);
+            }
+            Message::DismissExpiredAccount => {
+                if !self.expired_accounts.is_empty() {
+                    self.expired_accounts.remove(0);
+                }
+            }
+            Message::TokenRefreshed(username, token) => {
+                self.refreshing.remove(&username);
+                match token {
+                    Ok(token) => {
+                        let mut cfg = CONFIG.write();
+                        cfg.add_account(token);
+                        if let Err(e) = cfg.save() {
+                            log::error!("Error when saving settings: {e}");
+                        }
+                        drop(cfg);
+                        self.update_login();
+                    }
+                    Err(e) => {
+                        log::warn!("Couldn't refresh the token of {username}: {e}");
+                        self.token_expired(username);
+                    }
+                }
+            }
+            Message::AnnouncementSent(channel, Err(e)) => {
+                if let Some(chat) = self.channels.get_mut(&channel) {
+                    chat.push_system(
+                        SystemLine::notice(format!("Couldn't send the announcement: {e}")),
+                        MESSAGE_KEY.fetch_add(1, Ordering::Relaxed),
+                    );
+                }
+            }
+            // it comes back through IRC like any other
+            Message::AnnouncementSent(_, Ok(())) => (),
             Message::JoinPopupMessage(m) => {
                 if let Some(p) = &mut self.join_window {
                     return p.update(m).discard();
                 }
             }
+            Message::SetupMessage(m) => {
+                if let Some(s) = &mut self.setup {
+                    return s.update(m).map(Message::SetupMessage);
+                }
+            }
+            Message::SetupDone(channel) => {
+                self.setup = None;
+                self.update_login();
+                if let Some(channel) = channel {
+                    return self.open_tab(channel, None);
+                }
+            }
             Message::ChatMessage(chat, msg) => {
                 let Some(chat_elem) = self.channels.get_mut(&chat) else {
                     return Task::none();
                 };
-                if matches!(msg, chat::Message::SendMessage)
-                    && let Some(tx) = &self.irc_command
-                {
-                    let _ = tx.unbounded_send(IrcCommand::Message(
-                        chat_elem.channel.clone(),
-                        chat_elem.message.clone(),
-                    ));
+                if matches!(msg, chat::Message::LoadOlderMessages) {
+                    let before = chat_elem.messages.front().map(|m| m.2.timestamp_millis());
+                    return Task::future(async move {
+                        let msgs = get_recent_messages(&chat, before).await;
+                        Message::OlderMessagesLoaded(chat, msgs.map_err(|e| e.to_string()))
+                    });
+                }
+                if matches!(msg, chat::Message::RetryHistory) {
+                    chat_elem.history_error = None;
+                    return load_recent_messages(chat);
                 }
-                return chat_elem
-                    .update(msg)
-                    .map(move |m| Message::ChatMessage(chat.clone(), m));
+                if matches!(msg, chat::Message::ReloadEmotes) {
+                    if chat_elem.reloading_emotes {
+                        return Task::none();
+                    }
+                    chat_elem.reloading_emotes = true;
+                    return self.load_channel_emotes(chat);
+                }
+                if matches!(msg, chat::Message::TogglePopOut) {
+                    if let Some(window) = chat_elem.window {
+                        return window::close(window);
+                    }
+                    let (window, task) = window::open(window::Settings {
+                        size: Size::new(420.0, 640.0),
+                        ..Default::default()
+                    });
+                    chat_elem.window = Some(window);
+                    return task.discard();
+                }
+                let mut announce = Task::none();
+                if matches!(msg, chat::Message::SendMessage) {
+                    if let Some(Ok((color, text))) = chat::parse_announce(&chat_elem.message) {
+                        announce = send_announcement(
+                            self.login.clone(),
+                            chat.clone(),
+                            color,
+                            text.to_owned(),
+                        );
+                    } else if let Some(tx) = &self.irc_command {
+                        let _ = tx.unbounded_send(IrcCommand::Message(
+                            chat_elem.channel.clone(),
+                            chat_elem.outgoing_message(),
+                        ));
+                    }
+                }
+                return Task::batch([
+                    announce,
+                    chat_elem
+                        .update(msg)
+                        .map(move |m| Message::ChatMessage(chat.clone(), m)),
+                ]);
             }
             Message::ConfigMessage(msg) => {
-                self.config.update(msg);
+                if let config_ui::Message::LoginFinished(Ok(token)) = &msg {
+                    self.expired_accounts.retain(|a| *a != token.login);
+                }
+                let highlights = highlight_settings(&CONFIG.read());
+                let generation = SETTINGS_GENERATION.load(Ordering::Relaxed);
+                let task = self.config.update(msg).map(Message::ConfigMessage);
+                // accounts are added and removed from the settings
+                self.update_login();
+                if highlight_settings(&CONFIG.read()) != highlights {
+                    for chat in self.channels.values_mut() {
+                        chat.update_highlights();
+                    }
+                }
+                if SETTINGS_GENERATION.load(Ordering::Relaxed) != generation {
+                    for chat in self.channels.values_mut() {
+                        chat.update_blocked();
+                    }
+                }
+                return task;
             }
             Message::TitleBarMessage(message) => return self.title_bar.update(message).discard(),
+            Message::GlobalEmotesLoaded => {
+                let mut globals = hashbrown::HashMap::new();
+                // earlier providers win when names collide
+                let sets = [
+                    self.seventv_client.globals(),
+                    self.bttv_client.globals(),
+                    self.ffz_client.globals(),
+                ];
+                for emote in sets.iter().flatten().flat_map(|g| g.iter()) {
+                    globals
+                        .entry(emote.text_name().to_owned())
+                        .or_insert_with(|| emote.clone());
+                }
+                self.global_emotes = Arc::new(globals);
+                for chat in self.channels.values_mut() {
+                    chat.set_global_emotes(self.global_emotes.clone());
+                }
+            }
             Message::ChannelSevenTvDataLoaded { login, id } => {
-                if let (Some(chan), Some(emotes)) = (
-                    self.channels.get_mut(&login),
-                    self.seventv_client.channel_emote_set(&id),
-                ) {
+                let Poll::Ready(emotes) = self.seventv_client.try_channel_emote_set(&id) else {
+                    // still being written, wait for it instead of blocking the UI
+                    let client = self.seventv_client.clone();
+                    return Task::future(async move {
+                        client.channels_written().await;
+                        Message::ChannelSevenTvDataLoaded { login, id }
+                    });
+                };
+                if let (Some(chan), Some(emotes)) = (self.channels.get_mut(&login), emotes) {
+                    // replace rather than merge, so removed emotes disappear on reload
+                    chan.emotes
+                        .retain(|_, e| e.metadata.platform != EmotePlatform::SevenTv);
                     chan.emotes
                         .extend(emotes.iter().map(|e| (e.text_name().to_owned(), e.clone())));
                     return chan
@@ -340,10 +850,18 @@ impl Juliarino {
                 }
             }
             Message::ChannelBttvDataLoaded { login, id } => {
-                if let (Some(chan), Some(emotes)) = (
-                    self.channels.get_mut(&login),
-                    self.bttv_client.channel_emote_set(&id),
-                ) {
+                let Poll::Ready(emotes) = self.bttv_client.try_channel_emote_set(&id) else {
+                    // still being written, wait for it instead of blocking the UI
+                    let client = self.bttv_client.clone();
+                    return Task::future(async move {
+                        client.channels_written().await;
+                        Message::ChannelBttvDataLoaded { login, id }
+                    });
+                };
+                if let (Some(chan), Some(emotes)) = (self.channels.get_mut(&login), emotes) {
+                    // replace rather than merge, so removed emotes disappear on reload
+                    chan.emotes
+                        .retain(|_, e| e.metadata.platform != EmotePlatform::BetterTtv);
                     chan.emotes
                         .extend(emotes.iter().map(|e| (e.text_name().to_owned(), e.clone())));
                     return chan
@@ -351,11 +869,26 @@ impl Juliarino {
                         .map(move |m| Message::ChatMessage(login.clone(), m));
                 }
             }
+            Message::ChannelEmotesDone { login, failed } => {
+                if let Some(chan) = self.channels.get_mut(&login) {
+                    chan.failed_emotes = failed;
+                    chan.reloading_emotes = false;
+                }
+            }
             Message::ChannelFfzDataLoaded { login } => {
-                if let (Some(chan), Some(emotes)) = (
-                    self.channels.get_mut(&login),
-                    self.ffz_client.channel_emote_set_login(&login),
-                ) {
+                let Poll::Ready(emotes) = self.ffz_client.try_channel_emote_set_login(&login)
+                else {
+                    // still being written, wait for it instead of blocking the UI
+                    let client = self.ffz_client.clone();
+                    return Task::future(async move {
+                        client.channels_written().await;
+                        Message::ChannelFfzDataLoaded { login }
+                    });
+                };
+                if let (Some(chan), Some(emotes)) = (self.channels.get_mut(&login), emotes) {
+                    // replace rather than merge, so removed emotes disappear on reload
+                    chan.emotes
+                        .retain(|_, e| e.metadata.platform != EmotePlatform::FrankerFaceZ);
                     chan.emotes
                         .extend(emotes.iter().map(|e| (e.text_name().to_owned(), e.clone())));
                     return chan
@@ -363,6 +896,101 @@ impl Juliarino {
                         .map(move |m| Message::ChatMessage(login.clone(), m));
                 }
             }
+            Message::FocusInput(window) => {
+                if let Some(chat) = self.popped_out(window) {
+                    return iced::widget::operation::focus(chat.input_id.clone());
+                }
+                if self.show_config || self.join_window.is_some() {
+                    return Task::none();
+                }
+                // only the selected tab's chat is part of the main window's
+                // widget tree, so this ends up focusing that one
+                let inputs = self
+                    .channels
+                    .values()
+                    .filter(|c| c.window.is_none())
+                    .map(|c| c.input_id.clone())
+                    .collect();
+                return focus_any(inputs).discard();
+            }
+            Message::SystemThemeChanged(mode) => self.system_theme = mode,
+            Message::JumpToMessage(chan, key) => {
+                return self
+                    .select_tab(chan.clone())
+                    .chain(Task::done(Message::ChatMessage(
+                        chan,
+                        chat::Message::JumpTo(key),
+                    )));
+            }
+            Message::ActiveChatMessage(window, msg) => {
+                if let Some(chat) = self.popped_out(window) {
+                    let chan = chat.channel.clone();
+                    return self.update(Message::ChatMessage(chan, msg));
+                }
+                if window != self.main_window || self.show_config || self.join_window.is_some() {
+                    return Task::none();
+                }
+                if let Some(tab) = self.active_tab.clone() {
+                    return self.update(Message::ChatMessage(tab, msg));
+                }
+            }
+            Message::WindowClosed(window) if window == self.main_window => return iced::exit(),
+            Message::DismissLogError => self.log_error = None,
+            Message::Shortcut(window, action) => {
+                // the key is being bound to an action instead
+                if self.show_config && self.config.is_recording() {
+                    return Task::none();
+                }
+                let msg = match action {
+                    Action::FocusInput => Message::FocusInput(window),
+                    Action::HistoryPrevious => {
+                        Message::ActiveChatMessage(window, chat::Message::HistoryPrevious)
+                    }
+                    Action::HistoryNext => {
+                        Message::ActiveChatMessage(window, chat::Message::HistoryNext)
+                    }
+                    Action::Undo => Message::ActiveChatMessage(window, chat::Message::Undo),
+                    Action::Redo => Message::ActiveChatMessage(window, chat::Message::Redo),
+                    Action::CompleteMention => {
+                        Message::ActiveChatMessage(window, chat::Message::CompleteMention)
+                    }
+                    Action::CloseTab => Message::CloseActiveTab(window),
+                    Action::ReopenClosedTab => Message::ReopenClosedTab,
+                    Action::NextTab if window == self.main_window => return self.cycle_tab(1),
+                    Action::PreviousTab if window == self.main_window => {
+                        return self.cycle_tab(-1);
+                    }
+                    Action::NextTab | Action::PreviousTab => return Task::none(),
+                    Action::JoinChannel => Message::OpenJoin,
+                    Action::MarkAllRead => Message::MarkAllRead,
+                    Action::ToggleEmotePicker => {
+                        Message::ActiveChatMessage(window, chat::Message::ToggleEmotePicker)
+                    }
+                    Action::ToggleSettings => Message::ToggleSettings,
+                };
+                return self.update(msg);
+            }
+            Message::ModifiersChanged(modifiers) => {
+                for chat in self.channels.values_mut() {
+                    // only updates a flag, there's nothing to run
+                    let _ = chat.update(chat::Message::ModifiersChanged(modifiers));
+                }
+            }
+            Message::WindowFocused(window, true) => self.focused_window = Some(window),
+            Message::WindowFocused(window, false) => {
+                if self.focused_window == Some(window) {
+                    self.focused_window = None;
+                }
+            }
+            Message::WindowClosed(window) => {
+                if let Some(chat) = self
+                    .channels
+                    .values_mut()
+                    .find(|c| c.window == Some(window))
+                {
+                    chat.window = None;
+                }
+            }
             // Signaling messages
             Message::ImageLoaded => {
                 IMAGE_GENERATION.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
@@ -371,24 +999,276 @@ impl Juliarino {
         Task::none()
     }
 
+    /// Loads the emote sets available in every channel
+    fn load_global_emotes(&self) -> Task<Message> {
+        let stv = self.seventv_client.clone();
+        let bttv = self.bttv_client.clone();
+        let ffz = self.ffz_client.clone();
+        Task::batch([
+            Task::future(async move { stv.load_globals().await }),
+            Task::future(async move { bttv.load_globals().await }),
+            Task::future(async move { ffz.load_globals().await }),
+        ])
+        .map(|_| Message::GlobalEmotesLoaded)
+    }
+
+    /// Loads the 7TV, BTTV and FFZ emote sets of `chan`
+    fn load_channel_emotes(&self, chan: String) -> Task<Message> {
+        let stv = self.seventv_client.clone();
+        let bttv = self.bttv_client.clone();
+        let ffz = self.ffz_client.clone();
+        let providers = CONFIG.read().ui.emote_providers;
+        Task::future(async move {
+            // None for disabled providers, whether it loaded otherwise
+            let id = twitch::users::resolve_user_id(&chan)
+                .await
+                .inspect_err(|e| log::error!("{e}"));
+            if let Ok(id) = id {
+                let id = id.as_str();
+                let (stve, bttve, ffze) = futures::future::join3(
+                    async {
+                        match providers.seventv {
+                            true => Some(stv.load_channel_emote_set(id.to_owned()).await),
+                            false => None,
+                        }
+                    },
+                    async {
+                        match providers.bttv {
+                            true => Some(bttv.load_channel_emote_set(id.to_owned()).await),
+                            false => None,
+                        }
+                    },
+                    async {
+                        match providers.ffz {
+                            true => Some(ffz.load_channel_emote_set_login(chan.clone()).await),
+                            false => None,
+                        }
+                    },
+                )
+                .await;
+                (chan, id.to_owned(), stve, bttve, ffze)
+            } else {
+                (
+                    chan,
+                    "".into(),
+                    providers.seventv.then_some(false),
+                    providers.bttv.then_some(false),
+                    providers.ffz.then_some(false),
+                )
+            }
+        })
+        .then(|(c, id, s, b, f)| {
+            let mut task = Task::none();
+            let mut failed = Vec::new();
+            match s {
+                Some(true) => {
+                    task = task.chain(Task::done(Message::ChannelSevenTvDataLoaded {
+                        login: c.clone(),
+                        id: id.clone(),
+                    }))
+                }
+                Some(false) => failed.push(EmotePlatform::SevenTv),
+                None => (),
+            }
+            match b {
+                Some(true) => {
+                    task = task.chain(Task::done(Message::ChannelBttvDataLoaded {
+                        login: c.clone(),
+                        id: id.clone(),
+                    }))
+                }
+                Some(false) => failed.push(EmotePlatform::BetterTtv),
+                None => (),
+            }
+            match f {
+                Some(true) => {
+                    task = task.chain(Task::done(Message::ChannelFfzDataLoaded {
+                        login: c.clone(),
+                    }))
+                }
+                Some(false) => failed.push(EmotePlatform::FrankerFaceZ),
+                None => (),
+            }
+            task.chain(Task::done(Message::ChannelEmotesDone { login: c, failed }))
+        })
+    }
+
+    /// Selects the tab `step` tabs after the selected one in the main window,
+    /// wrapping around
+    fn cycle_tab(&self, step: isize) -> Task<Message> {
+        if self.show_config || self.join_window.is_some() {
+            return Task::none();
+        }
+        let tabs = (!self.mentions.is_empty())
+            .then_some(MENTIONS_TAB)
+            .into_iter()
+            .chain(
+                self.channels
+                    .iter()
+                    .filter(|(_, c)| c.window.is_none())
+                    .map(|(c, _)| c.as_str()),
+            )
+            .collect_vec();
+        if tabs.is_empty() {
+            return Task::none();
+        }
+        let current = self
+            .active_tab
+            .as_deref()
+            .and_then(|a| tabs.iter().position(|t| *t == a))
+            .unwrap_or(0);
+        let next = (current as isize + step).rem_euclid(tabs.len() as isize) as usize;
+        self.select_tab(tabs[next].to_owned())
+    }
+
+    /// Joins `tab` and selects it, inserting it at `at` if given or after the
+    /// other channels otherwise
+    fn open_tab(&mut self, tab: String, at: Option<usize>) -> Task<Message> {
+        let tab = tab.to_lowercase().trim().to_owned();
+        if self.channels.contains_key(&tab) {
+            self.join_window = None;
+            return self.select_tab(tab);
+        }
+
+        CONFIG.write().push_recent_channel(&tab);
+        let at = at.unwrap_or(self.channels.len()).min(self.channels.len());
+        self.channels.shift_insert(
+            at,
+            tab.clone(),
+            Chat::new(tab.clone(), self.global_emotes.clone()),
+        );
+        self.save_channel_order();
+        if let Some(tx) = &self.irc_command {
+            tx.unbounded_send(IrcCommand::Join(tab.clone())).unwrap();
+        }
+        self.join_window = None;
+        self.select_tab(tab)
+    }
+
+    /// Leaves the channel of `tab` and forgets it, closing its window if it
+    /// was popped out. It can be reopened with [Message::ReopenClosedTab]
+    fn close_tab(&mut self, tab: String) -> Task<Message> {
+        let Some((at, _, chat)) = self.channels.shift_remove_full(&tab) else {
+            return Task::none();
+        };
+        if self.closed_tabs.len() >= MAX_CLOSED_TABS {
+            self.closed_tabs.remove(0);
+        }
+        self.closed_tabs.push((tab.clone(), at));
+        self.save_channel_order();
+        if let Some(tx) = &self.irc_command {
+            tx.unbounded_send(IrcCommand::Part(tab)).unwrap();
+        }
+        match chat.window {
+            Some(window) => window::close(window),
+            None => Task::none(),
+        }
+    }
+
+    /// Saves the order of [Self::channels] as the joined channels, it's the
+    /// source of truth for [config::Config::chats] so they can't drift apart
+    fn save_channel_order(&self) {
+        let mut config = CONFIG.write();
+        config.chats = self.channels.keys().cloned().collect();
+        if let Err(e) = config.save() {
+            log::error!("Error when saving channels: {e}");
+        }
+    }
+
+    /// Turns logging off after a log couldn't be written, rather than
+    /// failing on every message
+    fn disable_logging(&mut self, e: std::io::Error) {
+        log::error!("couldn't write chat log, turning logging off: {e}");
+        self.logger.close_all();
+        let mut config = CONFIG.write();
+        config.logging.enabled = false;
+        if let Err(e) = config.save() {
+            log::error!("Error when saving settings: {e}");
+        }
+        self.log_error = Some(e.to_string());
+    }
+
+    /// Exchanges the refresh token of the account for a new token
+    fn refresh(&mut self, username: String, refresh_token: String) -> Task<Message> {
+        self.refreshing.insert(username.clone());
+        Task::future(async move {
+            let token = auth::refresh(&refresh_token).await;
+            Message::TokenRefreshed(username, token.map_err(|e| e.to_string()))
+        })
+    }
+
+    /// Asks to log in again as the account, whose token can't be used anymore
+    fn token_expired(&mut self, username: String) {
+        if !self.expired_accounts.contains(&username) {
+            self.expired_accounts.push(username);
+        }
+        // chat falls back to another account, or anonymous
+        self.update_login();
+    }
+
+    /// Reconnects chat if the account it should be logged in as changed
+    fn update_login(&mut self) {
+        let login = ChatLogin::from_config(&self.expired_accounts);
+        if login == self.login {
+            return;
+        }
+        self.login = login.clone();
+        if let Some(tx) = &self.irc_command {
+            let _ = tx.unbounded_send(IrcCommand::Login(login));
+        }
+    }
+
+    /// The chat popped out into `window`
+    fn popped_out(&self, window: window::Id) -> Option<&Chat> {
+        self.channels.values().find(|c| c.window == Some(window))
+    }
+
+    /// Selects `tab` in whichever channel layout is shown, or focuses its
+    /// window if it was popped out
+    fn select_tab(&self, tab: String) -> Task<Message> {
+        if let Some(window) = self.channels.get(&tab).and_then(|c| c.window) {
+            return window::gain_focus(window);
+        }
+        switch_to_tab(self.tabs_id.clone(), tab.clone())
+            .discard()
+            .chain(Task::done(Message::TabSelected(tab)))
+    }
+
+    fn title(&self, window: window::Id) -> String {
+        let title = if cfg!(debug_assertions) {
+            concat!("Juliarino - ", env!("CARGO_PKG_VERSION"), " (DEBUG)")
+        } else {
+            concat!("Juliarino - ", env!("CARGO_PKG_VERSION"))
+        };
+        match self.popped_out(window) {
+            Some(chat) => format!("#{} - {title}", chat.channel),
+            None => title.to_owned(),
+        }
+    }
+
     fn view(&self, id: window::Id) -> Element<'_, Message> {
-        let tabs = self.channels.iter().map(|(c, chat)| {
-            let span = iced::debug::time(format!("chat view ({c})"));
-            let view = chat
-                .view()
-                .map(move |m| Message::ChatMessage(c.to_owned(), m));
-            span.finish();
-            (c.clone(), view)
-        });
+        if id != self.main_window {
+            return match self.popped_out(id) {
+                Some(chat) => chat
+                    .view(self.connection, self.login.is_none(), self.background())
+                    .map(move |m| Message::ChatMessage(chat.channel.clone(), m)),
+                None => space().into(),
+            };
+        }
 
-        let main: Element<'_, Message> = if self.show_config {
+        let main: Element<'_, Message> = if let Some(setup) = &self.setup {
+            setup.view().map(|m| match m {
+                setup::Message::Join => Message::SetupDone(Some(setup.channel())),
+                setup::Message::Skip => Message::SetupDone(None),
+                m => Message::SetupMessage(m),
+            })
+        } else if self.show_config {
             self.config.view().map(Message::ConfigMessage)
         } else {
-            Tabs::new(tabs)
-                .id(self.tabs_id.clone())
-                .on_close(Message::TabClosed)
-                .on_add(Message::OpenJoin)
-                .into()
+            match CONFIG.read().ui.layout {
+                ChannelLayout::Tabs => self.view_tabs(),
+                ChannelLayout::Sidebar => self.view_sidebar(),
+            }
         };
 
         let popup: Element<'_, Message> = self
@@ -397,7 +1277,7 @@ impl Juliarino {
             .map(|w| {
                 opaque(
                     container(w.view().map(|m| match m {
-                        join_popup::Message::Submit => Message::OpenTab(w.value.clone()),
+                        join_popup::Message::Submit => Message::OpenTab(w.login()),
                         join_popup::Message::Close => Message::CloseJoin,
                         m => Message::JoinPopupMessage(m),
                     }))
@@ -412,30 +1292,525 @@ impl Juliarino {
             })
             .unwrap_or_else(|| space().into());
 
+        let confirm: Element<'_, Message> = self
+            .confirm_close
+            .as_deref()
+            .map(|tab| {
+                opaque(
+                    container(
+                        container(
+                            column![
+                                text(format!("Close #{tab}? The unsent message will be lost.")),
+                                row![
+                                    button("Close tab")
+                                        .style(button::danger)
+                                        .on_press(Message::ConfirmCloseTab),
+                                    button("Cancel")
+                                        .style(button::secondary)
+                                        .on_press(Message::CancelCloseTab),
+                                ]
+                                .spacing(6),
+                            ]
+                            .spacing(12),
+                        )
+                        .style(container::rounded_box)
+                        .padding(20),
+                    )
+                    .center(Length::Fill)
+                    .style(|_| {
+                        container::Style::default().background(Color::BLACK.scale_alpha(0.3))
+                    }),
+                )
+            })
+            .unwrap_or_else(|| space().into());
+
         // let status_bar = Row::new()
         //     .push(Button::new("Settings").on_press(Message::ToggleSettings))
         //     .height(24)
         //     .width(Length::Fill);
 
         // let view = column![main, status_bar];
-        let view = main;
-        iced::widget::stack!(view, popup).into()
+        let banners = [
+            self.log_error.as_deref().map(view_log_error),
+            self.expired_accounts
+                .first()
+                .map(|a| view_expired_account(a)),
+        ];
+        let view = Column::from_iter(banners.into_iter().flatten().chain([main]));
+        iced::widget::stack!(view, popup, confirm).into()
+    }
+
+    /// Background color of the active theme, for keeping text readable
+    fn background(&self) -> Color {
+        CONFIG
+            .read()
+            .ui
+            .theme(self.system_theme)
+            .palette()
+            .background
+    }
+
+    fn view_tabs(&self) -> Element<'_, Message> {
+        let background = self.background();
+        let tabs = self
+            .channels
+            .iter()
+            .filter(|(_, chat)| chat.window.is_none())
+            .map(|(c, chat)| {
+                let span = iced::debug::time(format!("chat view ({c})"));
+                let view = chat
+                    .view(self.connection, self.login.is_none(), background)
+                    .map(move |m| Message::ChatMessage(c.to_owned(), m));
+                span.finish();
+                (c.clone(), chat.live, view)
+            });
+        let mentions = (!self.mentions.is_empty())
+            .then(|| (MENTIONS_TAB.to_owned(), None, self.view_mentions()));
+        let tabs = mentions.into_iter().chain(tabs);
+
+        Tabs::with_live(tabs)
+            .id(self.tabs_id.clone())
+            .on_close(Message::TabClosed)
+            .on_select(Message::TabSelected)
+            .on_add(Message::OpenJoin)
+            .single_row(CONFIG.read().ui.single_row_tabs)
+            .into()
+    }
+
+    /// Lists the channels in a column on the left, showing only the selected one
+    fn view_sidebar(&self) -> Element<'_, Message> {
+        let active = self
+            .active_tab
+            .as_deref()
+            .filter(|t| {
+                *t == MENTIONS_TAB || self.channels.get(*t).is_some_and(|c| c.window.is_none())
+            })
+            .or_else(|| {
+                self.channels
+                    .iter()
+                    .find(|(_, c)| c.window.is_none())
+                    .map(|(c, _)| c.as_str())
+            });
+
+        let entry = |name: &str, unread: usize| {
+            let label = if unread > 0 {
+                format!("{name} ({unread})")
+            } else {
+                name.to_owned()
+            };
+            let selected = active == Some(name);
+            Element::from(
+                row![
+                    button(text(label))
+                        .on_press(Message::TabSelected(name.to_owned()))
+                        .style(if selected {
+                            button::primary
+                        } else {
+                            button::subtle
+                        })
+                        .width(Length::Fill),
+                    button(text("×"))
+                        .on_press(Message::TabClosed(name.to_owned()))
+                        .style(button::subtle),
+                ]
+                .align_y(Alignment::Center),
+            )
+        };
+
+        let mentions = (!self.mentions.is_empty()).then(|| entry(MENTIONS_TAB, 0));
+        let channels = self
+            .channels
+            .iter()
+            .filter(|(_, chat)| chat.window.is_none())
+            .map(|(c, chat)| entry(c, chat.unread));
+        let sidebar = column(mentions.into_iter().chain(channels))
+            .push(
+                button(text("+"))
+                    .on_press(Message::OpenJoin)
+                    .style(button::subtle)
+                    .width(Length::Fill),
+            )
+            .push(
+                button(text("Mark all read").size(12))
+                    .on_press_maybe(
+                        (!self.mentions.is_empty() || self.channels.values().any(|c| c.unread > 0))
+                            .then_some(Message::MarkAllRead),
+                    )
+                    .style(button::subtle)
+                    .width(Length::Fill),
+            )
+            .spacing(2)
+            .padding(4)
+            .width(180);
+
+        let background = self.background();
+        let content = match active {
+            Some(MENTIONS_TAB) => self.view_mentions(),
+            Some(c) => self.channels[c]
+                .view(self.connection, self.login.is_none(), background)
+                .map(move |m| Message::ChatMessage(c.to_owned(), m)),
+            None => container(text("No channels joined"))
+                .center(Length::Fill)
+                .into(),
+        };
+
+        row![
+            scrollable(sidebar).height(Length::Fill),
+            rule::vertical(1).style(rule::weak),
+            content
+        ]
+        .into()
+    }
+
+    fn view_mentions(&self) -> Element<'_, Message> {
+        let background = self.background();
+        let children = self.mentions.iter().filter_map(|(msg, key, sent)| {
+            let chan = msg.channel_login();
+            let chat = self.channels.get(chan)?;
+            let line = row![
+                container(text(format!("#{chan}")).size(12).style(text::secondary))
+                    .padding(Padding::ZERO.left(6.0)),
+                chat.view_mention(msg, *sent, background)
+                    .map(move |m| Message::ChatMessage(chan.to_owned(), m)),
+            ]
+            .align_y(Alignment::Center);
+            Some((
+                mouse_area(line)
+                    .on_press(Message::JumpToMessage(chan.to_owned(), *key))
+                    .interaction(mouse::Interaction::Pointer),
+                *key,
+            ))
+        });
+        scrollie(children)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let recording = (self.show_config && self.config.is_recording())
+            .then(|| event::listen_with(record_key));
+        let live_polling =
+            (!ARGS.demo).then(|| iced::time::every(LIVE_POLL_INTERVAL).map(|_| Message::PollLive));
+        Subscription::batch(
+            [
+                Subscription::run(twitch_worker),
+                event::listen_with(keyboard_shortcut),
+                event::listen_with(window_focus),
+                event::listen_with(modifiers_changed),
+                system::theme_changes().map(Message::SystemThemeChanged),
+                iced::time::every(TOKEN_REFRESH_INTERVAL).map(|_| Message::RefreshTokens),
+                window::close_events().map(Message::WindowClosed),
+            ]
+            .into_iter()
+            .chain(recording)
+            .chain(live_polling),
+        )
     }
 }
 
+/// What decides which messages are highlighted, see [Config::is_highlighted]
+fn highlight_settings(cfg: &Config) -> (Vec<String>, Vec<String>) {
+    let accounts = cfg
+        .accounts
+        .iter()
+        .map(|a| a.username().to_owned())
+        .collect();
+    (accounts, cfg.highlight_terms.clone())
+}
+
+/// Loads the cheermotes of `chan`, which takes the token of an account
+fn load_channel_cheermotes(chan: String, token: String) -> Task<Message> {
+    Task::future(async move {
+        twitch::cheermotes::load_channel_cheermotes(chan.clone(), token)
+            .await
+            .inspect_err(|e| log::error!("couldn't load the cheermotes of #{chan}: {e}"))
+            .map(|()| chan)
+    })
+    .then(|r| match r {
+        Ok(chan) => Task::done(Message::CheermotesLoaded(chan)),
+        Err(_) => Task::none(),
+    })
+}
+
+/// Fetches whether each of `logins` is streaming
+fn poll_live(logins: Vec<String>) -> Task<Message> {
+    /// Most logins ivr.fi takes per request
+    const CHUNK: usize = 50;
+
+    if logins.is_empty() {
+        return Task::none();
+    }
+    Task::batch(logins.chunks(CHUNK).map(|chunk| {
+        let logins = chunk.join(",");
+        Task::future(async move {
+            let users = CLIENT
+                .get(format!("https://api.ivr.fi/v2/twitch/user?login={logins}"))
+                .send()
+                .and_then(|r| r.json::<Vec<serde_json::Value>>())
+                .await
+                .inspect_err(|e| log::error!("couldn't poll live channels: {e}"))
+                .unwrap_or_default();
+            let status = users
+                .iter()
+                .filter_map(|u| {
+                    let login = u["login"].as_str()?.to_owned();
+                    Some((login, !u["stream"].is_null()))
+                })
+                .collect();
+            Message::LiveStatusLoaded(status)
+        })
+    }))
+}
+
+/// Loads the messages sent in `chan` before it was joined
+fn load_recent_messages(chan: String) -> Task<Message> {
+    Task::future(async move {
+        let msgs = get_recent_messages(&chan, None).await;
+        Message::RecentMessagesLoaded(chan, msgs.map_err(|e| e.to_string()))
+    })
+}
+
+/// Announces `text` in `chan` through Helix as the account chat is logged in
+/// as, IRC doesn't take `/announce` anymore
+fn send_announcement(
+    login: Option<ChatLogin>,
+    chan: String,
+    color: AnnouncementColor,
+    text: String,
+) -> Task<Message> {
+    let Some(login) = login else {
+        return Task::none();
+    };
+    Task::future(async move {
+        let res = twitch::helix::send_announcement(
+            &chan,
+            &login.nick,
+            &login.token,
+            &text,
+            color.api_name(),
+        )
+        .await;
+        Message::AnnouncementSent(chan, res.map_err(|e| e.to_string()))
+    })
+}
+
+/// Loads every badge, emote and cheermote used in `msgs`, each only once
+fn load_images<'a>(msgs: impl IntoIterator<Item = &'a PrivMsg>) -> Task<Message> {
+    let mut badges = HashSet::new();
+    let mut emotes = HashSet::new();
+    let mut cheers = HashMap::new();
+    let mut users = HashSet::new();
+    let show_pronouns = CONFIG.read().ui.show_pronouns;
+    for msg in msgs {
+        if show_pronouns && let Some(login) = msg.get_username() {
+            users.insert(login.to_string());
+        }
+        badges.extend(
+            msg.badges()
+                .map(|(set, id)| (set.to_owned(), id.to_owned())),
+        );
+        emotes.extend(msg.emotes().map(|e| e.0.to_owned()));
+        if msg.get_tag(OwnedTag::Bits).is_some() {
+            cheers.extend(
+                twitch::cheermotes::cheers(msg.channel_login(), msg.message_text())
+                    .map(|c| ((c.prefix.clone(), c.tier()), c)),
+            );
+        }
+    }
+
+    let badge_tasks = badges
+        .into_iter()
+        .map(|(set, id)| Task::future(async { load_badge(set, id).await }));
+    let emote_tasks = emotes
+        .into_iter()
+        .map(|e| Task::future(twitch::emotes::load_emote(e)));
+    let cheer_tasks = cheers
+        .into_values()
+        .map(|c| Task::future(twitch::cheermotes::load_cheermote(c)));
+
+    let pronoun_tasks = users
+        .into_iter()
+        .map(|u| Task::future(pronouns::load_pronouns(u)));
+
+    Task::batch(
+        badge_tasks
+            .chain(emote_tasks)
+            .chain(cheer_tasks)
+            .chain(pronoun_tasks),
+    )
+    .then(|r| {
+        if r {
+            Task::done(Message::ImageLoaded)
+        } else {
+            Task::none()
+        }
+    })
+}
+
+fn view_log_error(error: &str) -> Element<'_, Message> {
+    container(
+        row![
+            text(format!(
+                "Chat logging was turned off, a log couldn't be written: {error}"
+            ))
+            .size(12)
+            .color(Color::from_rgb8(0xed, 0x87, 0x96))
+            .width(Length::Fill),
+            button(text("Dismiss").size(12))
+                .on_press(Message::DismissLogError)
+                .style(button::secondary)
+                .padding(Padding::ZERO.vertical(2.0).horizontal(6.0)),
+        ]
+        .spacing(6)
+        .align_y(Alignment::Center),
+    )
+    .padding(Padding::ZERO.vertical(4.0).horizontal(6.0))
+    .into()
+}
+
+fn view_expired_account(username: &str) -> Element<'_, Message> {
+    container(
+        row![
+            text(format!(
+                "Twitch doesn't accept the token of {username} anymore, log in again to use it"
+            ))
+            .size(12)
+            .color(Color::from_rgb8(0xed, 0x87, 0x96))
+            .width(Length::Fill),
+            button(text("Log in again").size(12))
+                .on_press(Message::LogInAgain)
+                .padding(Padding::ZERO.vertical(2.0).horizontal(6.0)),
+            button(text("Dismiss").size(12))
+                .on_press(Message::DismissExpiredAccount)
+                .style(button::secondary)
+                .padding(Padding::ZERO.vertical(2.0).horizontal(6.0)),
+        ]
+        .spacing(6)
+        .align_y(Alignment::Center),
+    )
+    .padding(Padding::ZERO.vertical(4.0).horizontal(6.0))
+    .into()
+}
+
+fn window_focus(event: Event, _status: event::Status, window: window::Id) -> Option<Message> {
+    match event {
+        Event::Window(window::Event::Focused) => Some(Message::WindowFocused(window, true)),
+        Event::Window(window::Event::Unfocused) => Some(Message::WindowFocused(window, false)),
+        _ => None,
+    }
+}
+
+/// Shows a desktop notification for a highlighted message
+fn notify_mention(msg: &PrivMsg) {
+    let user = msg
+        .get_tag(OwnedTag::DisplayName)
+        .or_else(|| msg.get_username().map(Into::into))
+        .unwrap_or_default();
+    let summary = format!("{user} in #{}", msg.channel_login());
+    let body = msg.message_text().to_owned();
+    // showing it can block on D-Bus
+    std::thread::spawn(move || {
+        let shown = notify_rust::Notification::new()
+            .appname("Juliarino")
+            .summary(&summary)
+            .body(&body)
+            .show();
+        if let Err(e) = shown {
+            log::error!("couldn't show notification: {e}");
+        }
+    });
+}
+
+/// Maps key presses to the action they're bound to
+fn keyboard_shortcut(event: Event, status: event::Status, window: window::Id) -> Option<Message> {
+    let Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) = event else {
+        return None;
+    };
+    let combo = KeyCombo::from_key(&key, modifiers)?;
+    // the focused input captures every key press, so only combinations with
+    // Ctrl go through it. It leaves the vertical arrows uncaptured
+    if status == event::Status::Captured && !combo.ctrl {
+        return None;
+    }
+    let action = CONFIG.read().keybindings.action(&combo)?;
+    Some(Message::Shortcut(window, action))
+}
+
+fn modifiers_changed(event: Event, _status: event::Status, _window: window::Id) -> Option<Message> {
+    match event {
+        Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+            Some(Message::ModifiersChanged(modifiers))
+        }
+        _ => None,
+    }
+}
+
+/// Passes every key press to the settings while a keybinding is recorded
+fn record_key(event: Event, _status: event::Status, _window: window::Id) -> Option<Message> {
+    let Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) = event else {
+        return None;
+    };
+    let combo = KeyCombo::from_key(&key, modifiers)?;
+    Some(Message::ConfigMessage(config_ui::Message::KeyRecorded(
+        combo,
+    )))
+}
+
+impl Message {
+    /// Routes `event` to the channel it's about, see [target_channel]
+    fn system_event(event: AnySemantic) -> Self {
+        Self::SystemEvent {
+            channel: target_channel(&event),
+            event,
+        }
+    }
+}
+
+/// The channel an IRC message is about, from its first param. [None] for the
+/// ones about the whole connection like GLOBALUSERSTATE, which go to every
+/// channel
+fn target_channel(msg: &AnySemantic) -> Option<String> {
+    msg.get_param(0)?.strip_prefix('#').map(str::to_owned)
+}
+
 fn twitch_worker() -> impl Stream<Item = Message> {
     stream::channel(100, async |mut output| {
         let (tx, mut rx) = futures::channel::mpsc::unbounded();
         output.send(Message::IrcConnected(tx)).await.unwrap();
+        if ARGS.demo {
+            return demo::run(output, rx).await;
+        }
+        // chat messages that couldn't be sent yet, kept across reconnects
+        let mut pending: Vec<(String, String)> = Vec::new();
+        let mut login = ChatLogin::from_config(&[]);
         loop {
-            let mut conn = twixel_core::Connection::new(CONFIG.read().chats.iter(), Anonymous {});
+            output
+                .send(Message::ConnectionStateChanged(ConnectionState::Connecting))
+                .await
+                .unwrap();
+            let chats = CONFIG.read().chats.clone();
+            let mut conn = match login.clone() {
+                Some(ChatLogin { nick, token }) => {
+                    twixel_core::Connection::new(chats.iter(), OAuthToken { token, nick })
+                }
+                None => twixel_core::Connection::new(chats.iter(), Anonymous {}),
+            };
             conn.start().await.unwrap();
+            let mut authenticated = false;
             loop {
                 futures::select! {
                     msg = conn.next() => match msg.map(|m| m.map(AnySemantic::from)) {
                         Some(Ok(AnySemantic::PrivMsg(msg))) => {
                             output.send(Message::NewMessage(msg)).await.unwrap();
                         },
+                        Some(Ok(AnySemantic::UserNotice(notice))) => {
+                            let msg = match chat::announcement(notice.inner()) {
+                                Some(msg) => Message::NewMessage(msg),
+                                None => Message::system_event(AnySemantic::UserNotice(notice)),
+                            };
+                            output.send(msg).await.unwrap();
+                        },
                         Some(Ok(AnySemantic::Ping(ping))) => {
                             conn.send(ping.respond().to_owned())
                                 .await
@@ -451,13 +1826,108 @@ fn twitch_worker() -> impl Stream<Item = Message> {
                                 .await
                                 .unwrap();
                         },
-                        Some(Ok(m)) => log::debug!("{}", m.inner().inner().trim()),
+                        Some(Ok(AnySemantic::ClearMsg(clear))) => {
+                            let (Some(chan), Some(id)) = (clear.get_param(0), clear.get_tag(OwnedTag::TargetMsgId)) else {
+                                continue;
+                            };
+                            output.send(Message::MessageDeleted {
+                                channel: chan.trim_start_matches('#').to_owned(),
+                                id: id.into_owned(),
+                            })
+                                .await
+                                .unwrap();
+                        },
+                        Some(Ok(AnySemantic::ClearChat(clear))) => {
+                            let Some(chan) = clear.get_param(0) else {
+                                continue;
+                            };
+                            output.send(Message::MessagesCleared {
+                                channel: chan.trim_start_matches('#').to_owned(),
+                                login: clear.target_login().map(|l| l.to_string()),
+                                duration: match clear.duration() {
+                                    TimeoutDuration::Temporary(d) => Some(d),
+                                    TimeoutDuration::Permanent => None,
+                                },
+                            })
+                                .await
+                                .unwrap();
+                        },
+                        Some(Ok(AnySemantic::UserState(state))) => {
+                            let Some(chan) = state.get_param(0) else {
+                                continue;
+                            };
+                            let is_mod = state.get_tag(OwnedTag::Mod).is_some_and(|m| m == "1")
+                                || state
+                                    .get_tag(OwnedTag::Badges)
+                                    .is_some_and(|b| b.contains("broadcaster/"));
+                            let is_vip = state
+                                .get_tag(OwnedTag::Badges)
+                                .is_some_and(|b| b.contains("vip/"));
+                            output.send(Message::UserStateReceived {
+                                channel: chan.trim_start_matches('#').to_owned(),
+                                is_mod,
+                                is_vip,
+                            })
+                                .await
+                                .unwrap();
+                        },
+                        Some(Ok(AnySemantic::RoomState(state))) => {
+                            output.send(Message::system_event(AnySemantic::RoomState(state.clone())))
+                                .await
+                                .unwrap();
+                            // updates only carry the settings that changed
+                            let (Some(chan), Some(slow)) = (
+                                state.get_param(0),
+                                state.get_tag(OwnedTag::Slow).and_then(|s| s.parse().ok()),
+                            ) else {
+                                continue;
+                            };
+                            output.send(Message::SlowModeChanged {
+                                channel: chan.trim_start_matches('#').to_owned(),
+                                slow,
+                            })
+                                .await
+                                .unwrap();
+                        },
+                        Some(Ok(AnySemantic::AuthSuccessful(auth))) => {
+                            authenticated = true;
+                            output.send(Message::ConnectionStateChanged(ConnectionState::Connected))
+                                .await
+                                .unwrap();
+                            output.send(Message::system_event(AnySemantic::AuthSuccessful(auth)))
+                                .await
+                                .unwrap();
+                            for (chan, msg) in std::mem::take(&mut pending) {
+                                log::info!("Sending queued \"{}\" to #{}", &msg, &chan);
+                                if let Err(e) = conn.send(MessageBuilder::privmsg(&chan, &msg)).await {
+                                    log::error!("{e}");
+                                    pending.push((chan, msg));
+                                }
+                            }
+                        },
+                        Some(Ok(AnySemantic::Reconnect(reconnect))) => {
+                            log::info!("Twitch requested a reconnect");
+                            output.send(Message::system_event(AnySemantic::Reconnect(reconnect)))
+                                .await
+                                .unwrap();
+                            break;
+                        },
+                        Some(Ok(m)) => {
+                            log::debug!("{}", m.inner().inner().trim());
+                            output.send(Message::system_event(m)).await.unwrap();
+                        },
                         Some(Err(e)) => {
                             log::error!("{e}");
+                            output.send(Message::ConnectionStateChanged(ConnectionState::Disconnected))
+                                .await
+                                .unwrap();
                             break;
                         }
                         None => {
                             log::warn!("IRC connection closed with no error");
+                            output.send(Message::ConnectionStateChanged(ConnectionState::Disconnected))
+                                .await
+                                .unwrap();
                             break;
                         },
                     },
@@ -470,9 +1940,25 @@ fn twitch_worker() -> impl Stream<Item = Message> {
                             log::info!("Joining #{}", &chan);
                             conn.join(&chan).await.unwrap();
                         },
+                        Some(IrcCommand::Login(new_login)) => {
+                            log::info!("Reconnecting to log in again");
+                            login = new_login;
+                            break;
+                        },
+                        Some(IrcCommand::Message(chan, msg)) if !authenticated => {
+                            log::info!("Queueing \"{}\" to #{} until connected", &msg, &chan);
+                            pending.push((chan, msg));
+                        },
                         Some(IrcCommand::Message(chan, msg)) => {
                             log::info!("Sending \"{}\" to #{}", &msg, &chan);
-                            conn.send(MessageBuilder::privmsg(&chan, &msg)).await.unwrap();
+                            if let Err(e) = conn.send(MessageBuilder::privmsg(&chan, &msg)).await {
+                                log::error!("{e}");
+                                pending.push((chan, msg));
+                                output.send(Message::ConnectionStateChanged(ConnectionState::Disconnected))
+                                    .await
+                                    .unwrap();
+                                break;
+                            }
                         },
                         None => {
                             panic!("IRC control channel closed");
@@ -513,21 +1999,31 @@ fn main() -> anyhow::Result<()> {
                 // decorations: false,
                 ..Default::default()
             });
+            let app = Juliarino::new(CONFIG.read().chats.iter(), id);
+            let restore = match app.active_tab.clone() {
+                Some(tab) if app.channels.contains_key(&tab) => {
+                    switch_to_tab(app.tabs_id.clone(), tab).discard()
+                }
+                _ => Task::none(),
+            };
+            let system_theme = system::theme().map(Message::SystemThemeChanged);
+            let globals = app.load_global_emotes();
             (
-                Juliarino::new(CONFIG.read().chats.iter(), id),
-                task.discard(),
+                app,
+                Task::batch([
+                    task.discard().chain(restore),
+                    system_theme,
+                    globals,
+                    Task::done(Message::ValidateTokens),
+                ]),
             )
         },
         Juliarino::update,
         Juliarino::view,
     )
-    .subscription(|_| Subscription::run(twitch_worker))
-    .theme(|_s: &Juliarino, _| Some(Theme::CatppuccinMacchiato))
-    .title(if cfg!(debug_assertions) {
-        concat!("Juliarino - ", env!("CARGO_PKG_VERSION"), " (DEBUG)")
-    } else {
-        concat!("Juliarino - ", env!("CARGO_PKG_VERSION"))
-    })
+    .subscription(Juliarino::subscription)
+    .theme(|s: &Juliarino, _| Some(CONFIG.read().ui.theme(s.system_theme)))
+    .title(Juliarino::title)
     .run()?;
     Ok(())
 }
@@ -2,7 +2,7 @@ use std::{hash::Hash, marker::PhantomData};
 
 use iced::{
     Task,
-    advanced::widget::{Operation, operate},
+    advanced::widget::{Operation, operate, operation::Focusable},
     widget::Id,
 };
 
@@ -45,6 +45,45 @@ pub fn scroll_to_idx<K: Eq + Hash + Send + 'static>(id: Id, idx: usize) -> Task<
     })
 }
 
+/// Scrolls the [scrollie::Scrollie] with the given id to an absolute offset,
+/// clamped to its content
+pub fn scroll_to_offset<K: Eq + Hash + Send + 'static>(id: Id, offset: f32) -> Task<()> {
+    struct ScrollToOffset<I> {
+        id: Id,
+        offset: f32,
+        _phantom: PhantomData<I>,
+    }
+
+    impl<I: Eq + Hash + Send + 'static> Operation<()> for ScrollToOffset<I> {
+        fn traverse(&mut self, operate: &mut dyn FnMut(&mut dyn Operation<()>)) {
+            operate(self)
+        }
+
+        fn custom(
+            &mut self,
+            id: Option<&iced::widget::Id>,
+            _bounds: iced::Rectangle,
+            state: &mut dyn std::any::Any,
+        ) {
+            if Some(&self.id) != id {
+                return;
+            }
+
+            let Some(state) = state.downcast_mut::<scrollie::State<I>>() else {
+                return;
+            };
+
+            state.scroll_to(self.offset);
+        }
+    }
+
+    operate(ScrollToOffset::<K> {
+        id,
+        offset,
+        _phantom: Default::default(),
+    })
+}
+
 pub fn switch_to_tab<TabId: Send + Clone + Eq + 'static>(id: Id, tab_id: TabId) -> Task<bool> {
     struct SwitchToTab<TabId> {
         id: Id,
@@ -76,3 +115,32 @@ pub fn switch_to_tab<TabId: Send + Clone + Eq + 'static>(id: Id, tab_id: TabId)
 
     operate(SwitchToTab::<TabId> { id, tab_id })
 }
+
+/// Focuses whichever of the widgets with the given ids is currently in the
+/// widget tree, unfocusing every other focusable widget
+pub fn focus_any(ids: Vec<Id>) -> Task<()> {
+    struct FocusAny {
+        ids: Vec<Id>,
+    }
+
+    impl Operation<()> for FocusAny {
+        fn traverse(&mut self, operate: &mut dyn FnMut(&mut dyn Operation<()>)) {
+            operate(self)
+        }
+
+        fn focusable(
+            &mut self,
+            id: Option<&Id>,
+            _bounds: iced::Rectangle,
+            state: &mut dyn Focusable,
+        ) {
+            if id.is_some_and(|id| self.ids.contains(id)) {
+                state.focus();
+            } else {
+                state.unfocus();
+            }
+        }
+    }
+
+    operate(FocusAny { ids })
+}
@@ -1,25 +1,86 @@
-use std::{fmt::Display, sync::Arc};
+use std::{
+    fmt::Display,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use async_once_cell::Lazy;
 use futures::future::BoxFuture;
 use iced::{
     Border, Color, Element, Task,
     widget::{Container, Space, Text, column, container, sensor, tooltip},
 };
+use parking_lot::Mutex;
 
-use crate::widget::animated::AnimatedImage;
+use crate::{config::CONFIG, widget::animated::AnimatedImage};
 
 pub mod betterttv;
 pub mod frankerfacez;
+pub mod pronouns;
 pub mod recent_messages;
 pub mod seventv;
 pub mod twitch;
 
 pub static DECODER_SEMAPHORE: tokio::sync::Semaphore = tokio::sync::Semaphore::const_new(4);
 
+/// How long a failed image load is kept before it's tried again
+const FAILURE_TTL: Duration = Duration::from_secs(30);
+
+/// Keeps failed image loads cached for much less time than successful ones,
+/// so transient errors recover
+pub struct ImageExpiry;
+
+impl<K> moka::Expiry<K, MaybeImage> for ImageExpiry {
+    fn expire_after_create(
+        &self,
+        _key: &K,
+        value: &MaybeImage,
+        _created_at: std::time::Instant,
+    ) -> Option<Duration> {
+        value.is_none().then_some(FAILURE_TTL)
+    }
+}
+
+/// Downloads an image from the first of `urls` that works, retrying each one
+/// a few times with backoff
+pub async fn fetch_image(
+    client: &reqwest::Client,
+    urls: &[String],
+) -> Option<impl AsRef<[u8]> + Send + 'static> {
+    const ATTEMPTS: u32 = 3;
+
+    for url in urls {
+        for attempt in 1..=ATTEMPTS {
+            let res = async {
+                client
+                    .get(url)
+                    .header("Accept", "image/webp,image/png,image/gif")
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await
+            }
+            .await;
+            match res {
+                Ok(data) => return Some(data),
+                // the image isn't there, asking again won't change that
+                Err(e) if e.status().is_some_and(|s| s.is_client_error()) => {
+                    log::error!("{e}");
+                    break;
+                }
+                Err(e) => log::warn!("{e} (attempt {attempt}/{ATTEMPTS})"),
+            }
+            if attempt < ATTEMPTS {
+                tokio::time::sleep(Duration::from_millis(250 << attempt)).await;
+            }
+        }
+    }
+    None
+}
+
 type MaybeImage = Option<AnimatedImage>;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum EmotePlatform {
     SevenTv,
     FrankerFaceZ,
@@ -46,6 +107,14 @@ bitflags::bitflags! {
     }
 }
 
+/// Resolution of an emote image on its provider's CDN
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CdnSize {
+    OneX,
+    TwoX,
+    FourX,
+}
+
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct EmoteMetadata {
     pub original_name: String,
@@ -54,7 +123,47 @@ pub struct EmoteMetadata {
     pub platform: EmotePlatform,
 }
 
-type EmoteImage = (Lazy<MaybeImage, BoxFuture<'static, MaybeImage>>, (u32, u32));
+/// An image loaded the first time it's shown. Unlike a loaded one, a failure
+/// is forgotten after [FAILURE_TTL], so showing it again asks the cache again
+#[derive(derive_more::Debug)]
+pub struct LazyImage {
+    image: tokio::sync::OnceCell<AnimatedImage>,
+    failed_at: Mutex<Option<Instant>>,
+    #[debug(skip)]
+    load: Box<dyn Fn() -> BoxFuture<'static, MaybeImage> + Send + Sync>,
+}
+
+impl LazyImage {
+    pub fn new(load: impl Fn() -> BoxFuture<'static, MaybeImage> + Send + Sync + 'static) -> Self {
+        Self {
+            image: tokio::sync::OnceCell::new(),
+            failed_at: Mutex::new(None),
+            load: Box::new(load),
+        }
+    }
+
+    /// [None] until it's loaded or while it should be tried again, `Some(None)`
+    /// if it failed recently
+    fn try_get(&self) -> Option<Option<&AnimatedImage>> {
+        if let Some(image) = self.image.get() {
+            return Some(Some(image));
+        }
+        self.failed_at
+            .lock()
+            .is_some_and(|at| at.elapsed() < FAILURE_TTL)
+            .then_some(None)
+    }
+
+    async fn load(&self) {
+        let res = self
+            .image
+            .get_or_try_init(|| async { (self.load)().await.ok_or(()) })
+            .await;
+        *self.failed_at.lock() = res.is_err().then(Instant::now);
+    }
+}
+
+type EmoteImage = (LazyImage, (u32, u32));
 
 #[derive(Debug)]
 pub struct EmoteImages {
@@ -78,44 +187,123 @@ impl ChannelEmote {
             .unwrap_or(self.metadata.original_name.as_str())
     }
 
+    /// URL of the emote image at `size` on its provider's CDN, using the
+    /// closest available size for providers that don't have it
+    pub fn cdn_url(&self, size: CdnSize) -> String {
+        let id = &self.metadata.id;
+        match self.metadata.platform {
+            EmotePlatform::SevenTv => {
+                let size = match size {
+                    CdnSize::OneX => "1x",
+                    CdnSize::TwoX => "2x",
+                    CdnSize::FourX => "4x",
+                };
+                format!(
+                    "{}/emote/{id}/{size}.webp",
+                    CONFIG.read().endpoints.seventv_cdn
+                )
+            }
+            EmotePlatform::BetterTtv => {
+                let size = match size {
+                    CdnSize::OneX => "1x",
+                    CdnSize::TwoX => "2x",
+                    CdnSize::FourX => "3x",
+                };
+                format!("{}/emote/{id}/{size}", CONFIG.read().endpoints.bttv_cdn)
+            }
+            EmotePlatform::FrankerFaceZ => {
+                let size = match size {
+                    CdnSize::OneX => "1",
+                    CdnSize::TwoX => "2",
+                    CdnSize::FourX => "4",
+                };
+                format!("{}/emoticon/{id}/{size}", CONFIG.read().endpoints.ffz_cdn)
+            }
+            EmotePlatform::Twitch => twitch::emotes::cdn_url(id, size),
+        }
+    }
+
+    /// The emote's page on its provider's website
+    pub fn page_url(&self) -> Option<String> {
+        let id = &self.metadata.id;
+        match self.metadata.platform {
+            EmotePlatform::SevenTv => Some(format!("https://7tv.app/emotes/{id}")),
+            EmotePlatform::BetterTtv => Some(format!("https://betterttv.com/emotes/{id}")),
+            EmotePlatform::FrankerFaceZ => {
+                Some(format!("https://www.frankerfacez.com/emoticon/{id}"))
+            }
+            EmotePlatform::Twitch => None,
+        }
+    }
+
+    /// Renders the emote with its size multiplied by `scale`
     pub fn view<M: Send + 'static>(
         &self,
+        scale: f32,
     ) -> Element<'static, impl Fn() -> Task<M> + Clone + 'static> {
-        let tooltiper = |e: Element<'static, _>| {
-            tooltip(
-                e,
-                Container::new(column![
-                    Text::new(self.text_name().to_owned()),
-                    Text::new(self.metadata.platform.as_str())
-                ])
-                .padding(12)
-                .style(|_| {
-                    container::Style::default()
-                        .border(Border::default().rounded(6.0))
-                        .background(Color::from_rgba(0.0, 0.0, 0.0, 0.8))
-                }),
-                tooltip::Position::Top,
-            )
-        };
-
-        if let Some(image) = self.images.one_x.0.try_get().and_then(|i| i.as_ref()) {
-            tooltiper(image.clone().into()).into()
-        } else {
-            let copy = self.images.clone();
-            let placeholder = Space::new()
-                .width(self.images.one_x.1.0)
-                .height(self.images.one_x.1.1);
-            tooltiper(Element::new(sensor(placeholder).on_show(move |_| {
-                let sent = copy.clone();
-                move || {
-                    let sent2 = sent.clone();
-                    Task::future(async move {
-                        sent2.one_x.0.get_unpin().await;
-                    })
-                    .discard()
-                }
-            })))
-            .into()
+        tooltip(
+            self.view_image(|i| &i.one_x, scale),
+            Container::new(column![
+                Text::new(self.text_name().to_owned()),
+                Text::new(self.metadata.platform.as_str()),
+                Text::new("Right-click to copy URL").size(12)
+            ])
+            .padding(12)
+            .style(|_| {
+                container::Style::default()
+                    .border(Border::default().rounded(6.0))
+                    .background(Color::from_rgba(0.0, 0.0, 0.0, 0.8))
+            }),
+            tooltip::Position::Top,
+        )
+        .into()
+    }
+
+    /// Renders the largest available image of the emote at its own size
+    pub fn view_largest<M: Send + 'static>(
+        &self,
+    ) -> Element<'static, impl Fn() -> Task<M> + Clone + 'static> {
+        self.view_image(
+            |i| {
+                i.four_x
+                    .as_ref()
+                    .or(i.three_x.as_ref())
+                    .or(i.two_x.as_ref())
+                    .unwrap_or(&i.one_x)
+            },
+            1.0,
+        )
+    }
+
+    /// Renders the image `pick` chooses, loading it once it's shown
+    fn view_image<M: Send + 'static>(
+        &self,
+        pick: fn(&EmoteImages) -> &EmoteImage,
+        scale: f32,
+    ) -> Element<'static, impl Fn() -> Task<M> + Clone + 'static> {
+        let (image, (width, height)) = pick(&self.images);
+        // a failed image is shown as text until it's tried again
+        match image.try_get() {
+            Some(Some(image)) => image.clone().scaled(scale).into(),
+            Some(None) => Text::new(self.text_name().to_owned())
+                .size(CONFIG.read().ui.chat_font_size)
+                .into(),
+            None => {
+                let copy = self.images.clone();
+                let placeholder = Space::new()
+                    .width(*width as f32 * scale)
+                    .height(*height as f32 * scale);
+                Element::new(sensor(placeholder).on_show(move |_| {
+                    let sent = copy.clone();
+                    move || {
+                        let sent2 = sent.clone();
+                        Task::future(async move {
+                            pick(&sent2).0.load().await;
+                        })
+                        .discard()
+                    }
+                }))
+            }
         }
     }
 }
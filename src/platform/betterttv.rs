@@ -1,7 +1,10 @@
-use std::{fmt::Display, sync::Arc, time::Duration};
+use std::{
+    fmt::Display,
+    sync::{Arc, OnceLock},
+    task::Poll,
+    time::Duration,
+};
 
-use async_once_cell::Lazy;
-use futures::future::BoxFuture;
 use hashbrown::HashMap;
 use iced::Length;
 use moka::policy::EvictionPolicy;
@@ -9,8 +12,10 @@ use serde::Deserialize;
 use tokio::sync::RwLock;
 
 use crate::{
+    config::CONFIG,
     platform::{
-        ChannelEmote, DECODER_SEMAPHORE, EmoteFlags, EmoteImages, EmoteMetadata, MaybeImage,
+        ChannelEmote, DECODER_SEMAPHORE, EmoteFlags, EmoteImages, EmoteMetadata, ImageExpiry,
+        LazyImage, MaybeImage, fetch_image,
     },
     util::default_client,
     widget::animated::AnimatedImage,
@@ -70,6 +75,7 @@ type EmoteCache = moka::future::Cache<(String, EmoteSize), MaybeImage>;
 pub struct BetterTtvClient {
     client: reqwest::Client,
     channels: RwLock<HashMap<String, anyhow::Result<Arc<[ChannelEmote]>>>>,
+    globals: OnceLock<Arc<[ChannelEmote]>>,
     emotes: EmoteCache,
 }
 
@@ -78,83 +84,154 @@ impl BetterTtvClient {
         let cache = moka::future::CacheBuilder::new(300)
             .eviction_policy(EvictionPolicy::tiny_lfu())
             .time_to_idle(Duration::from_secs(60 * 30))
+            .expire_after(ImageExpiry)
             .name("betterttv_emotes")
             .build();
         Self {
             client: default_client(),
             channels: Default::default(),
+            globals: OnceLock::new(),
             emotes: cache,
         }
     }
 
-    /// Blocks until the channel cache lock has been free'd
-    pub fn channel_emote_set(&self, id: &str) -> Option<Arc<[ChannelEmote]>> {
-        self.channels
-            .blocking_read()
-            .get(id)
-            .and_then(|c| c.as_ref().ok())
-            .cloned()
+    pub async fn get_globals(&self) -> anyhow::Result<Vec<ChannelEmote>> {
+        let api = CONFIG.read().endpoints.bttv_api.clone();
+        let req = self
+            .client
+            .get(format!("{api}/cached/emotes/global"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Vec<Emote>>()
+            .await?;
+
+        let mut emotes = req
+            .into_iter()
+            .map(|e| self.channel_emote(e))
+            .collect::<Vec<_>>();
+
+        emotes.sort_unstable_by(|a, b| a.text_name().cmp(b.text_name()));
+
+        Ok(emotes)
+    }
+
+    /// Loads the global emote set unless it already was, returns whether it's
+    /// available
+    pub async fn load_globals(&self) -> bool {
+        if self.globals.get().is_some() {
+            return true;
+        }
+        match self.get_globals().await {
+            Ok(emotes) => {
+                let _ = self.globals.set(emotes.into());
+                true
+            }
+            Err(e) => {
+                log::error!("failed to load global BTTV emotes: {e}");
+                false
+            }
+        }
+    }
+
+    /// The global emote set, if it was loaded
+    pub fn globals(&self) -> Option<Arc<[ChannelEmote]>> {
+        self.globals.get().cloned()
+    }
+
+    /// Waits until no channel emote set is being written, after which
+    /// [Self::try_channel_emote_set] can be tried again
+    pub async fn channels_written(&self) {
+        drop(self.channels.read().await);
     }
 
-    fn lazy_emote(
-        &self,
-        id: String,
-        size: EmoteSize,
-    ) -> Lazy<MaybeImage, BoxFuture<'static, MaybeImage>> {
+    /// Gets the channel's emote set without blocking, [Poll::Pending] if it is
+    /// currently being loaded
+    pub fn try_channel_emote_set(&self, id: &str) -> Poll<Option<Arc<[ChannelEmote]>>> {
+        match self.channels.try_read() {
+            Ok(channels) => Poll::Ready(channels.get(id).and_then(|c| c.as_ref().ok()).cloned()),
+            Err(_) => Poll::Pending,
+        }
+    }
+
+    fn channel_emote(&self, e: Emote) -> ChannelEmote {
+        ChannelEmote {
+            images: Arc::new(EmoteImages {
+                one_x: (
+                    self.lazy_emote(e.id.clone(), EmoteSize::OneX),
+                    (EmoteSize::OneX.size(), EmoteSize::OneX.size()),
+                ),
+                two_x: Some((
+                    self.lazy_emote(e.id.clone(), EmoteSize::TwoX),
+                    (EmoteSize::TwoX.size(), EmoteSize::TwoX.size()),
+                )),
+                three_x: Some((
+                    self.lazy_emote(e.id.clone(), EmoteSize::ThreeX),
+                    (EmoteSize::ThreeX.size(), EmoteSize::ThreeX.size()),
+                )),
+                four_x: None,
+            }),
+            metadata: Arc::new(EmoteMetadata {
+                original_name: e.code_original.unwrap_or_else(|| e.code.clone()),
+                flags: EmoteFlags::empty(),
+                id: e.id,
+                platform: crate::platform::EmotePlatform::BetterTtv,
+            }),
+            alias: Some(e.code),
+        }
+    }
+
+    fn lazy_emote(&self, id: String, size: EmoteSize) -> LazyImage {
         let client = self.client.clone();
         let cache = self.emotes.clone();
 
-        Lazy::new(Box::pin(async move {
-            cache
-                .get_with((id.clone(), size), async move {
-                    let start = std::time::Instant::now();
-                    let data = client
-                        .get(format!("https://cdn.betterttv.net/emote/{}/{size}", &id))
-                        .header("Accept", "image/webp,image/png,image/gif")
-                        .send()
-                        .await
-                        .inspect_err(|e| log::error!("{e}"))
-                        .ok()?
-                        .error_for_status()
-                        .inspect_err(|e| log::error!("{e}"))
-                        .ok()?
-                        .bytes()
-                        .await
-                        .inspect_err(|e| log::error!("{e}"))
-                        .ok()?;
-
-                    let kbps = data.len() as f32 / 1000.0 / start.elapsed().as_secs_f32();
-
-                    let img = {
-                        let _ = DECODER_SEMAPHORE.acquire().await.unwrap();
-                        tokio::task::spawn_blocking(move || AnimatedImage::from_bytes(&data))
+        LazyImage::new(move || {
+            let client = client.clone();
+            let cache = cache.clone();
+            let id = id.clone();
+            Box::pin(async move {
+                cache
+                    .get_with((id.clone(), size), async move {
+                        let cdn = CONFIG.read().endpoints.bttv_cdn.clone();
+                        let start = std::time::Instant::now();
+                        let data =
+                            fetch_image(&client, &[format!("{cdn}/emote/{}/{size}", &id)]).await?;
+
+                        let kbps =
+                            data.as_ref().len() as f32 / 1000.0 / start.elapsed().as_secs_f32();
+
+                        let img = {
+                            let _ = DECODER_SEMAPHORE.acquire().await.unwrap();
+                            tokio::task::spawn_blocking(move || {
+                                AnimatedImage::from_bytes(data.as_ref())
+                            })
                             .await
                             .inspect_err(|e| log::error!("{e}"))
                             .ok()?
                             .inspect_err(|e| log::error!("{e}"))
                             .ok()?
-                    }
-                    .width(size.uniform_size())
-                    .height(size.uniform_size());
-
-                    log::trace!(
-                        "BTTV emote {id} loaded in {:?} at {kbps:02}kb/s",
-                        start.elapsed()
-                    );
-
-                    Some(img)
-                })
-                .await
-        }))
+                        }
+                        .width(size.uniform_size())
+                        .height(size.uniform_size());
+
+                        log::trace!(
+                            "BTTV emote {id} loaded in {:?} at {kbps:02}kb/s",
+                            start.elapsed()
+                        );
+
+                        Some(img)
+                    })
+                    .await
+            })
+        })
     }
 
     pub async fn load_channel_emote_set(&self, id: String) -> bool {
         let load = async || {
+            let api = CONFIG.read().endpoints.bttv_api.clone();
             let req = self
                 .client
-                .get(format!(
-                    "https://api.betterttv.net/3/cached/users/twitch/{id}"
-                ))
+                .get(format!("{api}/cached/users/twitch/{id}"))
                 .send()
                 .await?
                 .error_for_status()?
@@ -164,30 +241,7 @@ impl BetterTtvClient {
             let mut emotes = req
                 .shared_emotes
                 .into_iter()
-                .map(|e| ChannelEmote {
-                    images: Arc::new(EmoteImages {
-                        one_x: (
-                            self.lazy_emote(e.id.clone(), EmoteSize::OneX),
-                            (EmoteSize::OneX.size(), EmoteSize::OneX.size()),
-                        ),
-                        two_x: Some((
-                            self.lazy_emote(e.id.clone(), EmoteSize::TwoX),
-                            (EmoteSize::TwoX.size(), EmoteSize::TwoX.size()),
-                        )),
-                        three_x: Some((
-                            self.lazy_emote(e.id.clone(), EmoteSize::ThreeX),
-                            (EmoteSize::ThreeX.size(), EmoteSize::ThreeX.size()),
-                        )),
-                        four_x: None,
-                    }),
-                    metadata: Arc::new(EmoteMetadata {
-                        original_name: e.code_original.unwrap_or_else(|| e.code.clone()),
-                        flags: EmoteFlags::empty(),
-                        id: e.id,
-                        platform: crate::platform::EmotePlatform::BetterTtv,
-                    }),
-                    alias: Some(e.code),
-                })
+                .map(|e| self.channel_emote(e))
                 .collect::<Vec<_>>();
 
             emotes.sort_unstable_by(|a, b| a.text_name().cmp(b.text_name()));
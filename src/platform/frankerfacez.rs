@@ -1,15 +1,20 @@
-use std::{fmt::Display, sync::Arc, time::Duration};
+use std::{
+    fmt::Display,
+    sync::{Arc, OnceLock},
+    task::Poll,
+    time::Duration,
+};
 
-use async_once_cell::Lazy;
-use futures::future::BoxFuture;
 use hashbrown::HashMap;
 use moka::policy::EvictionPolicy;
 use serde::Deserialize;
 use tokio::sync::RwLock;
 
 use crate::{
+    config::CONFIG,
     platform::{
-        ChannelEmote, DECODER_SEMAPHORE, EmoteFlags, EmoteImages, EmoteMetadata, MaybeImage,
+        ChannelEmote, DECODER_SEMAPHORE, EmoteFlags, EmoteImages, EmoteMetadata, ImageExpiry,
+        LazyImage, MaybeImage, fetch_image,
     },
     util::default_client,
     widget::animated::AnimatedImage,
@@ -20,6 +25,13 @@ struct FfzRoomQuery {
     sets: std::collections::HashMap<String, EmoteSet>,
 }
 
+#[derive(Debug, Deserialize)]
+struct FfzGlobalQuery {
+    /// Sets everyone has, the others are only for some users
+    default_sets: Vec<i64>,
+    sets: std::collections::HashMap<String, EmoteSet>,
+}
+
 #[derive(Debug, Deserialize)]
 struct EmoteSet {
     emoticons: Vec<Emote>,
@@ -56,6 +68,7 @@ type EmoteCache = moka::future::Cache<(i64, EmoteSize), MaybeImage>;
 pub struct FfzClient {
     client: reqwest::Client,
     channels: RwLock<HashMap<String, anyhow::Result<Arc<[ChannelEmote]>>>>,
+    globals: OnceLock<Arc<[ChannelEmote]>>,
     emotes: EmoteCache,
 }
 
@@ -64,85 +77,159 @@ impl FfzClient {
         let cache = moka::future::CacheBuilder::new(300)
             .eviction_policy(EvictionPolicy::tiny_lfu())
             .time_to_idle(Duration::from_secs(60 * 30))
+            .expire_after(ImageExpiry)
             .name("betterttv_emotes")
             .build();
         Self {
             client: default_client(),
             channels: Default::default(),
+            globals: OnceLock::new(),
             emotes: cache,
         }
     }
 
-    /// Blocks until the channel cache lock has been free'd
-    pub fn channel_emote_set_login(&self, login: &str) -> Option<Arc<[ChannelEmote]>> {
-        self.channels
-            .blocking_read()
-            .get(login)
-            .and_then(|c| c.as_ref().ok())
-            .cloned()
+    pub async fn get_globals(&self) -> anyhow::Result<Vec<ChannelEmote>> {
+        let api = CONFIG.read().endpoints.ffz_api.clone();
+        let req = self
+            .client
+            .get(format!("{api}/set/global"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<FfzGlobalQuery>()
+            .await?;
+
+        let mut emotes = req
+            .sets
+            .into_iter()
+            .filter(|(id, _)| req.default_sets.iter().any(|d| d.to_string() == *id))
+            .flat_map(|(_, s)| s.emoticons.into_iter())
+            .map(|e| self.channel_emote(e))
+            .collect::<Vec<_>>();
+
+        emotes.sort_unstable_by(|a, b| a.text_name().cmp(b.text_name()));
+
+        Ok(emotes)
+    }
+
+    /// Loads the global emote set unless it already was, returns whether it's
+    /// available
+    pub async fn load_globals(&self) -> bool {
+        if self.globals.get().is_some() {
+            return true;
+        }
+        match self.get_globals().await {
+            Ok(emotes) => {
+                let _ = self.globals.set(emotes.into());
+                true
+            }
+            Err(e) => {
+                log::error!("failed to load global FFZ emotes: {e}");
+                false
+            }
+        }
     }
 
-    fn lazy_emote(
-        &self,
-        id: i64,
-        size: EmoteSize,
-        animated: bool,
-    ) -> Lazy<MaybeImage, BoxFuture<'static, MaybeImage>> {
+    /// The global emote set, if it was loaded
+    pub fn globals(&self) -> Option<Arc<[ChannelEmote]>> {
+        self.globals.get().cloned()
+    }
+
+    /// Waits until no channel emote set is being written, after which
+    /// [Self::try_channel_emote_set_login] can be tried again
+    pub async fn channels_written(&self) {
+        drop(self.channels.read().await);
+    }
+
+    /// Gets the channel's emote set without blocking, [Poll::Pending] if it is
+    /// currently being loaded
+    pub fn try_channel_emote_set_login(&self, login: &str) -> Poll<Option<Arc<[ChannelEmote]>>> {
+        match self.channels.try_read() {
+            Ok(channels) => Poll::Ready(channels.get(login).and_then(|c| c.as_ref().ok()).cloned()),
+            Err(_) => Poll::Pending,
+        }
+    }
+
+    fn channel_emote(&self, e: Emote) -> ChannelEmote {
+        let animated = e.animated.is_some();
+        ChannelEmote {
+            images: Arc::new(EmoteImages {
+                one_x: (
+                    self.lazy_emote(e.id, EmoteSize::OneX, animated),
+                    (e.width, e.height),
+                ),
+                two_x: Some((
+                    self.lazy_emote(e.id, EmoteSize::TwoX, animated),
+                    (e.width * 2, e.height * 2),
+                )),
+                three_x: None,
+                four_x: Some((
+                    self.lazy_emote(e.id, EmoteSize::FourX, animated),
+                    (e.width * 4, e.width * 4),
+                )),
+            }),
+            metadata: Arc::new(EmoteMetadata {
+                original_name: e.name,
+                flags: EmoteFlags::empty(),
+                id: e.id.to_string(),
+                platform: crate::platform::EmotePlatform::FrankerFaceZ,
+            }),
+            alias: None,
+        }
+    }
+
+    fn lazy_emote(&self, id: i64, size: EmoteSize, animated: bool) -> LazyImage {
         let client = self.client.clone();
         let cache = self.emotes.clone();
 
-        Lazy::new(Box::pin(async move {
-            cache
-                .get_with((id, size), async move {
-                    let url = if animated {
-                        format!("https://cdn.frankerfacez.com/emoticon/{id}/animated/{size}")
-                    } else {
-                        format!("https://cdn.frankerfacez.com/emoticon/{id}/{size}")
-                    };
-                    let start = std::time::Instant::now();
-                    let data = client
-                        .get(url)
-                        .header("Accept", "image/webp,image/png,image/gif")
-                        .send()
-                        .await
-                        .inspect_err(|e| log::error!("{e}"))
-                        .ok()?
-                        .error_for_status()
-                        .inspect_err(|e| log::error!("{e}"))
-                        .ok()?
-                        .bytes()
-                        .await
-                        .inspect_err(|e| log::error!("{e}"))
-                        .ok()?;
-
-                    let kbps = data.len() as f32 / 1000.0 / start.elapsed().as_secs_f32();
-
-                    let img = {
-                        let _ = DECODER_SEMAPHORE.acquire().await.unwrap();
-                        tokio::task::spawn_blocking(move || AnimatedImage::from_bytes(&data))
+        LazyImage::new(move || {
+            let client = client.clone();
+            let cache = cache.clone();
+            Box::pin(async move {
+                cache
+                    .get_with((id, size), async move {
+                        let cdn = CONFIG.read().endpoints.ffz_cdn.clone();
+                        let url = if animated {
+                            format!("{cdn}/emoticon/{id}/animated/{size}")
+                        } else {
+                            format!("{cdn}/emoticon/{id}/{size}")
+                        };
+                        let start = std::time::Instant::now();
+                        let data = fetch_image(&client, &[url]).await?;
+
+                        let kbps =
+                            data.as_ref().len() as f32 / 1000.0 / start.elapsed().as_secs_f32();
+
+                        let img = {
+                            let _ = DECODER_SEMAPHORE.acquire().await.unwrap();
+                            tokio::task::spawn_blocking(move || {
+                                AnimatedImage::from_bytes(data.as_ref())
+                            })
                             .await
                             .inspect_err(|e| log::error!("{e}"))
                             .ok()?
                             .inspect_err(|e| log::error!("{e}"))
                             .ok()?
-                    };
+                        };
 
-                    log::trace!(
-                        "BTTV emote {id} loaded in {:?} at {kbps:02}kb/s",
-                        start.elapsed()
-                    );
+                        log::trace!(
+                            "BTTV emote {id} loaded in {:?} at {kbps:02}kb/s",
+                            start.elapsed()
+                        );
 
-                    Some(img)
-                })
-                .await
-        }))
+                        Some(img)
+                    })
+                    .await
+            })
+        })
     }
 
     pub async fn load_channel_emote_set_login(&self, login: String) -> bool {
         let load = async || {
+            let api = CONFIG.read().endpoints.ffz_api.clone();
             let req = self
                 .client
-                .get(format!("https://api.frankerfacez.com/v1/room/{login}"))
+                .get(format!("{api}/room/{login}"))
                 .send()
                 .await?
                 .error_for_status()?
@@ -153,33 +240,7 @@ impl FfzClient {
                 .sets
                 .into_values()
                 .flat_map(|s| s.emoticons.into_iter())
-                .map(|e| {
-                    let animated = e.animated.is_some();
-                    ChannelEmote {
-                        images: Arc::new(EmoteImages {
-                            one_x: (
-                                self.lazy_emote(e.id, EmoteSize::OneX, animated),
-                                (e.width, e.height),
-                            ),
-                            two_x: Some((
-                                self.lazy_emote(e.id, EmoteSize::TwoX, animated),
-                                (e.width * 2, e.height * 2),
-                            )),
-                            three_x: None,
-                            four_x: Some((
-                                self.lazy_emote(e.id, EmoteSize::FourX, animated),
-                                (e.width * 4, e.width * 4),
-                            )),
-                        }),
-                        metadata: Arc::new(EmoteMetadata {
-                            original_name: e.name,
-                            flags: EmoteFlags::empty(),
-                            id: e.id.to_string(),
-                            platform: crate::platform::EmotePlatform::FrankerFaceZ,
-                        }),
-                        alias: None,
-                    }
-                })
+                .map(|e| self.channel_emote(e))
                 .collect::<Vec<_>>();
 
             emotes.sort_unstable_by(|a, b| a.text_name().cmp(b.text_name()));
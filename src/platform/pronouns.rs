@@ -0,0 +1,101 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use moka::policy::EvictionPolicy;
+use serde::Deserialize;
+use tokio::sync::OnceCell;
+
+use crate::util::default_client;
+
+const API: &str = "https://api.pronouns.alejo.io/v1";
+
+static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(default_client);
+
+/// Pronouns of each user by login, [None] for users who haven't set any
+type PronounCache = moka::sync::Cache<String, Arc<OnceCell<Option<String>>>>;
+
+static PRONOUN_CACHE: LazyLock<PronounCache> = LazyLock::new(|| {
+    moka::sync::CacheBuilder::new(5000)
+        .eviction_policy(EvictionPolicy::tiny_lfu())
+        .time_to_live(Duration::from_secs(60 * 60))
+        .name("pronouns")
+        .build()
+});
+
+/// Pronoun sets supported by the API, fetched once
+static PRONOUNS: OnceCell<HashMap<String, Pronoun>> = OnceCell::const_new();
+
+#[derive(Deserialize)]
+struct Pronoun {
+    subject: String,
+    object: String,
+    /// Only the subject is shown, e.g. "Any"
+    singular: bool,
+}
+
+#[derive(Deserialize)]
+struct User {
+    pronoun_id: String,
+    alt_pronoun_id: Option<String>,
+}
+
+/// The pronouns of `login` if they have already been loaded
+pub fn pronouns(login: &str) -> Option<String> {
+    PRONOUN_CACHE.get(login)?.get()?.clone()
+}
+
+async fn pronoun_sets() -> anyhow::Result<&'static HashMap<String, Pronoun>> {
+    PRONOUNS
+        .get_or_try_init(async || {
+            Ok(CLIENT
+                .get(format!("{API}/pronouns"))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?)
+        })
+        .await
+}
+
+async fn fetch(login: &str) -> anyhow::Result<Option<String>> {
+    let sets = pronoun_sets().await?;
+    let res = CLIENT.get(format!("{API}/users/{login}")).send().await?;
+    if res.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let user: User = res.error_for_status()?.json().await?;
+
+    let Some(main) = sets.get(&user.pronoun_id) else {
+        return Ok(None);
+    };
+    let text = match user.alt_pronoun_id.as_deref().and_then(|a| sets.get(a)) {
+        Some(alt) => format!("{}/{}", main.subject, alt.subject),
+        None if main.singular => main.subject.clone(),
+        None => format!("{}/{}", main.subject, main.object),
+    };
+    Ok(Some(text.to_lowercase()))
+}
+
+/// Loads the pronouns of `login`, returns whether any were found
+pub async fn load_pronouns(login: String) -> bool {
+    let mut found = false;
+
+    PRONOUN_CACHE
+        .get_with_by_ref(&login, || Arc::new(OnceCell::new()))
+        .get_or_init(async || {
+            let pronouns = fetch(&login)
+                .await
+                .inspect_err(|e| log::error!("failed to load pronouns of {login}: {e}"))
+                .ok()
+                .flatten();
+            found = pronouns.is_some();
+            pronouns
+        })
+        .await;
+
+    found
+}
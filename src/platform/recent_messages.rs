@@ -3,8 +3,13 @@ use std::{fmt, sync::LazyLock};
 use serde::{Deserialize, Deserializer, de::Visitor};
 use twixel_core::IrcMessage;
 
-use crate::util::default_client;
+use crate::{
+    config::{CONFIG, UiConfig},
+    util::default_client,
+};
 
+/// Base URL of the API, the environment variables take precedence over the
+/// config file
 static RECENT_MESSAGES_API: LazyLock<url::Url> = LazyLock::new(|| {
     // compat wowie!
     std::env::var("CHATTERINO2_RECENT_MESSAGES_URL")
@@ -12,20 +17,53 @@ static RECENT_MESSAGES_API: LazyLock<url::Url> = LazyLock::new(|| {
         .as_deref()
         .map(|e| e.parse().unwrap())
         .unwrap_or_else(|_| {
-            "https://recent-messages.robotty.de/api/v2/"
+            format!("{}/", CONFIG.read().endpoints.recent_messages)
                 .parse()
                 .unwrap()
         })
 });
 
-#[derive(Deserialize, Default)]
+#[derive(Deserialize)]
 struct Response {
-    _error: Option<String>,
-    _error_code: Option<String>,
-    #[serde(deserialize_with = "deser_irc")]
+    error: Option<String>,
+    error_code: Option<String>,
+    #[serde(default, deserialize_with = "deser_irc")]
     messages: Vec<IrcMessage>,
 }
 
+#[derive(Debug)]
+pub enum RecentMessagesError {
+    Request(reqwest::Error),
+    /// The API answered with an error instead of messages
+    Api {
+        message: String,
+        code: Option<String>,
+    },
+}
+
+impl std::fmt::Display for RecentMessagesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecentMessagesError::Request(e) => write!(f, "request failed: {e}"),
+            RecentMessagesError::Api { code, message } => match code.as_deref() {
+                Some("channel_not_joined") => {
+                    write!(f, "the channel isn't being logged yet, try again later")
+                }
+                Some("channel_ignored") => write!(f, "the channel opted out of message history"),
+                _ => write!(f, "{message}"),
+            },
+        }
+    }
+}
+
+impl std::error::Error for RecentMessagesError {}
+
+impl From<reqwest::Error> for RecentMessagesError {
+    fn from(value: reqwest::Error) -> Self {
+        Self::Request(value)
+    }
+}
+
 fn deser_irc<'de, D>(deserializer: D) -> Result<Vec<IrcMessage>, D::Error>
 where
     D: Deserializer<'de>,
@@ -54,15 +92,34 @@ where
     deserializer.deserialize_seq(IrcVisitor(Vec::new()))
 }
 
-pub async fn get_recent_messages(channel_login: &str) -> Vec<IrcMessage> {
+/// Gets the latest messages of the channel, or the ones sent before the
+/// `before` unix timestamp in milliseconds
+pub async fn get_recent_messages(
+    channel_login: &str,
+    before: Option<i64>,
+) -> Result<Vec<IrcMessage>, RecentMessagesError> {
     static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(default_client);
 
     let mut url = RECENT_MESSAGES_API
         .join(&format!("recent-messages/{channel_login}"))
         .unwrap();
-    url.set_query(Some("limit=250"));
-    match CLIENT.get(url).send().await {
-        Ok(r) => r.json::<Response>().await.unwrap_or_default().messages,
-        Err(_) => Vec::new(),
+    let limit = CONFIG
+        .read()
+        .ui
+        .history_limit
+        .min(UiConfig::MAX_HISTORY_LIMIT);
+    url.query_pairs_mut()
+        .append_pair("limit", &limit.to_string());
+    if let Some(before) = before {
+        url.query_pairs_mut()
+            .append_pair("before", &before.to_string());
+    }
+    let res = CLIENT.get(url).send().await?.json::<Response>().await?;
+    match res.error {
+        Some(message) if res.messages.is_empty() => Err(RecentMessagesError::Api {
+            message,
+            code: res.error_code,
+        }),
+        _ => Ok(res.messages),
     }
 }
@@ -1,7 +1,11 @@
-use std::{fmt::Display, str::FromStr, sync::Arc, time::Duration};
+use std::{
+    fmt::Display,
+    str::FromStr,
+    sync::{Arc, OnceLock},
+    task::Poll,
+    time::Duration,
+};
 
-use async_once_cell::Lazy;
-use futures::future::BoxFuture;
 use hashbrown::HashMap;
 use moka::policy::EvictionPolicy;
 use serde::Deserialize;
@@ -9,8 +13,10 @@ use tokio::sync::RwLock;
 use ulid::Ulid;
 
 use crate::{
+    config::CONFIG,
     platform::{
-        ChannelEmote, DECODER_SEMAPHORE, EmoteFlags, EmoteImages, EmoteMetadata, MaybeImage,
+        ChannelEmote, DECODER_SEMAPHORE, EmoteFlags, EmoteImages, EmoteMetadata, ImageExpiry,
+        LazyImage, MaybeImage, fetch_image,
     },
     util::default_client,
     widget::animated::AnimatedImage,
@@ -136,6 +142,7 @@ type EmoteCache = moka::future::Cache<(Ulid, EmoteSize), MaybeImage>;
 pub struct SevenTvClient {
     client: reqwest::Client,
     channels: RwLock<HashMap<String, anyhow::Result<Arc<[ChannelEmote]>>>>,
+    globals: OnceLock<Arc<[ChannelEmote]>>,
     emotes: EmoteCache,
 }
 
@@ -145,19 +152,22 @@ impl SevenTvClient {
         let cache = moka::future::CacheBuilder::new(300)
             .eviction_policy(EvictionPolicy::tiny_lfu())
             .time_to_idle(Duration::from_secs(60 * 30))
+            .expire_after(ImageExpiry)
             .name("seventv_emotes")
             .build();
         Self {
             client,
             channels: Default::default(),
+            globals: OnceLock::new(),
             emotes: cache,
         }
     }
 
     pub async fn get_globals(&self) -> anyhow::Result<Vec<ChannelEmote>> {
+        let api = CONFIG.read().endpoints.seventv_api.clone();
         let req = self
             .client
-            .get("https://7tv.io/v3/emote-sets/global")
+            .get(format!("{api}/emote-sets/global"))
             .send()
             .await?
             .error_for_status()?
@@ -226,75 +236,97 @@ impl SevenTvClient {
         Ok(emotes)
     }
 
-    /// Blocks until the channel cache lock has been free'd
-    pub fn channel_emote_set(&self, id: &str) -> Option<Arc<[ChannelEmote]>> {
-        self.channels
-            .blocking_read()
-            .get(id)
-            .and_then(|c| c.as_ref().ok())
-            .cloned()
+    /// Loads the global emote set unless it already was, returns whether it's
+    /// available
+    pub async fn load_globals(&self) -> bool {
+        if self.globals.get().is_some() {
+            return true;
+        }
+        match self.get_globals().await {
+            Ok(emotes) => {
+                let _ = self.globals.set(emotes.into());
+                true
+            }
+            Err(e) => {
+                log::error!("failed to load global 7TV emotes: {e}");
+                false
+            }
+        }
+    }
+
+    /// The global emote set, if it was loaded
+    pub fn globals(&self) -> Option<Arc<[ChannelEmote]>> {
+        self.globals.get().cloned()
+    }
+
+    /// Waits until no channel emote set is being written, after which
+    /// [Self::try_channel_emote_set] can be tried again
+    pub async fn channels_written(&self) {
+        drop(self.channels.read().await);
     }
 
-    /// Gets the channel's emote set without blocking
-    pub fn try_channel_emote_set(&self, id: &str) -> Option<Arc<[ChannelEmote]>> {
-        self.channels
-            .try_read()
-            .ok()?
-            .get(id)
-            .and_then(|c| c.as_ref().ok())
-            .cloned()
+    /// Gets the channel's emote set without blocking, [Poll::Pending] if it is
+    /// currently being loaded
+    pub fn try_channel_emote_set(&self, id: &str) -> Poll<Option<Arc<[ChannelEmote]>>> {
+        match self.channels.try_read() {
+            Ok(channels) => Poll::Ready(channels.get(id).and_then(|c| c.as_ref().ok()).cloned()),
+            Err(_) => Poll::Pending,
+        }
     }
 
-    fn lazy_emote(
-        &self,
-        id: Ulid,
-        size: EmoteSize,
-    ) -> Lazy<MaybeImage, BoxFuture<'static, MaybeImage>> {
+    fn lazy_emote(&self, id: Ulid, size: EmoteSize) -> LazyImage {
         let client = self.client.clone();
         let cache = self.emotes.clone();
 
-        Lazy::new(Box::pin(async move {
-            cache
-                .get_with((id, size), async move {
-                    let start = std::time::Instant::now();
-                    let data = client
-                        .get(format!("https://cdn.7tv.app/emote/{id}/{size}"))
-                        .header("Accept", "image/webp,image/png,image/gif")
-                        .send()
-                        .await
-                        .ok()?
-                        .error_for_status()
-                        .ok()?
-                        .bytes()
-                        .await
-                        .ok()?;
-
-                    let kbps = data.len() as f32 / 1000.0 / start.elapsed().as_secs_f32();
-
-                    let img = {
-                        let _ = DECODER_SEMAPHORE.acquire().await.unwrap();
-                        tokio::task::spawn_blocking(move || AnimatedImage::from_bytes(&data))
+        LazyImage::new(move || {
+            let client = client.clone();
+            let cache = cache.clone();
+            Box::pin(async move {
+                cache
+                    .get_with((id, size), async move {
+                        let cdn = CONFIG.read().endpoints.seventv_cdn.clone();
+                        let start = std::time::Instant::now();
+                        // falls back to the older extensionless path
+                        let urls = [
+                            format!("{cdn}/emote/{id}/{size}"),
+                            format!(
+                                "{cdn}/emote/{id}/{}",
+                                size.to_string().trim_end_matches(".webp")
+                            ),
+                        ];
+                        let data = fetch_image(&client, &urls).await?;
+
+                        let kbps =
+                            data.as_ref().len() as f32 / 1000.0 / start.elapsed().as_secs_f32();
+
+                        let img = {
+                            let _ = DECODER_SEMAPHORE.acquire().await.unwrap();
+                            tokio::task::spawn_blocking(move || {
+                                AnimatedImage::from_bytes(data.as_ref())
+                            })
                             .await
                             .ok()?
                             .ok()?
-                    };
+                        };
 
-                    log::trace!(
-                        "7TV emote {id} loaded in {:?} at {kbps:02}kb/s",
-                        start.elapsed()
-                    );
+                        log::trace!(
+                            "7TV emote {id} loaded in {:?} at {kbps:02}kb/s",
+                            start.elapsed()
+                        );
 
-                    Some(img)
-                })
-                .await
-        }))
+                        Some(img)
+                    })
+                    .await
+            })
+        })
     }
 
     pub async fn load_channel_emote_set(&self, id: String) -> bool {
         let load = async || {
+            let api = CONFIG.read().endpoints.seventv_api.clone();
             let req = self
                 .client
-                .get(format!("https://7tv.io/v3/users/twitch/{id}"))
+                .get(format!("{api}/users/twitch/{id}"))
                 .send()
                 .await?
                 .error_for_status()?
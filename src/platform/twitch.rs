@@ -4,6 +4,8 @@ use crate::util::default_client;
 
 static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(default_client);
 
+pub mod auth;
+
 pub mod badges {
     use std::{
         borrow::Cow,
@@ -25,6 +27,8 @@ pub mod badges {
         id: Cow<'static, str>,
         #[serde(borrow)]
         image: Cow<'static, str>,
+        #[serde(borrow)]
+        title: Cow<'static, str>,
     }
 
     type BadgeCache = moka::sync::Cache<(String, String), Arc<OnceCell<anyhow::Result<Handle>>>>;
@@ -41,13 +45,45 @@ pub mod badges {
         serde_json::from_str::<HashMap<String, Vec<BadgesCache>>>(res_str!("badges.json")).unwrap()
     });
 
+    /// What the badge `id` of `set` stands for, e.g. how long someone has
+    /// been subscribed. Falls back to the title of any badge of the set, as
+    /// channel badges aren't in the bundled list, and then to the set's name
+    pub fn label(set: &str, id: &str) -> Cow<'static, str> {
+        match (set, id.parse::<u32>()) {
+            ("subscriber", Ok(n)) => return subscriber_label(n).into(),
+            ("bits", Ok(n)) => return format!("Cheered {n} bits").into(),
+            _ => (),
+        }
+        let Some(badges) = SAVED_BADGES.get(set) else {
+            return set.to_owned().into();
+        };
+        badges
+            .iter()
+            .find(|b| b.id == id)
+            .or(badges.first())
+            .map(|b| b.title.clone())
+            .unwrap_or_else(|| set.to_owned().into())
+    }
+
+    /// Subscriber badge ids are the months, plus 1000 per tier above the first
+    fn subscriber_label(id: u32) -> String {
+        let tier = match id / 1000 {
+            0 | 1 => "Subscriber".to_owned(),
+            t => format!("Tier {t} subscriber"),
+        };
+        match id % 1000 {
+            0 => format!("New {}", tier.to_lowercase()),
+            1 => format!("{tier}, 1 month"),
+            m => format!("{tier}, {m} months"),
+        }
+    }
+
     pub async fn load_badge(set: String, id: String) -> bool {
         let mut loaded = false;
 
-        BADGE_CACHE
-            .get_with((set.clone(), id.clone()), || {
-                Arc::new(tokio::sync::OnceCell::new())
-            })
+        let key = (set.clone(), id.clone());
+        let failed = BADGE_CACHE
+            .get_with_by_ref(&key, || Arc::new(tokio::sync::OnceCell::new()))
             .get_or_init(async || {
                 let url: Cow<'static, str> = SAVED_BADGES
                     .get(&set)
@@ -71,10 +107,31 @@ pub mod badges {
 
                 Ok(Handle::from_bytes(data))
             })
-            .await;
+            .await
+            .as_ref()
+            .inspect_err(|e| log::error!("failed to load badge {set}/{id}: {e}"))
+            .is_err();
+        if failed {
+            // so the next message with this badge tries again
+            BADGE_CACHE.invalidate(&key);
+        }
 
         loaded
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn subscriber_months() {
+            assert_eq!(subscriber_label(0), "New subscriber");
+            assert_eq!(subscriber_label(1), "Subscriber, 1 month");
+            assert_eq!(subscriber_label(24), "Subscriber, 24 months");
+            assert_eq!(subscriber_label(3012), "Tier 3 subscriber, 12 months");
+            assert_eq!(subscriber_label(2000), "New tier 2 subscriber");
+        }
+    }
 }
 
 pub mod emotes {
@@ -86,7 +143,11 @@ pub mod emotes {
     use moka::policy::EvictionPolicy;
     use tokio::sync::OnceCell;
 
-    use crate::{platform::DECODER_SEMAPHORE, widget::animated::AnimatedImage};
+    use crate::{
+        config::CONFIG,
+        platform::{CdnSize, DECODER_SEMAPHORE},
+        widget::animated::AnimatedImage,
+    };
 
     type EmoteCache = moka::sync::Cache<String, Arc<OnceCell<anyhow::Result<AnimatedImage>>>>;
 
@@ -98,16 +159,24 @@ pub mod emotes {
             .build()
     });
 
+    pub fn cdn_url(id: &str, size: CdnSize) -> String {
+        let size = match size {
+            CdnSize::OneX => "1.0",
+            CdnSize::TwoX => "2.0",
+            CdnSize::FourX => "3.0",
+        };
+        let cdn = &CONFIG.read().endpoints.twitch_cdn;
+        format!("{cdn}/emoticons/v2/{id}/default/dark/{size}")
+    }
+
     pub async fn load_emote(id: String) -> bool {
         let mut loaded = false;
 
-        EMOTE_CACHE
+        let failed = EMOTE_CACHE
             .get_with_by_ref(&id, || Arc::new(tokio::sync::OnceCell::new()))
             .get_or_init(async || {
                 let data = super::CLIENT
-                    .get(format!(
-                        "https://static-cdn.jtvnw.net/emoticons/v2/{id}/default/dark/1.0"
-                    ))
+                    .get(cdn_url(&id, CdnSize::OneX))
                     .header("Accept", "image/webp,image/png,image/gif,image/avif")
                     .send()
                     .await?
@@ -124,8 +193,468 @@ pub mod emotes {
 
                 Ok(img)
             })
-            .await;
+            .await
+            .as_ref()
+            .inspect_err(|e| log::error!("failed to load Twitch emote {id}: {e}"))
+            .is_err();
+        if failed {
+            // so the next message with this emote tries again
+            EMOTE_CACHE.invalidate(&id);
+        }
+
+        loaded
+    }
+}
+
+pub mod cheermotes {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, LazyLock},
+        time::Duration,
+    };
+
+    use anyhow::Context;
+    use iced::Color;
+    use moka::policy::EvictionPolicy;
+    use parking_lot::RwLock;
+    use serde::Deserialize;
+    use tokio::sync::OnceCell;
+
+    use super::{auth, helix::HELIX_API, users::resolve_user_id};
+    use crate::{platform::DECODER_SEMAPHORE, widget::animated::AnimatedImage};
+
+    /// Prefixes of the global cheermotes, for channels whose own list wasn't
+    /// loaded
+    const PREFIXES: &[&str] = &[
+        "Cheer",
+        "DoodleCheer",
+        "BibleThump",
+        "cheerwhal",
+        "Corgo",
+        "Scoops",
+        "uni",
+        "ShowLove",
+        "Party",
+        "SeemsGood",
+        "Pride",
+        "Kappa",
+        "FrankerZ",
+        "HeyGuys",
+        "DansGame",
+        "EleGiggle",
+        "TriHard",
+        "Kreygasm",
+        "4Head",
+        "SwiftRage",
+        "NotLikeThis",
+        "FailFish",
+        "VoHiYo",
+        "PJSalt",
+        "MrDestructoid",
+        "bday",
+        "RIPCheer",
+        "Shamrock",
+        "BitBoss",
+        "Streamlabs",
+        "Muxy",
+        "HolidayCheer",
+        "Goal",
+        "Anon",
+        "Charity",
+    ];
+
+    /// Minimum amount of bits for each cheermote tier
+    const TIERS: &[u32] = &[1, 100, 1000, 5000, 10000, 100000];
+
+    type CheermoteCache =
+        moka::sync::Cache<(String, u32), Arc<OnceCell<anyhow::Result<AnimatedImage>>>>;
+
+    pub static CHEERMOTE_CACHE: LazyLock<CheermoteCache> = LazyLock::new(|| {
+        moka::sync::CacheBuilder::new(100)
+            .eviction_policy(EvictionPolicy::tiny_lfu())
+            .time_to_idle(Duration::from_secs(60 * 30))
+            .name("cheermotes")
+            .build()
+    });
+
+    /// A cheermote that can be used in a channel, with the image of each of
+    /// its tiers by their minimum amount
+    #[derive(Debug, Clone)]
+    struct Cheermote {
+        prefix: String,
+        images: HashMap<u32, String>,
+    }
+
+    /// The cheermotes of each channel by login, global ones included, once
+    /// they were loaded
+    static CHANNEL_CHEERMOTES: LazyLock<RwLock<HashMap<String, Arc<[Cheermote]>>>> =
+        LazyLock::new(Default::default);
+
+    /// A cheermote token such as `Cheer100`
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Cheer {
+        pub prefix: String,
+        pub amount: u32,
+        tier: u32,
+        /// Of the image for the cheer's tier
+        url: String,
+    }
+
+    impl Cheer {
+        /// Parses `word` as a prefix of the cheermotes of `channel` followed by
+        /// a non-zero amount of bits
+        pub fn parse(channel: &str, word: &str) -> Option<Self> {
+            let digits = word.len() - word.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+            let (prefix, amount) = word.split_at(word.len() - digits);
+            let amount = amount.parse::<u32>().ok().filter(|a| *a > 0)?;
+            if let Some(cheermotes) = CHANNEL_CHEERMOTES.read().get(channel) {
+                let cheermote = cheermotes
+                    .iter()
+                    .find(|c| c.prefix.eq_ignore_ascii_case(prefix))?;
+                let (tier, url) = cheermote
+                    .images
+                    .iter()
+                    .filter(|(min, _)| **min <= amount)
+                    .max_by_key(|(min, _)| **min)?;
+                return Some(Self {
+                    prefix: cheermote.prefix.clone(),
+                    amount,
+                    tier: *tier,
+                    url: url.clone(),
+                });
+            }
+            let prefix = PREFIXES.iter().find(|p| p.eq_ignore_ascii_case(prefix))?;
+            let tier = tier(amount);
+            Some(Self {
+                prefix: (*prefix).to_owned(),
+                amount,
+                tier,
+                url: format!(
+                    "https://d3aqoihi2n8ty8.cloudfront.net/actions/{}/dark/animated/{tier}/1.gif",
+                    prefix.to_lowercase()
+                ),
+            })
+        }
+
+        /// The minimum amount of the tier this cheer falls into
+        pub fn tier(&self) -> u32 {
+            self.tier
+        }
+
+        pub fn color(&self) -> Color {
+            bits_color(self.amount)
+        }
+
+        pub fn image(&self) -> Option<AnimatedImage> {
+            CHEERMOTE_CACHE
+                .get(&(self.prefix.clone(), self.tier()))
+                .and_then(|h| h.get()?.as_ref().ok().cloned())
+        }
+    }
+
+    /// The color of the tier `amount` bits fall into
+    pub fn bits_color(amount: u32) -> Color {
+        match tier(amount) {
+            1 => Color::from_rgb8(0x97, 0x97, 0x97),
+            100 => Color::from_rgb8(0x9c, 0x3e, 0xe8),
+            1000 => Color::from_rgb8(0x1d, 0xb2, 0xa5),
+            5000 => Color::from_rgb8(0x00, 0x99, 0xfe),
+            10000 => Color::from_rgb8(0xf4, 0x30, 0x21),
+            _ => Color::from_rgb8(0xf3, 0xa7, 0x1a),
+        }
+    }
+
+    fn tier(amount: u32) -> u32 {
+        TIERS
+            .iter()
+            .rev()
+            .find(|t| **t <= amount)
+            .copied()
+            .unwrap_or(1)
+    }
+
+    /// All the cheers in a message's text in `channel`
+    pub fn cheers<'a>(channel: &'a str, text: &'a str) -> impl Iterator<Item = Cheer> + 'a {
+        text.split(' ').filter_map(|w| Cheer::parse(channel, w))
+    }
+
+    #[derive(Deserialize)]
+    struct CheermotesResponse {
+        data: Vec<CheermoteData>,
+    }
+
+    #[derive(Deserialize)]
+    struct CheermoteData {
+        prefix: String,
+        tiers: Vec<TierData>,
+    }
+
+    #[derive(Deserialize)]
+    struct TierData {
+        min_bits: u32,
+        images: TierImages,
+    }
+
+    #[derive(Deserialize)]
+    struct TierImages {
+        dark: ThemedImages,
+    }
+
+    #[derive(Deserialize)]
+    struct ThemedImages {
+        /// By scale, `"1"` to `"4"`
+        animated: HashMap<String, String>,
+    }
+
+    /// Loads the cheermotes usable in `channel` from Helix, which needs the
+    /// `token` of an account. Until then the global ones are used
+    pub async fn load_channel_cheermotes(channel: String, token: String) -> anyhow::Result<()> {
+        let client_id = auth::CLIENT_ID.context("this build has no Twitch client id")?;
+        let id = resolve_user_id(&channel).await?;
+        let res: CheermotesResponse = super::CLIENT
+            .get(format!("{HELIX_API}/bits/cheermotes?broadcaster_id={id}"))
+            .header("Client-Id", client_id)
+            .bearer_auth(token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let cheermotes = res
+            .data
+            .into_iter()
+            .map(|c| Cheermote {
+                prefix: c.prefix,
+                images: c
+                    .tiers
+                    .into_iter()
+                    .filter_map(|t| Some((t.min_bits, t.images.dark.animated.get("1")?.clone())))
+                    .collect(),
+            })
+            .collect();
+        CHANNEL_CHEERMOTES.write().insert(channel, cheermotes);
+        Ok(())
+    }
+
+    pub async fn load_cheermote(cheer: Cheer) -> bool {
+        let mut loaded = false;
+        let tier = cheer.tier();
+
+        let failed = CHEERMOTE_CACHE
+            .get_with((cheer.prefix.clone(), tier), || {
+                Arc::new(tokio::sync::OnceCell::new())
+            })
+            .get_or_init(async || {
+                let data = super::CLIENT
+                    .get(&cheer.url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await?;
+
+                let img = {
+                    let _ = DECODER_SEMAPHORE.acquire().await.unwrap();
+                    tokio::task::spawn_blocking(move || AnimatedImage::from_bytes(&data)).await??
+                };
+
+                loaded = true;
+
+                Ok(img)
+            })
+            .await
+            .as_ref()
+            .inspect_err(|e| log::error!("failed to load cheermote {}{tier}: {e}", cheer.prefix))
+            .is_err();
+        if failed {
+            CHEERMOTE_CACHE.invalidate(&(cheer.prefix.clone(), tier));
+        }
 
         loaded
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn cheer_parsing() {
+            let parse = |word| Cheer::parse("forsen", word);
+            let cheer = parse("Cheer100").unwrap();
+            assert_eq!((cheer.prefix.as_str(), cheer.amount), ("Cheer", 100));
+            assert!(cheer.url.ends_with("/cheer/dark/animated/100/1.gif"));
+            assert_eq!(parse("cheer1").map(|c| c.prefix), Some("Cheer".to_owned()));
+            assert_eq!(parse("Cheer"), None);
+            assert_eq!(parse("Cheer0"), None);
+            assert_eq!(parse("NotACheer100"), None);
+            assert_eq!(parse("100"), None);
+        }
+
+        #[test]
+        fn channel_cheermotes_replace_the_global_ones() {
+            CHANNEL_CHEERMOTES.write().insert(
+                "xqc".to_owned(),
+                Arc::new([Cheermote {
+                    prefix: "xqcCheer".to_owned(),
+                    images: HashMap::from([(100, "https://example.com/100.gif".to_owned())]),
+                }]),
+            );
+            let cheer = Cheer::parse("xqc", "XQCcheer150").unwrap();
+            assert_eq!(cheer.prefix, "xqcCheer");
+            assert_eq!(cheer.url, "https://example.com/100.gif");
+            assert_eq!(cheer.tier(), 100);
+            // below the channel's lowest tier
+            assert_eq!(Cheer::parse("xqc", "xqcCheer1"), None);
+            assert_eq!(Cheer::parse("xqc", "Cheer100"), None);
+        }
+
+        #[test]
+        fn cheer_tiers() {
+            assert_eq!(tier(1), 1);
+            assert_eq!(tier(99), 1);
+            assert_eq!(tier(100), 100);
+            assert_eq!(tier(4999), 1000);
+            assert_eq!(tier(250000), 100000);
+        }
+    }
+}
+
+pub mod users {
+    use std::{sync::LazyLock, time::Duration};
+
+    use moka::policy::EvictionPolicy;
+
+    /// Client id of Twitch's own web player, accepted by GQL without a token
+    const GQL_CLIENT_ID: &str = "kimne78kx3ncx6brgo4mv6wki5h1ko";
+
+    /// Ids of users by login, only successful lookups are kept
+    static USER_IDS: LazyLock<moka::future::Cache<String, String>> = LazyLock::new(|| {
+        moka::future::CacheBuilder::new(1000)
+            .eviction_policy(EvictionPolicy::tiny_lfu())
+            .time_to_live(Duration::from_secs(60 * 60 * 24))
+            .name("user_ids")
+            .build()
+    });
+
+    async fn ivr_user_id(login: &str) -> anyhow::Result<String> {
+        let data = super::CLIENT
+            .get(format!("https://api.ivr.fi/v2/twitch/user?login={login}"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<serde_json::Value>()
+            .await?;
+        ivr_id(login, &data)
+    }
+
+    fn ivr_id(login: &str, data: &serde_json::Value) -> anyhow::Result<String> {
+        data[0]["id"]
+            .as_str()
+            .map(str::to_owned)
+            .ok_or_else(|| anyhow::anyhow!("ivr.fi doesn't know {login}"))
+    }
+
+    async fn gql_user_id(login: &str) -> anyhow::Result<String> {
+        let data = super::CLIENT
+            .post("https://gql.twitch.tv/gql")
+            .header("Client-Id", GQL_CLIENT_ID)
+            .json(&serde_json::json!({
+                "query": "query($login: String!) { user(login: $login) { id } }",
+                "variables": { "login": login },
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<serde_json::Value>()
+            .await?;
+        gql_id(login, &data)
+    }
+
+    fn gql_id(login: &str, data: &serde_json::Value) -> anyhow::Result<String> {
+        data["data"]["user"]["id"]
+            .as_str()
+            .map(str::to_owned)
+            .ok_or_else(|| anyhow::anyhow!("no Twitch user called {login}"))
+    }
+
+    /// The id of the user called `login`, from ivr.fi or Twitch's GQL if it's
+    /// down, cached once found
+    pub async fn resolve_user_id(login: &str) -> anyhow::Result<String> {
+        USER_IDS
+            .try_get_with_by_ref(login, async {
+                match ivr_user_id(login).await {
+                    Ok(id) => Ok(id),
+                    Err(e) => {
+                        log::warn!("ivr.fi lookup of {login} failed, asking GQL: {e}");
+                        gql_user_id(login).await
+                    }
+                }
+            })
+            .await
+            .map_err(|e| anyhow::anyhow!("couldn't resolve the id of {login}: {e}"))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use serde_json::json;
+
+        use super::*;
+
+        #[test]
+        fn unknown_users_are_errors() {
+            let ivr = json!([{ "login": "forsen", "id": "22484632" }]);
+            assert_eq!(ivr_id("forsen", &ivr).unwrap(), "22484632");
+            assert!(ivr_id("nobody", &json!([])).is_err());
+
+            let gql = json!({ "data": { "user": { "id": "22484632" } } });
+            assert_eq!(gql_id("forsen", &gql).unwrap(), "22484632");
+            let unknown = json!({ "data": { "user": null } });
+            assert_eq!(
+                gql_id("nobody", &unknown).unwrap_err().to_string(),
+                "no Twitch user called nobody"
+            );
+        }
+    }
+}
+
+pub mod helix {
+    use anyhow::{Context, bail};
+    use serde::Deserialize;
+
+    use super::{auth, users::resolve_user_id};
+
+    pub const HELIX_API: &str = "https://api.twitch.tv/helix";
+
+    #[derive(Deserialize)]
+    struct ErrorResponse {
+        message: String,
+    }
+
+    /// Announces `message` in `channel` as `sender`, who has to be a moderator
+    /// there. IRC doesn't accept `/announce` anymore
+    pub async fn send_announcement(
+        channel: &str,
+        sender: &str,
+        token: &str,
+        message: &str,
+        color: &str,
+    ) -> anyhow::Result<()> {
+        let client_id = auth::CLIENT_ID.context("this build has no Twitch client id")?;
+        let broadcaster_id = resolve_user_id(channel).await?;
+        let moderator_id = resolve_user_id(sender).await?;
+        let res = super::CLIENT
+            .post(format!(
+                "{HELIX_API}/chat/announcements?broadcaster_id={broadcaster_id}&moderator_id={moderator_id}"
+            ))
+            .header("Client-Id", client_id)
+            .bearer_auth(token)
+            .json(&serde_json::json!({ "message": message, "color": color }))
+            .send()
+            .await?;
+        if !res.status().is_success() {
+            let error: ErrorResponse = res.json().await?;
+            bail!("{}", error.message);
+        }
+        Ok(())
+    }
 }
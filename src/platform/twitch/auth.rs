@@ -0,0 +1,168 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, bail};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use super::CLIENT;
+
+const ID_API: &str = "https://id.twitch.tv/oauth2";
+
+/// Client id of the app registered with Twitch to log in with, given as
+/// `JULIARINO_CLIENT_ID` when building
+pub const CLIENT_ID: Option<&str> = option_env!("JULIARINO_CLIENT_ID");
+
+/// Enough to read and send chat messages and announcements
+const SCOPES: &str = "chat:read chat:edit moderator:manage:announcements";
+
+/// A login waiting for the user to enter [DeviceCode::user_code] at
+/// [DeviceCode::verification_uri]
+#[derive(derive_more::Debug, Clone, Deserialize)]
+pub struct DeviceCode {
+    #[debug(skip)]
+    device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    /// Seconds until the code can't be used anymore
+    expires_in: u64,
+    /// Seconds to wait between checks of whether the user is done
+    interval: u64,
+}
+
+/// The tokens of an account that logged in, never logged
+#[derive(derive_more::Debug, Clone)]
+pub struct Token {
+    pub login: String,
+    #[debug(skip)]
+    pub access_token: String,
+    #[debug(skip)]
+    pub refresh_token: String,
+    pub expires_at: DateTime<Utc>,
+    pub scopes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: String,
+    /// Seconds
+    expires_in: i64,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+/// What Twitch says about a token it accepts
+#[derive(Debug, Clone, Deserialize)]
+pub struct Validation {
+    pub login: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Seconds until the token expires, 0 if it doesn't
+    expires_in: i64,
+}
+
+impl Validation {
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        (self.expires_in > 0).then(|| Utc::now() + chrono::Duration::seconds(self.expires_in))
+    }
+}
+
+fn client_id() -> anyhow::Result<&'static str> {
+    CLIENT_ID.context("this build has no Twitch client id to log in with")
+}
+
+/// Starts logging in with Twitch's [device code flow], the user has to enter
+/// the returned code on Twitch
+///
+/// [device code flow]: https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#device-code-grant-flow
+pub async fn request_device_code() -> anyhow::Result<DeviceCode> {
+    Ok(CLIENT
+        .post(format!("{ID_API}/device"))
+        .form(&[("client_id", client_id()?), ("scopes", SCOPES)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
+
+/// Waits until the user entered `code` on Twitch, or it expired
+pub async fn wait_for_token(code: DeviceCode) -> anyhow::Result<Token> {
+    let client_id = client_id()?;
+    let deadline = Instant::now() + Duration::from_secs(code.expires_in);
+    let mut interval = Duration::from_secs(code.interval.max(1));
+    loop {
+        tokio::time::sleep(interval).await;
+        if Instant::now() >= deadline {
+            bail!("the login code expired");
+        }
+        let res = CLIENT
+            .post(format!("{ID_API}/token"))
+            .form(&[
+                ("client_id", client_id),
+                ("scopes", SCOPES),
+                ("device_code", code.device_code.as_str()),
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+            ])
+            .send()
+            .await?;
+        if res.status().is_success() {
+            return token(res.json().await?).await;
+        }
+        let error: ErrorResponse = res.json().await?;
+        match error.message.as_str() {
+            "authorization_pending" => (),
+            "slow_down" => interval += Duration::from_secs(5),
+            _ => bail!("Twitch refused the login: {}", error.message),
+        }
+    }
+}
+
+/// Exchanges `refresh_token` for new tokens, it can't be used again after
+pub async fn refresh(refresh_token: &str) -> anyhow::Result<Token> {
+    let res = CLIENT
+        .post(format!("{ID_API}/token"))
+        .form(&[
+            ("client_id", client_id()?),
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ])
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        let error: ErrorResponse = res.json().await?;
+        bail!("Twitch refused to refresh the token: {}", error.message);
+    }
+    token(res.json().await?).await
+}
+
+/// Checks whether Twitch still accepts `access_token`, [None] if it expired or
+/// was revoked
+pub async fn validate(access_token: &str) -> anyhow::Result<Option<Validation>> {
+    let res = CLIENT
+        .get(format!("{ID_API}/validate"))
+        .header("Authorization", format!("OAuth {access_token}"))
+        .send()
+        .await?;
+    if res.status() == reqwest::StatusCode::UNAUTHORIZED {
+        return Ok(None);
+    }
+    Ok(Some(res.error_for_status()?.json().await?))
+}
+
+/// Looks up who the new token belongs to
+async fn token(res: TokenResponse) -> anyhow::Result<Token> {
+    let validation = validate(&res.access_token)
+        .await?
+        .context("Twitch didn't accept the new token")?;
+    Ok(Token {
+        login: validation.login,
+        access_token: res.access_token,
+        refresh_token: res.refresh_token,
+        expires_at: Utc::now() + chrono::Duration::seconds(res.expires_in),
+        scopes: validation.scopes,
+    })
+}
@@ -0,0 +1,85 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::{LazyLock, mpsc},
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use rodio::{OutputStream, OutputStreamHandle, Source};
+
+/// Least time between two highlight sounds, the ones in between are dropped
+const DEBOUNCE: Duration = Duration::from_millis(1500);
+
+struct Request {
+    path: PathBuf,
+    volume: f32,
+}
+
+/// Sounds to play, sent to the thread owning the audio output since it can't
+/// be moved between threads
+static PLAYER: LazyLock<mpsc::Sender<Request>> = LazyLock::new(|| {
+    let (tx, rx) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("audio".into())
+        .spawn(move || player(rx))
+        .expect("couldn't start the audio thread");
+    tx
+});
+
+static LAST_HIGHLIGHT: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Plays the sound of a highlight, unless another one played less than
+/// [DEBOUNCE] ago
+pub fn play_highlight(path: &Path, volume: f32) {
+    let mut last = LAST_HIGHLIGHT.lock();
+    if last.is_some_and(|l| l.elapsed() < DEBOUNCE) {
+        return;
+    }
+    *last = Some(Instant::now());
+    drop(last);
+    play(path, volume);
+}
+
+/// Plays the sound file at `path` on the default output, `volume` being from 0
+/// to 1
+pub fn play(path: &Path, volume: f32) {
+    let req = Request {
+        path: path.to_owned(),
+        volume,
+    };
+    if PLAYER.send(req).is_err() {
+        log::error!("the audio thread stopped, can't play sounds");
+    }
+}
+
+fn player(rx: mpsc::Receiver<Request>) {
+    // opened on the first sound, so nothing holds the device before that
+    let mut output: Option<(OutputStream, OutputStreamHandle)> = None;
+    for req in rx {
+        if output.is_none() {
+            match OutputStream::try_default() {
+                Ok(o) => output = Some(o),
+                Err(e) => {
+                    log::error!("couldn't open the audio output: {e}");
+                    continue;
+                }
+            }
+        }
+        let Some((_, handle)) = &output else {
+            continue;
+        };
+
+        let played = File::open(&req.path)
+            .map_err(anyhow::Error::from)
+            .and_then(|f| Ok(rodio::Decoder::new(BufReader::new(f))?))
+            .and_then(|source| {
+                // plays in the background, the next sound can be mixed in
+                Ok(handle.play_raw(source.convert_samples().amplify(req.volume))?)
+            });
+        if let Err(e) = played {
+            log::error!("couldn't play {}: {e}", req.path.display());
+        }
+    }
+}
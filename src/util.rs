@@ -1,3 +1,5 @@
+use crate::config::CONFIG;
+
 #[macro_export]
 macro_rules! res {
     ($file: literal) => {
@@ -12,8 +14,16 @@ macro_rules! res_str {
     };
 }
 
+/// HTTP client with the proxy from the config, if any
 pub fn default_client() -> reqwest::Client {
-    reqwest::Client::builder()
+    let mut builder = reqwest::Client::builder();
+    if let Some(proxy) = CONFIG.read().proxy.as_deref() {
+        match reqwest::Proxy::all(proxy) {
+            Ok(proxy) => builder = builder.proxy(proxy),
+            Err(e) => log::error!("Invalid proxy URL \"{proxy}\", connecting directly: {e}"),
+        }
+    }
+    builder
         .brotli(true)
         .deflate(true)
         .gzip(true)
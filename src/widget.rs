@@ -1,7 +1,9 @@
 pub mod animated;
+pub mod context_menu;
 pub mod draggable;
 pub mod icon_button;
 pub mod overlaid;
 pub mod scrollie;
+pub mod splitter;
 mod tab;
 pub mod tabs;
@@ -110,6 +110,17 @@ impl AnimatedImage {
         self
     }
 
+    /// Multiplies the fixed dimensions of the image by `scale`
+    pub fn scaled(mut self, scale: f32) -> Self {
+        if let Length::Fixed(w) = self.width {
+            self.width = Length::Fixed(w * scale);
+        }
+        if let Length::Fixed(h) = self.height {
+            self.height = Length::Fixed(h * scale);
+        }
+        self
+    }
+
     fn from_animation_decoder<'a, D: image::AnimationDecoder<'a>>(
         dec: D,
     ) -> Result<Self, AnimatedImageError> {
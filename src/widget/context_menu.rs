@@ -0,0 +1,345 @@
+use iced::{
+    Element, Event, Length, Point, Rectangle, Size, Vector,
+    advanced::{
+        Clipboard, Layout, Renderer, Shell, Widget,
+        layout::{Limits, Node},
+        overlay, renderer,
+        widget::{Operation, Tree, tree},
+    },
+    keyboard, mouse, touch,
+};
+
+/// Shows `menu` where the content was right-clicked, until something in it is
+/// clicked, the user clicks outside of it or presses Escape
+pub struct ContextMenu<'a, M, T, R> {
+    /// The content and the menu
+    children: [Element<'a, M, T, R>; 2],
+}
+
+impl<'a, M, T, R> ContextMenu<'a, M, T, R> {
+    pub fn new(
+        content: impl Into<Element<'a, M, T, R>>,
+        menu: impl Into<Element<'a, M, T, R>>,
+    ) -> Self {
+        Self {
+            children: [content.into(), menu.into()],
+        }
+    }
+}
+
+pub fn context_menu<'a, M, T, R>(
+    content: impl Into<Element<'a, M, T, R>>,
+    menu: impl Into<Element<'a, M, T, R>>,
+) -> ContextMenu<'a, M, T, R> {
+    ContextMenu::new(content, menu)
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Where the menu was opened, relative to the content
+    open_at: Option<Vector>,
+}
+
+impl<'a, M, T, R> Widget<M, T, R> for ContextMenu<'a, M, T, R>
+where
+    R: Renderer,
+{
+    fn size(&self) -> Size<Length> {
+        self.children[0].as_widget().size()
+    }
+
+    fn size_hint(&self) -> Size<Length> {
+        self.children[0].as_widget().size_hint()
+    }
+
+    fn layout(&mut self, tree: &mut Tree, renderer: &R, limits: &Limits) -> Node {
+        self.children[0]
+            .as_widget_mut()
+            .layout(&mut tree.children[0], renderer, limits)
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut R,
+        theme: &T,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.children[0].as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            layout,
+            cursor,
+            viewport,
+        );
+    }
+
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::default())
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        self.children.iter().map(Tree::new).collect()
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(&self.children);
+    }
+
+    fn operate(
+        &mut self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &R,
+        operation: &mut dyn Operation,
+    ) {
+        self.children[0].as_widget_mut().operate(
+            &mut tree.children[0],
+            layout,
+            renderer,
+            operation,
+        );
+    }
+
+    fn update(
+        &mut self,
+        tree: &mut Tree,
+        event: &Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &R,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, M>,
+        viewport: &Rectangle,
+    ) {
+        self.children[0].as_widget_mut().update(
+            &mut tree.children[0],
+            event,
+            layout,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        );
+        if shell.is_event_captured() {
+            return;
+        }
+
+        if let Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right)) = event
+            && let Some(position) = cursor.position_over(layout.bounds())
+        {
+            let state = tree.state.downcast_mut::<State>();
+            state.open_at = Some(position - layout.bounds().position());
+            shell.capture_event();
+            shell.request_redraw();
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &R,
+    ) -> mouse::Interaction {
+        self.children[0].as_widget().mouse_interaction(
+            &tree.children[0],
+            layout,
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'b>,
+        renderer: &R,
+        viewport: &Rectangle,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, M, T, R>> {
+        let [content, menu] = &mut self.children;
+        let [content_tree, menu_tree] = &mut tree.children[..] else {
+            return None;
+        };
+        let content =
+            content
+                .as_widget_mut()
+                .overlay(content_tree, layout, renderer, viewport, translation);
+
+        let state = tree.state.downcast_mut::<State>();
+        let Some(offset) = state.open_at else {
+            return content;
+        };
+        let menu = overlay::Element::new(Box::new(Menu {
+            menu,
+            tree: menu_tree,
+            state,
+            position: layout.bounds().position() + offset + translation,
+        }));
+
+        Some(overlay::Group::with_children(content.into_iter().chain([menu]).collect()).overlay())
+    }
+}
+
+impl<'a, M, T, R> From<ContextMenu<'a, M, T, R>> for Element<'a, M, T, R>
+where
+    M: 'a,
+    T: 'a,
+    R: Renderer + 'a,
+{
+    fn from(value: ContextMenu<'a, M, T, R>) -> Self {
+        Element::new(value)
+    }
+}
+
+struct Menu<'a, 'b, M, T, R> {
+    menu: &'b mut Element<'a, M, T, R>,
+    tree: &'b mut Tree,
+    state: &'b mut State,
+    /// Where the menu was opened, in window coordinates
+    position: Point,
+}
+
+impl<M, T, R> Menu<'_, '_, M, T, R> {
+    fn close(&mut self, shell: &mut Shell<'_, M>) {
+        self.state.open_at = None;
+        shell.request_redraw();
+    }
+}
+
+impl<M, T, R> overlay::Overlay<M, T, R> for Menu<'_, '_, M, T, R>
+where
+    R: Renderer,
+{
+    fn layout(&mut self, renderer: &R, bounds: Size) -> Node {
+        let menu =
+            self.menu
+                .as_widget_mut()
+                .layout(self.tree, renderer, &Limits::new(Size::ZERO, bounds));
+        // kept inside the window, opening towards the top left near the edges
+        let size = menu.size();
+        let position = Point::new(
+            self.position.x.min(bounds.width - size.width).max(0.0),
+            self.position.y.min(bounds.height - size.height).max(0.0),
+        );
+        Node::with_children(size, vec![menu]).move_to(position)
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut R,
+        theme: &T,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+    ) {
+        let Some(menu_layout) = layout.children().next() else {
+            return;
+        };
+        self.menu.as_widget().draw(
+            self.tree,
+            renderer,
+            theme,
+            style,
+            menu_layout,
+            cursor,
+            &layout.bounds(),
+        );
+    }
+
+    fn operate(&mut self, layout: Layout<'_>, renderer: &R, operation: &mut dyn Operation) {
+        if let Some(menu_layout) = layout.children().next() {
+            self.menu
+                .as_widget_mut()
+                .operate(self.tree, menu_layout, renderer, operation);
+        }
+    }
+
+    fn update(
+        &mut self,
+        event: &Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &R,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, M>,
+    ) {
+        let Some(menu_layout) = layout.children().next() else {
+            return;
+        };
+        let over = cursor.is_over(layout.bounds());
+
+        match event {
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key: keyboard::Key::Named(keyboard::key::Named::Escape),
+                ..
+            }) => {
+                self.close(shell);
+                shell.capture_event();
+                return;
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(_))
+            | Event::Touch(touch::Event::FingerPressed { .. })
+                if !over =>
+            {
+                self.close(shell);
+                shell.capture_event();
+                return;
+            }
+            _ => (),
+        }
+
+        self.menu.as_widget_mut().update(
+            self.tree,
+            event,
+            menu_layout,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            &layout.bounds(),
+        );
+
+        // buttons publish on release, so the menu closes after they've run
+        if over
+            && matches!(
+                event,
+                Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                    | Event::Touch(touch::Event::FingerLifted { .. })
+            )
+        {
+            self.close(shell);
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &R,
+    ) -> mouse::Interaction {
+        let Some(menu_layout) = layout.children().next() else {
+            return mouse::Interaction::None;
+        };
+        self.menu.as_widget().mouse_interaction(
+            self.tree,
+            menu_layout,
+            cursor,
+            &layout.bounds(),
+            renderer,
+        )
+    }
+}
@@ -49,9 +49,10 @@ where
         self
     }
 
-    pub fn color(mut self, color: impl Into<Color> + Copy + 'a) -> Self {
-        self.handle = self.handle.style(move |_, _| svg::Style {
-            color: Some(color.into()),
+    /// Colors the icon depending on the theme
+    pub fn color(mut self, color: impl Fn(&T) -> Color + 'a) -> Self {
+        self.handle = self.handle.style(move |theme, _| svg::Style {
+            color: Some(color(theme)),
         });
         self
     }
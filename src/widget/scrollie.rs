@@ -8,7 +8,11 @@ use iced::{
         Clipboard, Layout, Renderer, Shell, Widget,
         layout::{Limits, Node},
         mouse, overlay,
-        widget::{Operation, Tree, operation::Scrollable, tree::Tag},
+        widget::{
+            Operation, Tree,
+            operation::{Focusable, Scrollable},
+            tree::Tag,
+        },
     },
     keyboard,
     widget::Id,
@@ -43,7 +47,10 @@ pub struct Scrollie<'a, M, T, R, K> {
     width: Length,
     height: Length,
     natural_scrolling: bool,
+    scroll_speed: f32,
+    smooth_scroll: bool,
     on_scroll: Option<Box<dyn Fn(ScrollViewport) -> M + 'a>>,
+    on_zoom: Option<Box<dyn Fn(i32) -> M + 'a>>,
 }
 
 pub fn scrollie<'a, M, T, R, K>(
@@ -61,7 +68,10 @@ impl<'a, M, T, R, K> Scrollie<'a, M, T, R, K> {
             width: Length::Shrink,
             height: Length::Shrink,
             natural_scrolling: false,
+            scroll_speed: 80.0,
+            smooth_scroll: true,
             on_scroll: None,
+            on_zoom: None,
         }
     }
 
@@ -80,11 +90,29 @@ impl<'a, M, T, R, K> Scrollie<'a, M, T, R, K> {
         self
     }
 
+    /// Pixels scrolled per mouse wheel line
+    pub fn scroll_speed(mut self, scroll_speed: f32) -> Self {
+        self.scroll_speed = scroll_speed;
+        self
+    }
+
+    pub fn smooth_scroll(mut self, smooth_scroll: bool) -> Self {
+        self.smooth_scroll = smooth_scroll;
+        self
+    }
+
     pub fn on_scroll(mut self, on_scroll: impl Fn(ScrollViewport) -> M + 'a) -> Self {
         self.on_scroll = Some(Box::new(on_scroll));
         self
     }
 
+    /// Published with the number of whole lines scrolled while Ctrl is held,
+    /// which then doesn't scroll. Positive when scrolling up
+    pub fn on_zoom(mut self, on_zoom: impl Fn(i32) -> M + 'a) -> Self {
+        self.on_zoom = Some(Box::new(on_zoom));
+        self
+    }
+
     pub fn id(mut self, id: Id) -> Self {
         self.id = Some(id);
         self
@@ -142,6 +170,13 @@ pub struct State<K> {
     last_frame: std::time::Instant,
     /// Whether this was scrolled, either by scrolling or an operation and on_scroll should be called
     scrolled: bool,
+    /// Wheel events don't carry the modifiers, so they're tracked here
+    modifiers: keyboard::Modifiers,
+    /// Lines scrolled while zooming that didn't add up to a whole one yet
+    zoom_remainder: f32,
+    /// Clicked last, so Home and End scroll instead of moving the cursor of a
+    /// text input
+    focused: bool,
 }
 
 #[derive(Debug)]
@@ -163,6 +198,9 @@ impl<K: PartialEq> State<K> {
             animation_state: AnimationState::None,
             last_frame: std::time::Instant::now(),
             scrolled: false,
+            modifiers: keyboard::Modifiers::default(),
+            zoom_remainder: 0.0,
+            focused: false,
         }
     }
 
@@ -189,6 +227,11 @@ impl<K: PartialEq> State<K> {
         }
     }
 
+    /// Current vertical scroll offset from the top of the content
+    pub fn translation(&self) -> f32 {
+        self.translation
+    }
+
     pub fn scroll_to(&mut self, translation: f32) {
         let prev_transl = self.translation;
         self.translation = translation;
@@ -423,6 +466,8 @@ where
             state,
         );
 
+        operation.focusable(self.id.as_ref(), layout.bounds(), state);
+
         operation.custom(self.id.as_ref(), layout.bounds(), state);
 
         operation.traverse(&mut |op| {
@@ -486,6 +531,65 @@ where
             );
         }
 
+        if let Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) = event {
+            state.modifiers = *modifiers;
+        }
+
+        if !shell.is_event_captured()
+            && state.modifiers.command()
+            && let Some(on_zoom) = &self.on_zoom
+            && cursor.position_in(layout.bounds()).is_some()
+        {
+            let lines = match event {
+                Event::Mouse(mouse::Event::WheelScrolled {
+                    delta: mouse::ScrollDelta::Lines { y, .. },
+                }) => Some(*y),
+                Event::Mouse(mouse::Event::WheelScrolled {
+                    delta: mouse::ScrollDelta::Pixels { y, .. },
+                }) => Some(y / self.scroll_speed),
+                _ => None,
+            };
+            if let Some(lines) = lines {
+                let lines = state.zoom_remainder + lines;
+                let steps = lines.trunc();
+                state.zoom_remainder = lines - steps;
+                if steps != 0.0 {
+                    shell.publish(on_zoom(steps as i32));
+                }
+                shell.capture_event();
+            }
+        }
+
+        if let Event::Mouse(mouse::Event::ButtonPressed(_)) = event {
+            state.focused = cursor.position_in(layout.bounds()).is_some();
+        }
+
+        if !shell.is_event_captured()
+            && state.focused
+            && cursor.position_in(layout.bounds()).is_some()
+            && let Event::Keyboard(keyboard::Event::KeyPressed {
+                physical_key: keyboard::key::Physical::Code(code),
+                ..
+            }) = event
+        {
+            // jumps right away even with smooth scrolling, so End lands exactly
+            // on the bottom and keeps following new children
+            let target = match code {
+                keyboard::key::Code::Home => Some(0.0),
+                keyboard::key::Code::End => {
+                    Some(state.content_bounds().height - state.bounds.height)
+                }
+                _ => None,
+            };
+            if let Some(target) = target {
+                state.animation_state = AnimationState::None;
+                state.scroll_to(target);
+                if layout.bounds().intersects(viewport) {
+                    shell.request_redraw();
+                }
+            }
+        }
+
         if !shell.is_event_captured() {
             let delta = match (cursor.position_in(layout.bounds()).is_some(), event) {
                 (
@@ -493,7 +597,7 @@ where
                     Event::Mouse(mouse::Event::WheelScrolled {
                         delta: mouse::ScrollDelta::Lines { x: _, y },
                     }),
-                ) => Some(-y * 80.0),
+                ) => Some(-y * self.scroll_speed),
                 (
                     true,
                     Event::Mouse(mouse::Event::WheelScrolled {
@@ -522,21 +626,33 @@ where
                     delta = -delta;
                 }
 
-                let (lerp, start, target) =
-                    if let AnimationState::Animating { target, .. } = state.animation_state {
-                        (0.0, state.translation, target + delta)
-                    } else {
-                        (0.0, state.translation, state.translation + delta)
+                if !self.smooth_scroll {
+                    let target = match state.animation_state {
+                        AnimationState::Animating { target, .. } => target,
+                        AnimationState::None => state.translation,
                     };
-
-                state.animation_state = AnimationState::Animating {
-                    lerp,
-                    start,
-                    target,
-                };
-                state.last_frame = std::time::Instant::now();
-                if layout.bounds().intersects(viewport) {
-                    shell.request_redraw();
+                    state.animation_state = AnimationState::None;
+                    state.scroll_to(target + delta);
+                    if layout.bounds().intersects(viewport) {
+                        shell.request_redraw();
+                    }
+                } else {
+                    let (lerp, start, target) =
+                        if let AnimationState::Animating { target, .. } = state.animation_state {
+                            (0.0, state.translation, target + delta)
+                        } else {
+                            (0.0, state.translation, state.translation + delta)
+                        };
+
+                    state.animation_state = AnimationState::Animating {
+                        lerp,
+                        start,
+                        target,
+                    };
+                    state.last_frame = std::time::Instant::now();
+                    if layout.bounds().intersects(viewport) {
+                        shell.request_redraw();
+                    }
                 }
             }
         }
@@ -706,6 +822,21 @@ impl<K: PartialEq> Scrollable for State<K> {
     }
 }
 
+/// Focusing anything else, like a text input, stops Home and End from scrolling
+impl<K> Focusable for State<K> {
+    fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    fn focus(&mut self) {
+        self.focused = true;
+    }
+
+    fn unfocus(&mut self) {
+        self.focused = false;
+    }
+}
+
 impl<'a, M, T, R, K> From<Scrollie<'a, M, T, R, K>> for Element<'a, M, T, R>
 where
     M: 'a,
@@ -0,0 +1,320 @@
+use iced::{
+    Border, Element, Event, Length, Rectangle, Shadow, Size, Vector,
+    advanced::{
+        Layout, Renderer, Widget,
+        layout::{Limits, Node},
+        overlay,
+        renderer::Quad,
+        widget::{Operation, Tree, tree},
+    },
+    mouse, theme,
+    theme::palette::Extended,
+    touch,
+};
+
+/// Two widgets stacked vertically with a draggable divider between them
+pub struct Splitter<'a, M, T, R> {
+    children: [Element<'a, M, T, R>; 2],
+    /// Fraction of the available height given to the top pane
+    ratio: f32,
+    min_size: f32,
+    on_resize: Option<Box<dyn Fn(f32) -> M + 'a>>,
+    on_resize_end: Option<M>,
+}
+
+impl<'a, M, T, R> Splitter<'a, M, T, R> {
+    const HANDLE_HEIGHT: f32 = 6.0;
+
+    pub fn new(
+        top: impl Into<Element<'a, M, T, R>>,
+        bottom: impl Into<Element<'a, M, T, R>>,
+        ratio: f32,
+    ) -> Self {
+        Self {
+            children: [top.into(), bottom.into()],
+            ratio,
+            min_size: 40.0,
+            on_resize: None,
+            on_resize_end: None,
+        }
+    }
+
+    /// Minimum height of each pane
+    pub fn min_size(mut self, min_size: f32) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// Published with the new ratio while the divider is being dragged
+    pub fn on_resize(mut self, on_resize: impl Fn(f32) -> M + 'a) -> Self {
+        self.on_resize = Some(Box::new(on_resize));
+        self
+    }
+
+    /// Published once the divider is released
+    pub fn on_resize_end(mut self, on_resize_end: M) -> Self {
+        self.on_resize_end = Some(on_resize_end);
+        self
+    }
+
+    /// Height of the top pane for a total height of `height`
+    fn top_height(&self, height: f32) -> f32 {
+        let available = (height - Self::HANDLE_HEIGHT).max(0.0);
+        let min = self.min_size.min(available / 2.0);
+        (available * self.ratio).clamp(min, available - min)
+    }
+
+    fn handle_bounds(&self, layout: Layout<'_>) -> Rectangle {
+        let bounds = layout.bounds();
+        Rectangle {
+            y: bounds.y + self.top_height(bounds.height),
+            height: Self::HANDLE_HEIGHT,
+            ..bounds
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    dragging: bool,
+}
+
+impl<'a, M, T, R> Widget<M, T, R> for Splitter<'a, M, T, R>
+where
+    M: Clone,
+    T: theme::Base,
+    R: Renderer,
+{
+    fn size(&self) -> Size<Length> {
+        Size::new(Length::Fill, Length::Fill)
+    }
+
+    fn layout(&mut self, tree: &mut Tree, renderer: &R, limits: &Limits) -> Node {
+        let size = limits.max();
+        let top_height = self.top_height(size.height);
+        let bottom_height = (size.height - top_height - Self::HANDLE_HEIGHT).max(0.0);
+
+        let [top, bottom] = &mut self.children;
+        let top = top.as_widget_mut().layout(
+            &mut tree.children[0],
+            renderer,
+            &Limits::new(Size::ZERO, Size::new(size.width, top_height)),
+        );
+        let bottom = bottom
+            .as_widget_mut()
+            .layout(
+                &mut tree.children[1],
+                renderer,
+                &Limits::new(Size::ZERO, Size::new(size.width, bottom_height)),
+            )
+            .move_to([0.0, top_height + Self::HANDLE_HEIGHT]);
+
+        Node::with_children(size, vec![top, bottom])
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut R,
+        theme: &T,
+        style: &iced::advanced::renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        for ((child, state), layout) in self
+            .children
+            .iter()
+            .zip(&tree.children)
+            .zip(layout.children())
+        {
+            child
+                .as_widget()
+                .draw(state, renderer, theme, style, layout, cursor, viewport);
+        }
+
+        let state = tree.state.downcast_ref::<State>();
+        let handle = self.handle_bounds(layout);
+        let active = state.dragging || cursor.is_over(handle);
+        let color = theme
+            .palette()
+            .map(|p| {
+                let e = Extended::generate(p);
+                if active {
+                    e.primary.base.color
+                } else {
+                    e.background.strong.color
+                }
+            })
+            .unwrap_or(theme.base().text_color);
+        let thickness = if active { 2.0 } else { 1.0 };
+
+        renderer.fill_quad(
+            Quad {
+                bounds: Rectangle {
+                    y: handle.center_y() - thickness / 2.0,
+                    height: thickness,
+                    ..handle
+                },
+                border: Border::default(),
+                shadow: Shadow::default(),
+                snap: true,
+            },
+            color,
+        );
+    }
+
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::default())
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        self.children.iter().map(Tree::new).collect()
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(&self.children);
+    }
+
+    fn operate(
+        &mut self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &R,
+        operation: &mut dyn Operation,
+    ) {
+        operation.traverse(&mut |op| {
+            for ((child, state), layout) in self
+                .children
+                .iter_mut()
+                .zip(&mut tree.children)
+                .zip(layout.children())
+            {
+                child.as_widget_mut().operate(state, layout, renderer, op);
+            }
+        });
+    }
+
+    fn update(
+        &mut self,
+        tree: &mut Tree,
+        event: &Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &R,
+        clipboard: &mut dyn iced::advanced::Clipboard,
+        shell: &mut iced::advanced::Shell<'_, M>,
+        viewport: &Rectangle,
+    ) {
+        for ((child, state), layout) in self
+            .children
+            .iter_mut()
+            .zip(&mut tree.children)
+            .zip(layout.children())
+        {
+            child.as_widget_mut().update(
+                state, event, layout, cursor, renderer, clipboard, shell, viewport,
+            );
+        }
+
+        let handle = self.handle_bounds(layout);
+        let state = tree.state.downcast_mut::<State>();
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+            | Event::Touch(touch::Event::FingerPressed { .. }) => {
+                if !shell.is_event_captured() && cursor.is_over(handle) {
+                    state.dragging = true;
+                    shell.capture_event();
+                    shell.request_redraw();
+                }
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+            | Event::Touch(touch::Event::FingerLifted { .. })
+                if state.dragging =>
+            {
+                state.dragging = false;
+                if let Some(msg) = self.on_resize_end.clone() {
+                    shell.publish(msg);
+                }
+                shell.capture_event();
+                shell.request_redraw();
+            }
+            Event::Mouse(mouse::Event::CursorMoved { position })
+            | Event::Touch(touch::Event::FingerMoved { position, .. })
+                if state.dragging =>
+            {
+                let bounds = layout.bounds();
+                let available = bounds.height - Self::HANDLE_HEIGHT;
+                if available <= 0.0 {
+                    return;
+                }
+                let ratio = ((position.y - bounds.y - Self::HANDLE_HEIGHT / 2.0) / available)
+                    .clamp(0.0, 1.0);
+                if let Some(on_resize) = &self.on_resize {
+                    shell.publish(on_resize(ratio));
+                }
+                shell.capture_event();
+            }
+            _ => (),
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &R,
+    ) -> mouse::Interaction {
+        let state = tree.state.downcast_ref::<State>();
+        if state.dragging || cursor.is_over(self.handle_bounds(layout)) {
+            return mouse::Interaction::ResizingVertically;
+        }
+        self.children
+            .iter()
+            .zip(&tree.children)
+            .zip(layout.children())
+            .map(|((child, state), layout)| {
+                child
+                    .as_widget()
+                    .mouse_interaction(state, layout, cursor, viewport, renderer)
+            })
+            .max()
+            .unwrap_or_default()
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'b>,
+        renderer: &R,
+        viewport: &Rectangle,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, M, T, R>> {
+        overlay::from_children(
+            &mut self.children,
+            tree,
+            layout,
+            renderer,
+            viewport,
+            translation,
+        )
+    }
+}
+
+impl<'a, M, T, R> From<Splitter<'a, M, T, R>> for Element<'a, M, T, R>
+where
+    M: Clone + 'a,
+    T: theme::Base + 'a,
+    R: Renderer + 'a,
+{
+    fn from(value: Splitter<'a, M, T, R>) -> Self {
+        Element::new(value)
+    }
+}
@@ -31,6 +31,8 @@ where
     cross: Svg<'a, T>,
     label: Text<'static, T, R>,
     active: bool,
+    /// Whether the channel is streaming, [None] if unknown
+    live: Option<bool>,
     on_click: Option<M>,
     on_double_click: Option<M>,
     on_close: Option<M>,
@@ -47,8 +49,7 @@ where
         let close_button = Svg::new(CROSS_SVG.clone())
             .width(10)
             .height(10)
-            .style(|_, _| {
-                let t = <T as theme::Base>::default(theme::Mode::Dark);
+            .style(|t: &T, _| {
                 let col = t
                     .palette()
                     .map(|p| Extended::generate(p).secondary.base.color)
@@ -60,6 +61,7 @@ where
             id,
             cross: close_button,
             active: false,
+            live: None,
             on_click: None,
             on_double_click: None,
             on_close: None,
@@ -81,12 +83,21 @@ where
         self
     }
 
+    /// Shows a dot while live and dims the label while offline
+    pub fn live(mut self, live: Option<bool>) -> Self {
+        self.live = live;
+        self
+    }
+
     pub fn set_active(mut self) -> Self {
         self.active = true;
         self
     }
 }
 
+const LIVE_DOT: f32 = 6.0;
+const LIVE_COLOR: Color = Color::from_rgb8(0xe9, 0x19, 0x16);
+
 fn tab_background(palette: Palette) -> (Color, Color) {
     let e = Extended::generate(palette);
     (e.background.strong.color, e.background.strongest.color)
@@ -134,14 +145,19 @@ where
             text.translate_mut([0.0, (close.bounds().height - text.bounds().height) / 2.0]);
         }
 
-        text.translate_mut([10.0, 6.0]);
-        close.translate_mut([10.0, 6.0]);
+        let dot = if self.live == Some(true) {
+            LIVE_DOT + 4.0
+        } else {
+            0.0
+        };
+        text.translate_mut([10.0 + dot, 6.0]);
+        close.translate_mut([10.0 + dot, 6.0]);
 
         Node::with_children(
             text.bounds()
                 .union(&close.bounds())
                 .size()
-                .expand([20.0, 12.0]),
+                .expand([20.0 + dot, 12.0]),
             vec![text, close],
         )
     }
@@ -188,10 +204,42 @@ where
             bg,
         );
 
-        if cursor
+        let label_style = if self.live == Some(false) {
+            iced::advanced::renderer::Style {
+                text_color: style.text_color.scale_alpha(0.5),
+            }
+        } else {
+            *style
+        };
+        let style = &label_style;
+
+        let hovered = cursor
             .position()
-            .is_some_and(|p| layout.bounds().contains(p))
-        {
+            .is_some_and(|p| layout.bounds().contains(p));
+        if self.live == Some(true) {
+            let label = layout.child(0).bounds();
+            let shift = if hovered {
+                0.0
+            } else {
+                layout.child(1).bounds().width / 2.0 + 2.0
+            };
+            renderer.fill_quad(
+                Quad {
+                    bounds: Rectangle {
+                        x: label.x - LIVE_DOT - 4.0 + shift,
+                        y: label.center_y() - LIVE_DOT / 2.0,
+                        width: LIVE_DOT,
+                        height: LIVE_DOT,
+                    },
+                    border: Border::default().rounded(LIVE_DOT / 2.0),
+                    shadow: Shadow::default(),
+                    snap: false,
+                },
+                LIVE_COLOR,
+            );
+        }
+
+        if hovered {
             <Text<_, _> as Widget<M, _, _>>::draw(
                 &self.label,
                 &tree.children[0],
@@ -1,10 +1,11 @@
 use std::{fmt::Display, sync::LazyLock};
 
 use iced::{
-    Alignment, Color, Element, Event, Length, Padding, Rectangle, Size,
+    Alignment, Border, Color, Element, Event, Length, Padding, Point, Rectangle, Shadow, Size,
     advanced::{
         Renderer, Widget,
-        layout::Node,
+        layout::{Limits, Node},
+        renderer::Quad,
         svg::Renderer as SvgRenderer,
         text::Renderer as TextRenderer,
         widget::{
@@ -12,8 +13,9 @@ use iced::{
             tree::{self, Tag},
         },
     },
+    keyboard,
     mouse::{self, Interaction},
-    theme,
+    theme::{self, palette::Extended},
     widget::{
         Id, Row,
         button::Catalog as ButtonCatalog,
@@ -35,14 +37,28 @@ pub struct Tabs<'a, M, T, R, TabId> {
     row: Wrapping<'a, M, T, R>,
     tabs: Vec<(TabId, Element<'a, M, T, R>)>,
     fallback: Option<Element<'a, M, T, R>>,
+    /// Keeps all tabs on one horizontally scrollable row instead of wrapping
+    single_row: bool,
+    overflow_button: Element<'a, M, T, R>,
+    /// One entry per tab, shown in the overflow menu when that tab is hidden
+    overflow_entries: Vec<Element<'a, M, T, R>>,
     on_add: Option<M>,
     on_close: Option<Box<dyn Fn(TabId) -> M>>,
+    on_select: Option<Box<dyn Fn(TabId) -> M>>,
     on_reorder: Option<Box<dyn Fn(usize, usize) -> M>>,
 }
 
 #[derive(Debug)]
 pub struct State<TabId: Clone + Eq> {
     selected: Option<TabId>,
+    /// The tab that was last scrolled into view in single row mode
+    revealed: Option<TabId>,
+    scroll_offset: f32,
+    visible_width: f32,
+    overflowing: bool,
+    /// Indices of the tabs that are scrolled out of view
+    hidden: Vec<usize>,
+    overflow_open: bool,
 }
 
 impl<TabId: Clone + Eq> State<TabId> {
@@ -60,28 +76,41 @@ where
     TabId: Clone + Eq + Display + 'a,
 {
     pub fn new(tabs: impl IntoIterator<Item = (TabId, impl Into<Element<'a, M, T, R>>)>) -> Self {
+        Self::with_live(tabs.into_iter().map(|(id, e)| (id, None, e)))
+    }
+
+    /// Tabs along with whether their channel is live, if known
+    pub fn with_live(
+        tabs: impl IntoIterator<Item = (TabId, Option<bool>, impl Into<Element<'a, M, T, R>>)>,
+    ) -> Self {
         let mut row = Row::new()
             .spacing(2)
             .width(Length::Fill)
             .align_y(Alignment::Center);
         let mut tabs_vec = Vec::<(TabId, Element<'a, M, T, R>)>::new();
-        for c in tabs {
-            row = row.push(Tab::new(c.0.clone()));
-            tabs_vec.push((c.0, c.1.into()));
+        let mut overflow_entries = Vec::new();
+        for (id, live, elem) in tabs {
+            row = row.push(Tab::new(id.clone()).live(live));
+            overflow_entries.push(Tab::new(id.clone()).live(live).into());
+            tabs_vec.push((id, elem.into()));
         }
         row = row.push(
             IconButton::new(svg::Svg::new(ICON.clone()))
                 .size(24)
                 .padding(Padding::new(7.0))
-                .color(Color::WHITE),
+                .color(|theme: &T| theme.palette().map_or(Color::WHITE, |p| p.text)),
         );
         Self {
             id: None,
             row: row.wrap(),
             tabs: tabs_vec,
             fallback: None,
+            single_row: false,
+            overflow_button: iced::widget::Text::new("»").size(16).into(),
+            overflow_entries,
             on_add: None,
             on_close: None,
+            on_select: None,
             on_reorder: None,
         }
     }
@@ -96,6 +125,11 @@ where
         self
     }
 
+    pub fn single_row(mut self, single_row: bool) -> Self {
+        self.single_row = single_row;
+        self
+    }
+
     pub fn on_add(mut self, msg: M) -> Self {
         self.on_add = Some(msg);
         self
@@ -106,11 +140,22 @@ where
         self
     }
 
+    pub fn on_select(mut self, msg: impl Fn(TabId) -> M + 'static) -> Self {
+        self.on_select = Some(Box::new(msg));
+        self
+    }
+
     pub fn on_reorder(mut self, msg: impl Fn(usize, usize) -> M + 'static) -> Self {
         self.on_reorder = Some(Box::new(msg));
         self
     }
 
+    /// Index of the overflow button's tree, followed by the overflow entries
+    fn overflow_offset(&self) -> usize {
+        let offset = if self.fallback.is_some() { 2 } else { 1 };
+        offset + self.tabs.len()
+    }
+
     #[allow(clippy::type_complexity)]
     fn get_active(&self, state: &State<TabId>) -> Option<(usize, &(TabId, Element<'a, M, T, R>))> {
         if let Some(selected) = &state.selected {
@@ -136,6 +181,265 @@ where
     }
 }
 
+impl<'a, M, T, R, TabId> Tabs<'a, M, T, R, TabId>
+where
+    M: Clone + 'a,
+    R: Renderer + TextRenderer + SvgRenderer + 'a,
+    T: TextCatalog + ButtonCatalog + SvgCatalog + theme::Base + 'a,
+    TabId: Clone + Eq + Display + 'static,
+{
+    /// Scrolls the single row of tabs so the selected one is visible and lays
+    /// out the overflow button and the menu listing the hidden tabs
+    fn layout_overflow(
+        &mut self,
+        tree: &mut Tree,
+        row_node: &mut Node,
+        renderer: &R,
+        available: f32,
+    ) -> (Node, Node) {
+        const SPACING: f32 = 2.0;
+
+        let offset = self.overflow_offset();
+        let state = tree.state.downcast_mut::<State<TabId>>();
+
+        let text = self.overflow_button.as_widget_mut().layout(
+            &mut tree.children[offset],
+            renderer,
+            &Limits::new(Size::ZERO, Size::INFINITY),
+        );
+        let button_size = text.bounds().size().expand([16.0, 12.0]);
+        let button = Node::with_children(button_size, vec![text.move_to([8.0, 6.0])])
+            .move_to([available - button_size.width, 0.0]);
+
+        let tab_bounds: Vec<Rectangle> = row_node.children().iter().map(Node::bounds).collect();
+        let content_width = tab_bounds.last().map(|b| b.x + b.width).unwrap_or_default();
+
+        state.overflowing = content_width > available;
+        if !state.overflowing {
+            state.scroll_offset = 0.0;
+            state.visible_width = available;
+            state.hidden.clear();
+            state.overflow_open = false;
+            return (button, Node::default());
+        }
+
+        let visible = (available - button_size.width - SPACING).max(0.0);
+        state.visible_width = visible;
+
+        if state.revealed != state.selected {
+            let selected = self
+                .tabs
+                .iter()
+                .position(|t| Some(&t.0) == state.selected.as_ref());
+            if let Some(b) = selected.and_then(|i| tab_bounds.get(i)) {
+                if b.x + b.width > state.scroll_offset + visible {
+                    state.scroll_offset = b.x + b.width - visible;
+                }
+                if b.x < state.scroll_offset {
+                    state.scroll_offset = b.x;
+                }
+            }
+            state.revealed = state.selected.clone();
+        }
+        state.scroll_offset = state
+            .scroll_offset
+            .clamp(0.0, (content_width - visible).max(0.0));
+
+        row_node.translate_mut([-state.scroll_offset, 0.0]);
+
+        state.hidden = tab_bounds
+            .iter()
+            .take(self.tabs.len())
+            .enumerate()
+            .filter(|(_, b)| {
+                b.x < state.scroll_offset - 0.5
+                    || b.x + b.width > state.scroll_offset + visible + 0.5
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        let mut y = 4.0;
+        let mut width = 0.0f32;
+        let mut entries = Vec::with_capacity(state.hidden.len());
+        for &i in &state.hidden {
+            let entry = self.overflow_entries[i].as_widget_mut().layout(
+                &mut tree.children[offset + 1 + i],
+                renderer,
+                &Limits::new(Size::ZERO, Size::INFINITY),
+            );
+            let height = entry.bounds().height;
+            width = width.max(entry.bounds().width);
+            entries.push(entry.move_to([4.0, y]));
+            y += height + SPACING;
+        }
+        let menu_size = Size::new(width + 8.0, y - SPACING + 4.0);
+        let menu = Node::with_children(menu_size, entries)
+            .move_to([available - menu_size.width, row_node.bounds().height]);
+
+        (button, menu)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_overflow(
+        &self,
+        tree: &Tree,
+        renderer: &mut R,
+        theme: &T,
+        style: &iced::advanced::renderer::Style,
+        layout: iced::advanced::Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        let state = tree.state.downcast_ref::<State<TabId>>();
+        let offset = self.overflow_offset();
+        let mut overflow = layout.children().skip(2);
+        let (Some(button), Some(menu)) = (overflow.next(), overflow.next()) else {
+            return;
+        };
+
+        let (base, strong) = theme
+            .palette()
+            .map(|p| {
+                let e = Extended::generate(p);
+                (e.background.strong.color, e.background.strongest.color)
+            })
+            .unwrap_or_default();
+
+        let hovered = cursor.position_over(button.bounds()).is_some();
+        renderer.fill_quad(
+            Quad {
+                bounds: button.bounds(),
+                border: Border::default().rounded(6.0),
+                shadow: Shadow::default(),
+                snap: false,
+            },
+            if hovered || state.overflow_open {
+                strong
+            } else {
+                base
+            },
+        );
+        self.overflow_button.as_widget().draw(
+            &tree.children[offset],
+            renderer,
+            theme,
+            style,
+            button.child(0),
+            cursor,
+            viewport,
+        );
+
+        if !state.overflow_open || state.hidden.is_empty() {
+            return;
+        }
+
+        renderer.with_layer(menu.bounds(), |r| {
+            r.fill_quad(
+                Quad {
+                    bounds: menu.bounds(),
+                    border: Border::default().rounded(6.0),
+                    shadow: Shadow::default(),
+                    snap: false,
+                },
+                base,
+            );
+            for (&i, entry) in state.hidden.iter().zip(menu.children()) {
+                self.overflow_entries[i].as_widget().draw(
+                    &tree.children[offset + 1 + i],
+                    r,
+                    theme,
+                    style,
+                    entry,
+                    cursor,
+                    viewport,
+                );
+            }
+        });
+    }
+
+    /// Handles the overflow button and menu, returning whether the event was
+    /// consumed by them
+    fn update_overflow(
+        &self,
+        state: &mut State<TabId>,
+        event: &Event,
+        layout: iced::advanced::Layout<'_>,
+        cursor: mouse::Cursor,
+        shell: &mut iced::advanced::Shell<'_, M>,
+    ) -> bool {
+        let mut overflow = layout.children().skip(2);
+        let (Some(button), Some(menu)) = (overflow.next(), overflow.next()) else {
+            return false;
+        };
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                let Some(pos) = cursor.position() else {
+                    return false;
+                };
+                if button.bounds().contains(pos) {
+                    state.overflow_open = !state.overflow_open;
+                    shell.request_redraw();
+                    shell.capture_event();
+                    return true;
+                }
+                if !state.overflow_open {
+                    return false;
+                }
+
+                state.overflow_open = false;
+                shell.request_redraw();
+                let clicked = state
+                    .hidden
+                    .iter()
+                    .zip(menu.children())
+                    .find(|(_, e)| e.bounds().contains(pos))
+                    .map(|(i, _)| *i);
+                if let Some(idx) = clicked {
+                    let new_selected = self.tabs[idx].0.clone();
+                    if state.selected.as_ref() != Some(&new_selected)
+                        && let Some(on_select) = &self.on_select
+                    {
+                        shell.publish(on_select(new_selected.clone()));
+                    }
+                    state.selected = Some(new_selected);
+                    shell.invalidate_layout();
+                    shell.capture_event();
+                    return true;
+                }
+                false
+            }
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key: keyboard::Key::Named(keyboard::key::Named::Escape),
+                ..
+            }) if state.overflow_open => {
+                state.overflow_open = false;
+                shell.request_redraw();
+                shell.capture_event();
+                true
+            }
+            Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                let row_height = layout.child(0).bounds().height;
+                let over_row = cursor.position().is_some_and(|p| {
+                    layout.bounds().contains(p) && p.y < layout.bounds().y + row_height
+                });
+                if !over_row {
+                    return false;
+                }
+                state.scroll_offset -= match delta {
+                    mouse::ScrollDelta::Lines { x, y } => (x + y) * 40.0,
+                    mouse::ScrollDelta::Pixels { x, y } => x + y,
+                };
+                shell.invalidate_layout();
+                shell.request_redraw();
+                shell.capture_event();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
 impl<'a, M, T, R, TabId> Widget<M, T, R> for Tabs<'a, M, T, R, TabId>
 where
     M: Clone + 'a,
@@ -159,11 +463,23 @@ where
     ) -> iced::advanced::layout::Node {
         let span = iced::debug::time("tabs layout");
         let limits = limits.width(Length::Fill);
-        let row_node = self.row.layout(
-            &mut tree.children[0],
-            renderer,
-            &limits.height(Length::Shrink).loose(),
-        );
+        let mut row_node = if self.single_row {
+            self.row.layout(
+                &mut tree.children[0],
+                renderer,
+                &Limits::new(Size::ZERO, Size::new(f32::INFINITY, f32::INFINITY)),
+            )
+        } else {
+            self.row.layout(
+                &mut tree.children[0],
+                renderer,
+                &limits.height(Length::Shrink).loose(),
+            )
+        };
+
+        let overflow_nodes = self
+            .single_row
+            .then(|| self.layout_overflow(tree, &mut row_node, renderer, limits.max().width));
 
         let mut children = Vec::with_capacity(2);
         let bounds = row_node.bounds();
@@ -175,7 +491,7 @@ where
                 active
                     .as_widget_mut()
                     .layout(tree, renderer, &limits.shrink([0.0, bounds.height]));
-            shown_node = shown_node.move_to([bounds.x, bounds.y + bounds.height]);
+            shown_node = shown_node.move_to([0.0, bounds.y + bounds.height]);
             children.push(shown_node);
         } else if let Some(fallback) = &mut self.fallback
             && self.tabs.is_empty()
@@ -185,7 +501,14 @@ where
                 renderer,
                 &limits,
             ));
+        } else if overflow_nodes.is_some() {
+            // keeps the overflow nodes at a fixed position
+            children.push(Node::default());
         };
+        if let Some((button, menu)) = overflow_nodes {
+            children.push(button);
+            children.push(menu);
+        }
         span.finish();
         Node::with_children(limits.max(), children)
     }
@@ -200,15 +523,36 @@ where
         cursor: iced::advanced::mouse::Cursor,
         viewport: &iced::Rectangle,
     ) {
-        self.row.draw(
-            &tree.children[0],
-            renderer,
-            theme,
-            style,
-            layout.child(0),
-            cursor,
-            viewport,
-        );
+        let state = tree.state.downcast_ref::<State<TabId>>();
+        if self.single_row {
+            let bounds = layout.bounds();
+            let clip = Rectangle {
+                width: state.visible_width,
+                height: layout.child(0).bounds().height,
+                ..bounds
+            };
+            renderer.with_layer(clip, |r| {
+                self.row.draw(
+                    &tree.children[0],
+                    r,
+                    theme,
+                    style,
+                    layout.child(0),
+                    cursor,
+                    &clip,
+                );
+            });
+        } else {
+            self.row.draw(
+                &tree.children[0],
+                renderer,
+                theme,
+                style,
+                layout.child(0),
+                cursor,
+                viewport,
+            );
+        }
         if let Some(layout) = layout.children().nth(1) {
             if let Some((i, (_, active))) = self.get_active(tree.state.downcast_ref()) {
                 let offset = if self.fallback.is_some() { 2 } else { 1 };
@@ -229,6 +573,9 @@ where
                 )
             };
         }
+        if self.single_row && state.overflowing {
+            self.draw_overflow(tree, renderer, theme, style, layout, cursor, viewport);
+        }
     }
 
     fn update(
@@ -250,16 +597,30 @@ where
                 .any(|t| Some(&t.0) == state.selected.as_ref())
         {
             state.selected = self.tabs.first().map(|t| t.0.clone());
-            if state.selected.is_some() {
+            if let Some(selected) = &state.selected {
                 shell.invalidate_layout();
                 shell.request_redraw();
+                if let Some(on_select) = &self.on_select {
+                    shell.publish(on_select(selected.clone()));
+                }
             }
         }
 
+        if self.single_row
+            && state.overflowing
+            && self.update_overflow(state, event, layout, cursor, shell)
+        {
+            return;
+        }
+
+        let in_row =
+            |pos: Point| !self.single_row || pos.x < layout.bounds().x + state.visible_width;
+
         let click = if matches!(
             event,
             Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
         ) && let Some(pos) = cursor.position()
+            && in_row(pos)
         {
             layout
                 .child(0)
@@ -290,9 +651,12 @@ where
             } else if idx < self.tabs.len() {
                 let new_selected = self.tabs[idx].0.clone();
 
-                if state.selected.as_ref().is_some_and(|s| s != &new_selected) {
+                if state.selected.as_ref() != Some(&new_selected) {
                     shell.invalidate_layout();
                     shell.request_redraw();
+                    if let Some(on_select) = &self.on_select {
+                        shell.publish(on_select(new_selected.clone()));
+                    }
                 }
 
                 state.selected = Some(new_selected);
@@ -348,12 +712,20 @@ where
             children.push(fallback.as_widget());
         }
         children.extend(self.tabs.iter().map(|t| t.1.as_widget()));
+        children.push(self.overflow_button.as_widget());
+        children.extend(self.overflow_entries.iter().map(|e| e.as_widget()));
         tree.diff_children(children.as_slice());
     }
 
     fn state(&self) -> iced::advanced::widget::tree::State {
         tree::State::new(State::<TabId> {
             selected: self.tabs.first().map(|f| f.0.clone()),
+            revealed: None,
+            scroll_offset: 0.0,
+            visible_width: 0.0,
+            overflowing: false,
+            hidden: Vec::new(),
+            overflow_open: false,
         })
     }
 
@@ -410,6 +782,19 @@ where
         if !matches!(row_inter, Interaction::None) {
             return row_inter;
         }
+        let state = tree.state.downcast_ref::<State<TabId>>();
+        if self.single_row
+            && state.overflowing
+            && let Some(pos) = cursor.position()
+        {
+            let mut overflow = layout.children().skip(2);
+            let over_button = overflow.next().is_some_and(|b| b.bounds().contains(pos));
+            let over_menu =
+                state.overflow_open && overflow.next().is_some_and(|m| m.bounds().contains(pos));
+            if over_button || over_menu {
+                return Interaction::Pointer;
+            }
+        }
         if let Some((i, (_, active))) = self.get_active(tree.state.downcast_ref())
             && let Some(layout) = layout.children().nth(1)
         {
@@ -431,6 +816,12 @@ where
             children.push(Tree::new(fallback.as_widget()));
         }
         children.extend(self.tabs.iter().map(|t| Tree::new(t.1.as_widget())));
+        children.push(Tree::new(self.overflow_button.as_widget()));
+        children.extend(
+            self.overflow_entries
+                .iter()
+                .map(|e| Tree::new(e.as_widget())),
+        );
         children
     }
 
@@ -470,3 +861,51 @@ where
         Element::new(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::{
+        Point, Theme,
+        advanced::{Layout, Shell, clipboard, layout::Limits, mouse::Cursor},
+        widget::space,
+    };
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Msg {
+        Selected(String),
+    }
+
+    fn tabs() -> Tabs<'static, Msg, Theme, (), String> {
+        Tabs::new(["a", "b", "c"].map(|t| (t.to_owned(), space()))).on_select(Msg::Selected)
+    }
+
+    #[test]
+    fn clicking_a_tab_publishes_on_select_once() {
+        let mut tabs = tabs();
+        let mut tree = Tree::new(&tabs as &dyn Widget<Msg, Theme, ()>);
+        let viewport = Rectangle::new(Point::ORIGIN, Size::new(800.0, 600.0));
+        let node = tabs.layout(&mut tree, &(), &Limits::new(Size::ZERO, viewport.size()));
+        let layout = Layout::new(&node);
+
+        // top-left corner of the second tab, away from its close cross
+        let tab_b = layout.child(0).children().nth(1).unwrap().bounds();
+        let cursor = Cursor::Available(Point::new(tab_b.x + 2.0, tab_b.y + 2.0));
+
+        let mut messages = Vec::new();
+        let mut shell = Shell::new(&mut messages);
+        tabs.update(
+            &mut tree,
+            &Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)),
+            layout,
+            cursor,
+            &(),
+            &mut clipboard::Null,
+            &mut shell,
+            &viewport,
+        );
+        drop(shell);
+
+        assert_eq!(messages, vec![Msg::Selected("b".to_owned())]);
+    }
+}